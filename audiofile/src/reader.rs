@@ -44,6 +44,15 @@ pub struct Reader {
 
 impl Reader {
     pub fn new(file_path: &str, sample_rate: usize) -> Result<Reader, failure::Error> {
+        Reader::open(file_path, Some(sample_rate))
+    }
+
+    // Reader keeping the file sample rate
+    pub fn with_file_sample_rate(file_path: &str) -> Result<Reader, failure::Error> {
+        Reader::open(file_path, None)
+    }
+
+    fn open(file_path: &str, sample_rate: Option<usize>) -> Result<Reader, failure::Error> {
         let input_context = format::input(&file_path).unwrap();
         let input_stream = input_context
         .streams()
@@ -66,6 +75,7 @@ impl Reader {
             }
         }
 
+        let sample_rate = sample_rate.unwrap_or(decoder.rate() as usize);
         let sample_format = format::Sample::F32(util::format::sample::Type::Planar);
         let filter = filter(&decoder, sample_format, sample_rate).map_err(|e| failure::err_msg(format!("Filter graph : {}", e)))?;
 
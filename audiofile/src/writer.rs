@@ -1,9 +1,101 @@
 use ffmpeg::{channel_layout, codec, filter, format, frame, util};
 
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum SampleFormat {
+    Auto,
+    Int16,
+    Int24,
+    Int32,
+    Float32,
+}
+
+pub const SAMPLE_FORMATS: [&str; 5] = ["auto", "s16", "s24", "s32", "f32"];
+
+impl SampleFormat {
+    pub fn from_index(index: usize) -> SampleFormat {
+        match index {
+            1 => SampleFormat::Int16,
+            2 => SampleFormat::Int24,
+            3 => SampleFormat::Int32,
+            4 => SampleFormat::Float32,
+            _ => SampleFormat::Auto,
+        }
+    }
+    pub fn index(&self) -> usize {
+        match self {
+            SampleFormat::Auto => 0,
+            SampleFormat::Int16 => 1,
+            SampleFormat::Int24 => 2,
+            SampleFormat::Int32 => 3,
+            SampleFormat::Float32 => 4,
+        }
+    }
+    pub fn from_name(name: &str) -> SampleFormat {
+        SampleFormat::from_index(SAMPLE_FORMATS.iter().position(|n| *n == name).unwrap_or(0))
+    }
+    pub fn name(&self) -> &'static str {
+        SAMPLE_FORMATS[self.index()]
+    }
+
+    fn matches(&self, format: &format::Sample) -> bool {
+        match (self, format) {
+            (SampleFormat::Int16, format::Sample::I16(_)) => true,
+            (SampleFormat::Int24, format::Sample::I32(_)) => true,
+            (SampleFormat::Int32, format::Sample::I32(_)) => true,
+            (SampleFormat::Float32, format::Sample::F32(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Dither {
+    None,
+    Tpdf,
+    NoiseShaped,
+}
+
+pub const DITHERS: [&str; 3] = ["none", "tpdf", "shaped"];
+
+impl Dither {
+    pub fn from_index(index: usize) -> Dither {
+        match index {
+            1 => Dither::Tpdf,
+            2 => Dither::NoiseShaped,
+            _ => Dither::None,
+        }
+    }
+    pub fn index(&self) -> usize {
+        match self {
+            Dither::None => 0,
+            Dither::Tpdf => 1,
+            Dither::NoiseShaped => 2,
+        }
+    }
+    pub fn from_name(name: &str) -> Dither {
+        Dither::from_index(DITHERS.iter().position(|n| *n == name).unwrap_or(0))
+    }
+    pub fn name(&self) -> &'static str {
+        DITHERS[self.index()]
+    }
+}
+
+// The dither is applied by the resampler when it converts the float samples to the encoder integer format.
+// Where no noise shaping filter exists for the sample rate, swresample falls back to a high pass triangular dither.
+fn dither_options(dither: Dither, encoder_format: &format::Sample, sample_bits: usize) -> String {
+    let method = match (dither, encoder_format) {
+        (_, format::Sample::F32(_)) | (_, format::Sample::F64(_)) => return String::new(),
+        (Dither::None, _) => return String::new(),
+        (Dither::Tpdf, _) => "triangular",
+        (Dither::NoiseShaped, _) => "shibata",
+    };
+    format!(":dither_method={}:output_sample_bits={}", method, sample_bits)
+}
 
 fn filter(sample_format: &format::Sample, in_sample_rate: usize,
     channel_layout: &channel_layout::ChannelLayout,
     encoder: &codec::encoder::Audio,
+    resample_options: &str,
 ) -> Result<filter::Graph, ffmpeg::Error> {
     let mut filter = filter::Graph::new();
 
@@ -20,8 +112,8 @@ fn filter(sample_format: &format::Sample, in_sample_rate: usize,
         out.set_sample_rate(encoder.rate());
     }
 
-    let conversion_spec = format!("aresample={},aformat=sample_fmts={}:channel_layouts=0x{:x}",
-            encoder.rate(), encoder.format().name(), encoder.channel_layout().bits());
+    let conversion_spec = format!("aresample={}{},aformat=sample_fmts={}:channel_layouts=0x{:x}",
+            encoder.rate(), resample_options, encoder.format().name(), encoder.channel_layout().bits());
 
     filter.output("in", 0)?.input("out", 0)?.parse(&conversion_spec)?;
     filter.validate()?;
@@ -53,7 +145,9 @@ pub struct Writer {
 }
 
 impl Writer {
-    pub fn new(codec_name: &str, in_sample_rate: usize, out_sample_rate: usize, channels: usize, file_path: &str,) -> Result<Writer, failure::Error> {
+    pub fn new(codec_name: &str, in_sample_rate: usize, out_sample_rate: usize, channels: usize, file_path: &str,
+        sample_format: SampleFormat, dither: Dither,
+    ) -> Result<Writer, failure::Error> {
 
         let mut output = format::output(&file_path)?;
 
@@ -90,7 +184,27 @@ impl Writer {
             println!("Codec {} ({}) supported format : {}", codec.name(), codec.description(), fmt.name());
         }
 
-        encoder.set_format(codec.formats().expect("unknown supported formats").last().unwrap());
+        let default_format = codec.formats().expect("unknown supported formats").last().unwrap();
+
+        let encoder_format = match codec.formats().expect("unknown supported formats").find(|f| sample_format.matches(f)) {
+            Some(fmt) => fmt,
+            None => {
+                if sample_format != SampleFormat::Auto {
+                    eprintln!("Codec {} does not support sample format {}. Fallback to {}.", codec.name(), sample_format.name(), default_format.name());
+                }
+                default_format
+            }
+        };
+        encoder.set_format(encoder_format);
+
+        let sample_bits = match encoder_format {
+            format::Sample::I32(_) if sample_format == SampleFormat::Int24 => {
+                // 24-bit samples are carried in 32-bit integers
+                unsafe { (*encoder.as_mut_ptr()).bits_per_raw_sample = 24; }
+                24
+            }
+            fmt => fmt.bytes() * 8,
+        };
         encoder.set_bit_rate(96000);
         encoder.set_max_bit_rate(192000);
 
@@ -100,9 +214,10 @@ impl Writer {
         let encoder = encoder.open_as(codec)?;
         stream.set_parameters(&encoder);
 
-        let sample_format = format::Sample::F32(util::format::sample::Type::Planar);
-        let filter = filter(&sample_format, in_sample_rate, &codec_channel_layout, &encoder).map_err(|e| failure::err_msg(format!("Filter graph : {}", e)))?;
-        let mut frame = frame::audio::Audio::new(sample_format, 1024, codec_channel_layout);
+        let in_format = format::Sample::F32(util::format::sample::Type::Planar);
+        let resample_options = dither_options(dither, &encoder_format, sample_bits);
+        let filter = filter(&in_format, in_sample_rate, &codec_channel_layout, &encoder, &resample_options).map_err(|e| failure::err_msg(format!("Filter graph : {}", e)))?;
+        let mut frame = frame::audio::Audio::new(in_format, 1024, codec_channel_layout);
         frame.set_rate(in_sample_rate as u32);
 
         let in_time_base = ffmpeg::Rational::new(1, in_sample_rate as i32);
@@ -115,6 +230,18 @@ impl Writer {
         self.encoder.channels() as usize
     }
 
+    // Container tags written with the header. Empty values are skipped
+    pub fn set_metadata(&mut self, tags: &Vec<(&str, &str)>) {
+        let mut metadata = ffmpeg::Dictionary::new();
+
+        for (key, value) in tags {
+            if !value.is_empty() {
+                metadata.set(key, value);
+            }
+        }
+        self.output.set_metadata(metadata);
+    }
+
     pub fn write_header(&mut self) -> Result<(), failure::Error> {
         self.output.write_header().map_err(|e| failure::err_msg(format!("{}", e)))
     }
@@ -8,9 +8,12 @@ use std::fs::File;
 use std::io::Read;
 
 use session::player::Player;
+use session::settings::Settings;
 use session::state::State;
 
 fn main() {
+    session::plugins_scan::probe_on_request();
+
     let args: Vec<String> = env::args().collect();
     for i in 1..args.len() {
         let filename = &args[i];
@@ -26,7 +29,7 @@ fn main() {
 
 fn play(filename: &str) -> Result<(), failure::Error> {
     let band_description = String::from_utf8(fs::read(filename)?)?;
-    let mut player = Player::new(band_description)?;
+    let mut player = Player::new(band_description, Settings::load())?;
 
     let mut state = player.play()?;
 
@@ -2,7 +2,7 @@ use std::cell::RefCell;
 use std::rc::Rc;
 use std::str::FromStr;
 
-use audiofile::writer::Writer;
+use audiofile::writer::{Dither, SampleFormat, Writer};
 use talker::identifier::RIdentifier;
 
 use crate::audio_data::Vector;
@@ -12,6 +12,108 @@ use crate::output::{Output, ROutput};
 
 pub const MODEL: &str = "file";
 
+const MIX_MODE: &str = "mix";
+const STEMS_MODE: &str = "stems";
+
+const TAGS_PARAMS_INDEX: usize = 7;
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Tags {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub comment: String,
+    pub session: String,
+}
+
+impl Tags {
+    pub fn entries(&self) -> Vec<(&'static str, &str)> {
+        vec![
+            ("title", self.title.as_str()),
+            ("artist", self.artist.as_str()),
+            ("album", self.album.as_str()),
+            ("comment", self.comment.as_str()),
+            ("session", self.session.as_str()),
+        ]
+    }
+
+    pub fn set(&mut self, key: &str, value: String) -> Result<(), failure::Error> {
+        match key {
+            "title" => self.title = value,
+            "artist" => self.artist = value,
+            "album" => self.album = value,
+            "comment" => self.comment = value,
+            "session" => self.session = value,
+            _ => return Err(failure::err_msg(format!("Unknown {} output tag {}", MODEL, key))),
+        }
+        Ok(())
+    }
+
+    fn from_params(params: &[&str]) -> Result<Tags, failure::Error> {
+        let mut tags = Tags::default();
+
+        for param in params.iter().filter(|p| !p.is_empty()) {
+            match param.split_once('=') {
+                Some((key, value)) => tags.set(key, unescape_tag(value))?,
+                None => return Err(failure::err_msg(format!("{} output tag {} has no value", MODEL, param))),
+            }
+        }
+        Ok(tags)
+    }
+
+    fn backup(&self) -> String {
+        let mut conf = String::new();
+
+        for (key, value) in self.entries() {
+            if !value.is_empty() {
+                conf.push_str(&format!("|{}={}", key, escape_tag(value)));
+            }
+        }
+        conf
+    }
+}
+
+// The tags values are kept on the output configuration line without its separator
+fn escape_tag(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\p").replace('\n', "\\n").replace(':', "\\c")
+}
+
+fn unescape_tag(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('p') => result.push('|'),
+                Some('n') => result.push('\n'),
+                Some('c') => result.push(':'),
+                Some(e) => result.push(e),
+                None => (),
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+#[test]
+fn test_tags_backup() {
+    let tags = Tags {
+        title: "A|b:c".to_string(),
+        comment: "line\nnext \\ end".to_string(),
+        ..Tags::default()
+    };
+    let conf = tags.backup();
+    assert!(conf == "|title=A\\pb\\cc|comment=line\\nnext \\\\ end");
+
+    let params: Vec<&str> = conf.split('|').collect();
+    assert!(Tags::from_params(&params).unwrap() == tags);
+
+    assert!(Tags::from_params(&["genre=rock"]).is_err());
+    assert!(Tags::default().set("genre", "rock".to_string()).is_err());
+}
 
 
 pub struct AudioFileOutput {
@@ -21,11 +123,17 @@ pub struct AudioFileOutput {
     out_sample_rate: usize,
     channel_layout: String,
     file_path: String,
+    stems: bool,
+    sample_format: SampleFormat,
+    dither: Dither,
+    tags: Tags,
     writer: Option<Writer>,
 }
 
 impl AudioFileOutput {
-    pub fn new(codec_name: &str, in_sample_rate: usize, out_sample_rate: usize, channel_layout: &str, file_path: &str,) -> Result<AudioFileOutput, failure::Error> {
+    pub fn new(codec_name: &str, in_sample_rate: usize, out_sample_rate: usize, channel_layout: &str, file_path: &str, stems: bool,
+        sample_format: SampleFormat, dither: Dither, tags: Tags,
+    ) -> Result<AudioFileOutput, failure::Error> {
         Ok(Self {
             identifier: output::new_identifier("", MODEL),
             codec_name: codec_name.to_string(),
@@ -33,26 +141,36 @@ impl AudioFileOutput {
             out_sample_rate,
             channel_layout: channel_layout.to_string(),
             file_path: file_path.to_string(),
+            stems,
+            sample_format,
+            dither,
+            tags,
             writer: None,
         })
     }
 
-    pub fn new_ref(codec_name: &str, in_sample_rate: usize, out_sample_rate: usize, channel_layout: &str, file_path: &str,) -> Result<ROutput, failure::Error> {
-        Ok(Rc::new(RefCell::new(AudioFileOutput::new(codec_name, in_sample_rate, out_sample_rate, channel_layout, file_path)?)))
+    pub fn new_ref(codec_name: &str, in_sample_rate: usize, out_sample_rate: usize, channel_layout: &str, file_path: &str, stems: bool,
+        sample_format: SampleFormat, dither: Dither, tags: Tags,
+    ) -> Result<ROutput, failure::Error> {
+        Ok(Rc::new(RefCell::new(AudioFileOutput::new(codec_name, in_sample_rate, out_sample_rate, channel_layout, file_path, stems, sample_format, dither, tags)?)))
     }
 
     pub fn from_backup(in_sample_rate: usize, configuration: &str,) -> Result<ROutput, failure::Error> {
         let params: Vec<&str> = configuration.split('|').collect();
 
-        if params.len() == 4 {
+        if params.len() >= 4 {
             let codec_name = params[0];
             let out_sample_rate = usize::from_str(params[1]).map_err(|e| failure::err_msg(format!("{}", e)))?;
             let channel_layout = params[2];
             let file_path = params[3];
-            AudioFileOutput::new_ref(codec_name, in_sample_rate, out_sample_rate, channel_layout, file_path)
+            let stems = params.len() > 4 && params[4] == STEMS_MODE;
+            let sample_format = if params.len() > 5 { SampleFormat::from_name(params[5]) } else { SampleFormat::Auto };
+            let dither = if params.len() > 6 { Dither::from_name(params[6]) } else { Dither::None };
+            let tags = if params.len() > TAGS_PARAMS_INDEX { Tags::from_params(&params[TAGS_PARAMS_INDEX..])? } else { Tags::default() };
+            AudioFileOutput::new_ref(codec_name, in_sample_rate, out_sample_rate, channel_layout, file_path, stems, sample_format, dither, tags)
         }
         else {
-            Err(failure::err_msg(format!("AudioFileOutput configuration {} need 4 parameters!", configuration)))
+            Err(failure::err_msg(format!("AudioFileOutput configuration {} need at least 4 parameters!", configuration)))
         }
     }
 }
@@ -93,16 +211,34 @@ impl Output for AudioFileOutput {
         &self.file_path
     }
 
+    fn stems(&self) -> bool {
+        self.stems
+    }
+
+    fn sample_format(&self) -> SampleFormat {
+        self.sample_format
+    }
+
+    fn dither(&self) -> Dither {
+        self.dither
+    }
+
+    fn tags(&self) -> Tags {
+        self.tags.clone()
+    }
+
     fn open(&mut self) -> Result<(), failure::Error> {
 
         let channels = channel::Layout::channels(&self.channel_layout);
 
-        let mut writer = Writer::new(self.codec_name.as_str(), self.in_sample_rate, self.out_sample_rate, channels, self.file_path.as_str())?;
+        let mut writer = Writer::new(self.codec_name.as_str(), self.in_sample_rate, self.out_sample_rate, channels, self.file_path.as_str(),
+            self.sample_format, self.dither)?;
 
         if writer.channels() != channels {
             self.channel_layout = channel::Layout::from_channels(writer.channels()).to_string();
         }
 
+        writer.set_metadata(&self.tags.entries());
         writer.write_header()?;
 
         self.writer = Some(writer);
@@ -137,7 +273,9 @@ impl Output for AudioFileOutput {
     }
 
     fn backup(&self) -> (&str, &str, String) {
-        let conf = format!("{}|{}|{}|{}", self.codec_name(), self.out_sample_rate, self.channel_layout, self.file_path());
+        let mode = if self.stems { STEMS_MODE } else { MIX_MODE };
+        let conf = format!("{}|{}|{}|{}|{}|{}|{}{}", self.codec_name(), self.out_sample_rate, self.channel_layout, self.file_path(), mode,
+            self.sample_format.name(), self.dither.name(), self.tags.backup());
         (output::KIND, MODEL, conf)
     }
 }
@@ -21,14 +21,22 @@ use std::fmt::Write as FmtWrite;
 use std::rc::Rc;
 
 use talker::ear::{Ear, Talk};
+use talker::horn::PortType;
 use talker::identifier::{Id, Identifiable, Identifier, Index};
 use talker::talker::RTalker;
+use talker::transport::{self, Transport};
 
+use crate::audio_data::Vector;
 use crate::factory::{Factory, OutputParam};
+use crate::meter::{LoudnessSummary, MixerLevels};
+use crate::midi_mapping::MidiMapping;
 use crate::mixer;
-use crate::mixer::RMixer;
+use crate::mixer::{Mixer, RMixer};
+use crate::output::ROutput;
 use crate::parser;
 use crate::parser::{PMixer, POutput, PTalk, PTalker};
+use crate::talkers::automation::{self, Breakpoint};
+use crate::talkers::lv2;
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum EarHumTalk {
@@ -62,22 +70,45 @@ pub enum Operation {
     AddSetVoiceToEar(Id, Index, Index, Id, Index),
     SupEarSet(Id, Index, Index),
     SetMixerOutputs(Id, Vec<OutputParam>),
+    SetMixerConfiguration(Id, mixer::Configuration),
     SetIndexedData(Id, Index, u32, Vec<u8>),
+    SetLv2Preset(Id, String),
+    SetTempo(f32),
+    SetAutomation(Id, Id, Index, Index, Index, Vec<Breakpoint>),
+    SupAutomation(Id, Index, Index, Index),
+    SetMidiMapping(MidiMapping),
+    SupMidiMapping(Id, Index, Index, Index),
 }
 
 pub struct Band {
     talkers: HashMap<Id, RTalker>,
     mixers: HashMap<Id, RMixer>,
+    tempo: f32,
+    midi_mappings: Vec<MidiMapping>,
+    // Mixers summed to the feedback, updated on graph change
+    feedback_mixers_names: Vec<String>,
+    feedback_mixers_ids: Vec<Id>,
     effective: bool,
 }
 
 pub type RBand = Rc<RefCell<Band>>;
 
+fn has_value_hum(tkr: &RTalker, ear_idx: Index, set_idx: Index, hum_idx: Index) -> bool {
+    tkr.ears().get(ear_idx)
+        .and_then(|ear| ear.sets().get(set_idx))
+        .and_then(|set| set.hums().get(hum_idx))
+        .map_or(false, |hum| hum.can_have_a_value())
+}
+
 impl Band {
     pub fn new(talkers: Option<HashMap<Id, RTalker>>, mixers: Option<HashMap<Id, RMixer>>, effective: bool) -> Band {
         Self {
             talkers: talkers.unwrap_or(HashMap::new()),
             mixers: mixers.unwrap_or(HashMap::new()),
+            tempo: transport::DEFAULT_TEMPO,
+            midi_mappings: Vec::new(),
+            feedback_mixers_names: Vec::new(),
+            feedback_mixers_ids: Vec::new(),
             effective,
         }
     }
@@ -86,6 +117,10 @@ impl Band {
         Self {
             talkers: HashMap::new(),
             mixers: HashMap::new(),
+            tempo: transport::DEFAULT_TEMPO,
+            midi_mappings: Vec::new(),
+            feedback_mixers_names: Vec::new(),
+            feedback_mixers_ids: Vec::new(),
             effective,
         }
     }
@@ -104,14 +139,70 @@ impl Band {
         &self.mixers
     }
 
-    pub fn first_mixer(&self) -> &RMixer {
-        self.mixers.values().next().unwrap()
-    }
-
     pub fn find_mixer(&self, mixer_id: Id) -> Option<&RMixer> {
         self.mixers.get(&mixer_id)
     }
 
+    pub fn set_feedback_mixers(&mut self, names: &Vec<String>) {
+        self.feedback_mixers_names = names.clone();
+        self.update_feedback_mixers_ids();
+    }
+
+    pub fn feedback_mixers_ids(&self) -> &Vec<Id> {
+        &self.feedback_mixers_ids
+    }
+
+    // Mixers named in the feedback settings or, failing that, the mixer with the lowest id
+    fn update_feedback_mixers_ids(&mut self) {
+        let names = &self.feedback_mixers_names;
+        let ids = &mut self.feedback_mixers_ids;
+
+        ids.clear();
+        ids.extend(self.mixers.iter().filter(|(_, m)| names.contains(&m.borrow().name())).map(|(id, _)| *id));
+
+        if ids.is_empty() {
+            if let Some(id) = self.mixers.keys().min() {
+                ids.push(*id);
+            }
+        }
+        ids.sort();
+    }
+
+    // Sum of the feedback mixers channels or, when audible is set, of their audible tracks.
+    // A mixer with less channels than the sum repeats its last channel.
+    pub fn feedback_channels(&self, audible: bool, channels: &mut Vec<Vector>, len: usize) {
+        let channels_count = self.feedback_mixers_ids.iter()
+            .filter_map(|id| self.mixers.get(id))
+            .map(|m| m.borrow().channels())
+            .max()
+            .unwrap_or(1);
+
+        channels.resize(channels_count, Vec::new());
+
+        for ch in channels.iter_mut() {
+            ch.clear();
+            ch.resize(len, 0.);
+        }
+
+        for rmixer in self.feedback_mixers_ids.iter().filter_map(|id| self.mixers.get(id)) {
+            let mixer = rmixer.borrow();
+            let mixer_channels = if audible { mixer.feedback() } else { mixer.channels_buffers() };
+
+            if mixer_channels.is_empty() {
+                continue;
+            }
+            let last_mixer_chan = mixer_channels.len() - 1;
+
+            for (chan_idx, ch) in channels.iter_mut().enumerate() {
+                let mixer_chan = &mixer_channels[chan_idx.min(last_mixer_chan)];
+
+                for i in 0..len.min(mixer_chan.len()) {
+                    ch[i] += mixer_chan[i];
+                }
+            }
+        }
+    }
+
     fn set_talker_ears<'a>(
         &'a mut self,
         talkers_ptalkers: &mut HashMap<Id, (RTalker, &PTalker)>,
@@ -192,16 +283,32 @@ impl Band {
             }
         }
 
-        Factory::make_mixer(pmixer.talker.id, pmixer.talker.name, None, outputs)
+        let configuration = match pmixer.talker.data {
+            Some(backup) => mixer::Configuration::from_backup(backup)?,
+            None => mixer::Configuration::default(),
+        };
+
+        Factory::make_mixer(pmixer.talker.id, pmixer.talker.name, None, outputs, configuration)
     }
 
     pub fn build(factory: &Factory, source: &String, effective: bool) -> Result<Band, failure::Error> {
         Identifier::initialize_id_count();
         let mut band = Band::empty(effective);
 
-        let (ptalkers, pmixers, poutputs) = parser::parse(&source)?;
+        let (ptalkers, pmixers, poutputs, tempo, midi_mappings) = parser::parse(&source)?;
+
+        band.set_tempo(tempo.unwrap_or(transport::DEFAULT_TEMPO));
+        band.midi_mappings = midi_mappings;
 
         let mut talkers_ptalkers = HashMap::new();
+        let mut mixers = Vec::with_capacity(pmixers.len());
+
+        // The mixers are made first for their buses voices to be available to the talkers
+        for pmixer in pmixers.values() {
+            let rmixer = band.make_mixer(&poutputs, &pmixer)?;
+            band.talkers.insert(pmixer.talker.id, rmixer.borrow().talker().clone());
+            mixers.push((rmixer, pmixer));
+        }
 
         for ptalker in ptalkers.values() {
             let mut talker =
@@ -232,9 +339,7 @@ impl Band {
             }
         }
 
-        for pmixer in pmixers.values() {
-            let rmixer = band.make_mixer(&poutputs, &pmixer)?;
-
+        for (rmixer, pmixer) in mixers {
             band.set_talker_ears(
                 &mut talkers_ptalkers,
                 rmixer.borrow_mut().talker().clone(),
@@ -243,6 +348,7 @@ impl Band {
             rmixer.borrow_mut().initialize();
             band.mixers.insert(pmixer.talker.id, rmixer);
         }
+        band.graph_changed();
 
         Ok(band)
     }
@@ -280,6 +386,14 @@ impl Band {
     pub fn serialize(&self) -> Result<String, failure::Error> {
         let mut buf = String::new();
 
+        if self.tempo != transport::DEFAULT_TEMPO {
+            writeln!(buf, "\n{}{}", parser::TEMPO_TAG, self.tempo)?;
+        }
+
+        for mapping in &self.midi_mappings {
+            writeln!(buf, "{}{}", parser::MIDI_MAPPING_TAG, mapping.serialize())?;
+        }
+
         for tkr in self.talkers.values() {
             if tkr.model() != mixer::KIND {
                 let (model, data, ears, state): (String, Option<String>, &Vec<Ear>, Option<String>) = tkr.backup()?;
@@ -307,6 +421,10 @@ impl Band {
             let mixer = rmixer.borrow();
             writeln!(buf, "\n{} {}#{}", mixer::KIND, mixer.id(), &mixer.name())?;
 
+            if let Some(configuration) = mixer.configuration().backup() {
+                writeln!(buf, "[:{}:]", configuration)?;
+            }
+
             for (ear_idx, ear) in mixer.talker().ears().iter().enumerate() {
                 ear.fold_talks(
                     |set_idx, hum_idx, talk_idx, talk, buf| {
@@ -342,8 +460,10 @@ impl Band {
     pub fn add_mixer(&mut self, rmixer: RMixer) {
         let id = rmixer.borrow().id();
         self.talkers.insert(id, rmixer.borrow().talker().clone());
-        
+        rmixer.borrow_mut().graph_changed();
+
         self.mixers.insert(id, rmixer);
+        self.update_feedback_mixers_ids();
     }
 
     pub fn channels(&self) -> usize {
@@ -412,6 +532,17 @@ impl Band {
         }
         self.talkers.remove(talker_id);
 
+        // The MIDI mappings follow the new talker while their hum still exists
+        let new_talker_id = new_talker.id();
+
+        self.midi_mappings.retain_mut(|m| {
+            if m.talker_id != *talker_id {
+                return true;
+            }
+            m.talker_id = new_talker_id;
+            has_value_hum(&new_talker, m.ear_idx, m.set_idx, m.hum_idx)
+        });
+
         for tkr in self.talkers.values() {
             for ear in tkr.ears() {
                 if ear.is_listening_talker(*talker_id) {
@@ -446,7 +577,28 @@ impl Band {
     }
 
     pub fn sup_talker(&mut self, talker_id: &Id) -> Result<(), failure::Error> {
+        // The automations of the talker go with it
+        let mut automations_ids = Vec::new();
+
+        if let Some(tkr) = self.talkers.get(talker_id) {
+            for ear in tkr.ears() {
+                ear.iter_talkers(
+                    |dep, ids: &mut Vec<Id>| {
+                        if dep.model() == automation::MODEL {
+                            ids.push(dep.id());
+                        }
+                        Ok(())
+                    },
+                    &mut automations_ids,
+                )?;
+            }
+        }
+        for automation_id in automations_ids {
+            self.talkers.remove(&automation_id);
+        }
+
         self.talkers.remove(talker_id);
+        self.midi_mappings.retain(|m| m.talker_id != *talker_id);
 
         for tkr in self.talkers.values() {
             for ear in tkr.ears() {
@@ -458,6 +610,73 @@ impl Band {
         Ok(())
     }
 
+    // Automation talker listened by the hum
+    pub fn find_automation(
+        &self,
+        ear_tkr_id: Id,
+        ear_idx: Index,
+        set_idx: Index,
+        hum_idx: Index,
+    ) -> Result<Option<RTalker>, failure::Error> {
+        let ear_tkr = self.fetch_talker(&ear_tkr_id)?;
+        let mut oautomation = None;
+
+        ear_tkr.ear(ear_idx).iter_hum_talks(set_idx, hum_idx, |tlk| {
+            if tlk.talker().model() == automation::MODEL {
+                oautomation = Some(tlk.talker().clone());
+            }
+            Ok(())
+        })?;
+        Ok(oautomation)
+    }
+
+    fn set_automation(
+        &mut self,
+        automation_id: Id,
+        ear_tkr_id: Id,
+        ear_idx: Index,
+        set_idx: Index,
+        hum_idx: Index,
+        points: &Vec<Breakpoint>,
+    ) -> Result<(), failure::Error> {
+        let data = automation::serialize_breakpoints(points);
+
+        if let Some(automation_tkr) = self.find_automation(ear_tkr_id, ear_idx, set_idx, hum_idx)? {
+            automation_tkr.set_data_from_string_update(&data)?;
+            return Ok(());
+        }
+
+        let automation_tkr = self.add_talker(automation::MODEL, Some(automation_id), None)?;
+        automation_tkr.set_data_from_string_update(&data)?;
+
+        let ear_tkr = self.fetch_talker(&ear_tkr_id)?;
+
+        let port = match ear_tkr.ear(ear_idx).sets().get(set_idx).and_then(|set| set.hums().get(hum_idx)) {
+            Some(hum) if hum.port_type() == PortType::Audio => automation::AUDIO_PORT,
+            _ => automation::CV_PORT,
+        };
+        ear_tkr.set_ear_hum_voice(ear_idx, set_idx, hum_idx, &automation_tkr, port)
+    }
+
+    // The hum gets back the automation value at the start
+    fn sup_automation(
+        &mut self,
+        ear_tkr_id: Id,
+        ear_idx: Index,
+        set_idx: Index,
+        hum_idx: Index,
+    ) -> Result<(), failure::Error> {
+        if let Some(automation_tkr) = self.find_automation(ear_tkr_id, ear_idx, set_idx, hum_idx)? {
+            let points = automation::parse_breakpoints(&automation_tkr.data_string().unwrap_or_default())?;
+
+            let ear_tkr = self.fetch_talker(&ear_tkr_id)?;
+            ear_tkr.set_ear_hum_value(ear_idx, set_idx, hum_idx, automation::value_at(&points, 0))?;
+
+            self.talkers.remove(&automation_tkr.id());
+        }
+        Ok(())
+    }
+
     pub fn fetch_talker<'a>(&'a self, talker_id: &Id) -> Result<&'a RTalker, failure::Error> {
         match self.talkers.get(talker_id) {
             Some(tkr) => Ok(tkr),
@@ -470,14 +689,12 @@ impl Band {
             None => Err(failure::err_msg(format!("Talker {} not found!", talker_id))),
         }
     }
-/*
-pub fn fetch_mixer<'a>(&'a self, mixer_id: &Id) -> Result<&'a RMixer, failure::Error> {
-    match self.mixers.get(mixer_id) {
-        Some(mxr) => Ok(mxr),
-        None => Err(failure::err_msg(format!("Mixer {} not found!", mixer_id))),
+    pub fn fetch_mixer<'a>(&'a self, mixer_id: &Id) -> Result<&'a RMixer, failure::Error> {
+        match self.mixers.get(mixer_id) {
+            Some(mxr) => Ok(mxr),
+            None => Err(failure::err_msg(format!("Mixer {} not found!", mixer_id))),
+        }
     }
-}
-*/
     pub fn extract_mixer(&mut self, mixer_id: &Id) -> Result<RMixer, failure::Error> {
         match self.mixers.remove(mixer_id) {
             Some(mxr) => Ok(mxr),
@@ -485,14 +702,17 @@ pub fn fetch_mixer<'a>(&'a self, mixer_id: &Id) -> Result<&'a RMixer, failure::E
         }
     }
 
-    pub fn set_mixer_outputs(&mut self, mixer_id: &Id, outputs_params: &Vec<OutputParam>) -> Result<(), failure::Error> {
+    fn rebuild_mixer(
+        &mut self,
+        mixer_id: &Id,
+        outputs: Vec<ROutput>,
+        configuration: mixer::Configuration,
+    ) -> Result<(), failure::Error> {
         let mixer = self.extract_mixer(mixer_id)?;
         let id = mixer.borrow().id();
         let name = mixer.borrow().name();
 
-        let outputs = Factory::make_outputs(outputs_params)?;
-
-        let updated_mixer = Factory::make_mixer(id, &name, Some(&mixer), outputs)?;
+        let updated_mixer = Factory::make_mixer(id, &name, Some(&mixer), outputs, configuration)?;
 
         updated_mixer.borrow_mut().set_record(mixer.borrow().record())?;
 
@@ -501,17 +721,53 @@ pub fn fetch_mixer<'a>(&'a self, mixer_id: &Id) -> Result<&'a RMixer, failure::E
             updated_mixer.borrow_mut().open()?;
         }
 
-        self.add_mixer(updated_mixer);
+        // The talkers listening the buses have to listen the new mixer talker
+        let updated_talker = updated_mixer.borrow().talker().clone();
+        self.replace_talker(&id, updated_talker)?;
+        self.mixers.insert(id, updated_mixer);
 
         Ok(())
     }
 
+    pub fn set_mixer_outputs(&mut self, mixer_id: &Id, outputs_params: &Vec<OutputParam>) -> Result<(), failure::Error> {
+        let configuration = *self.fetch_mixer(mixer_id)?.borrow().configuration();
+        let outputs = Factory::make_outputs(outputs_params)?;
+
+        self.rebuild_mixer(mixer_id, outputs, configuration)
+    }
+
+    pub fn set_mixer_configuration(
+        &mut self,
+        mixer_id: &Id,
+        configuration: &mixer::Configuration,
+    ) -> Result<(), failure::Error> {
+        let rmixer = self.fetch_mixer(mixer_id)?.clone();
+
+        // Only a change of the buses count or of the panning modifies the mixer ports
+        let current = *rmixer.borrow().configuration();
+
+        if current.buses == configuration.buses && current.panning == configuration.panning {
+            rmixer.borrow_mut().set_summing(configuration.summing, configuration.clipping);
+            Ok(())
+        } else {
+            let outputs = rmixer.borrow().outputs().clone();
+            self.rebuild_mixer(mixer_id, outputs, *configuration)
+        }
+    }
+
     fn check_cyclic_dependency(
         &self,
         voice_talker: &RTalker,
         ear_talker_id: &Id,
+        ear_idx: Index,
+        hum_idx: Index,
     ) -> Result<(), failure::Error> {
-        if voice_talker.is(*ear_talker_id) || voice_talker.depends_of(*ear_talker_id) {
+        // A mixer track input can be fed by a talker listening the buses since the return tracks are not sent to them
+        let is_track_input = self.mixers.contains_key(ear_talker_id)
+            && ear_idx == mixer::TRACKS_EAR_INDEX
+            && hum_idx == mixer::INPUT_HUM_INDEX;
+
+        if voice_talker.is(*ear_talker_id) || (!is_track_input && voice_talker.depends_of(*ear_talker_id)) {
             let ear_tkr = self.fetch_talker(ear_talker_id)?;
 
             Err(failure::err_msg(format!(
@@ -526,7 +782,31 @@ pub fn fetch_mixer<'a>(&'a self, mixer_id: &Id) -> Result<&'a RMixer, failure::E
         }
     }
 
+    // The cached dependencies of the mixers are updated when the graph changes
+    fn graph_changed(&mut self) {
+        for rmixer in self.mixers.values() {
+            rmixer.borrow_mut().graph_changed();
+        }
+        self.update_feedback_mixers_ids();
+    }
+
     pub fn modify(&mut self, operation: &Operation) -> Result<(), failure::Error> {
+        let result = self.apply_operation(operation);
+
+        match operation {
+            Operation::SetEarHumValue(..)
+            | Operation::SetEarTalkValue(..)
+            | Operation::SetIndexedData(..)
+            | Operation::SetTempo(..)
+            | Operation::SetMidiMapping(..)
+            | Operation::SupMidiMapping(..)
+            | Operation::SetLv2Preset(..) => (),
+            _ => self.graph_changed(),
+        }
+        result
+    }
+
+    fn apply_operation(&mut self, operation: &Operation) -> Result<(), failure::Error> {
         let mut result = Ok(());
         match operation {
             Operation::AddTalker(tkr_id, model) => {
@@ -545,7 +825,7 @@ pub fn fetch_mixer<'a>(&'a self, mixer_id: &Id) -> Result<&'a RMixer, failure::E
                 match ear_hum.talks[0] {
                     EarHumTalk::Voice(voice_tkr_id, voice_port) => {
                         let voice_tkr = self.fetch_talker(&voice_tkr_id)?;
-                        self.check_cyclic_dependency(voice_tkr, &ear_tkr_id)?;
+                        self.check_cyclic_dependency(voice_tkr, &ear_tkr_id, ear_hum.ear_idx, ear_hum.hum_idx)?;
 
                         ear_tkr.set_ear_hum_voice(ear_hum.ear_idx, ear_hum.set_idx, ear_hum.hum_idx, &voice_tkr, voice_port)?;
                     },
@@ -556,7 +836,7 @@ pub fn fetch_mixer<'a>(&'a self, mixer_id: &Id) -> Result<&'a RMixer, failure::E
                     match ear_hum.talks[tlk_idx] {
                         EarHumTalk::Voice(voice_tkr_id, voice_port) => {
                             let voice_tkr = self.fetch_talker(&voice_tkr_id)?;
-                            self.check_cyclic_dependency(voice_tkr, &ear_tkr_id)?;
+                            self.check_cyclic_dependency(voice_tkr, &ear_tkr_id, ear_hum.ear_idx, ear_hum.hum_idx)?;
     
                             ear_tkr.add_voice_to_ear_hum(ear_hum.ear_idx, ear_hum.set_idx, ear_hum.hum_idx, &voice_tkr, voice_port)?;
                         },
@@ -573,7 +853,7 @@ pub fn fetch_mixer<'a>(&'a self, mixer_id: &Id) -> Result<&'a RMixer, failure::E
                 voice_port,
             ) => {
                 let voice_tkr = self.fetch_talker(voice_tkr_id)?;
-                self.check_cyclic_dependency(voice_tkr, ear_tkr_id)?;
+                self.check_cyclic_dependency(voice_tkr, ear_tkr_id, *ear_idx, *hum_idx)?;
                 let ear_tkr = self.fetch_talker(ear_tkr_id)?;
 
                 ear_tkr.set_ear_hum_voice(*ear_idx, *set_idx, *hum_idx, &voice_tkr, *voice_port)?;
@@ -593,7 +873,7 @@ pub fn fetch_mixer<'a>(&'a self, mixer_id: &Id) -> Result<&'a RMixer, failure::E
                 voice_port,
             ) => {
                 let voice_tkr = self.fetch_talker(voice_tkr_id)?;
-                self.check_cyclic_dependency(voice_tkr, ear_tkr_id)?;
+                self.check_cyclic_dependency(voice_tkr, ear_tkr_id, *ear_idx, *hum_idx)?;
                 let ear_tkr = self.fetch_talker(ear_tkr_id)?;
 
                 ear_tkr.set_ear_talk_voice(
@@ -624,7 +904,7 @@ pub fn fetch_mixer<'a>(&'a self, mixer_id: &Id) -> Result<&'a RMixer, failure::E
                 voice_port,
             ) => {
                 let voice_tkr = self.fetch_talker(voice_tkr_id)?;
-                self.check_cyclic_dependency(voice_tkr, ear_tkr_id)?;
+                self.check_cyclic_dependency(voice_tkr, ear_tkr_id, *ear_idx, *hum_idx)?;
                 let ear_tkr = self.fetch_talker(ear_tkr_id)?;
 
                 ear_tkr.add_voice_to_ear_hum(
@@ -647,7 +927,7 @@ pub fn fetch_mixer<'a>(&'a self, mixer_id: &Id) -> Result<&'a RMixer, failure::E
             }
             Operation::AddSetVoiceToEar(ear_tkr_id, ear_idx, hum_idx, voice_tkr_id, voice_port) => {
                 let voice_tkr = self.extract_talker(voice_tkr_id)?;
-                self.check_cyclic_dependency(&voice_tkr, ear_tkr_id)?;
+                self.check_cyclic_dependency(&voice_tkr, ear_tkr_id, *ear_idx, *hum_idx)?;
 
                 result = self.update_talker(ear_tkr_id, |tkr| {
                     tkr.add_set_voice_to_ear_update(*ear_idx, *hum_idx, &voice_tkr, *voice_port)
@@ -660,11 +940,35 @@ pub fn fetch_mixer<'a>(&'a self, mixer_id: &Id) -> Result<&'a RMixer, failure::E
             Operation::SetMixerOutputs(mixer_idx, outputs_params) => {
                 self.set_mixer_outputs(mixer_idx, outputs_params)?;
             }
+            Operation::SetMixerConfiguration(mixer_idx, configuration) => {
+                self.set_mixer_configuration(mixer_idx, configuration)?;
+            }
             Operation::SetIndexedData(tkr_id, idx, protocol, data) => {
                 let tkr = self.fetch_talker(tkr_id)?;
 
                 tkr.set_indexed_data(*idx, *protocol, data)?;
             }
+            Operation::SetTempo(tempo) => self.set_tempo(*tempo),
+            Operation::SetAutomation(automation_id, ear_tkr_id, ear_idx, set_idx, hum_idx, points) => {
+                self.set_automation(*automation_id, *ear_tkr_id, *ear_idx, *set_idx, *hum_idx, points)?;
+            }
+            Operation::SupAutomation(ear_tkr_id, ear_idx, set_idx, hum_idx) => {
+                self.sup_automation(*ear_tkr_id, *ear_idx, *set_idx, *hum_idx)?;
+            }
+            Operation::SetMidiMapping(mapping) => self.set_midi_mapping(mapping),
+            Operation::SupMidiMapping(ear_tkr_id, ear_idx, set_idx, hum_idx) => {
+                self.midi_mappings.retain(|m| !m.is_hum(*ear_tkr_id, *ear_idx, *set_idx, *hum_idx));
+            }
+            Operation::SetLv2Preset(tkr_id, preset_uri) => {
+                let tkr = self.fetch_talker(tkr_id)?;
+
+                for (ear_idx, value) in lv2::preset_ears_values(&tkr.model(), preset_uri)? {
+                    tkr.set_ear_hum_value(ear_idx, 0, 0, value)?;
+                }
+                if let Some(state) = lv2::preset_state(preset_uri)? {
+                    tkr.set_state(&state)?;
+                }
+            }
         }
         result
     }
@@ -701,6 +1005,48 @@ pub fn fetch_mixer<'a>(&'a self, mixer_id: &Id) -> Result<&'a RMixer, failure::E
         })
     }
 
+    pub fn tempo(&self) -> f32 {
+        self.tempo
+    }
+
+    pub fn midi_mappings(&self) -> &Vec<MidiMapping> {
+        &self.midi_mappings
+    }
+
+    pub fn find_midi_mapping(&self, talker_id: Id, ear_idx: Index, set_idx: Index, hum_idx: Index) -> Option<&MidiMapping> {
+        self.midi_mappings.iter().find(|m| m.is_hum(talker_id, ear_idx, set_idx, hum_idx))
+    }
+
+    // A controller drives one hum and a hum is driven by one controller
+    fn set_midi_mapping(&mut self, mapping: &MidiMapping) {
+        self.midi_mappings.retain(|m| {
+            (m.channel != mapping.channel || m.controller != mapping.controller) && m.hum() != mapping.hum()
+        });
+        self.midi_mappings.push(mapping.clone());
+    }
+
+    // Hums values set by a MIDI controller change
+    pub fn midi_control(&mut self, channel: u8, controller: u8, value: u8) -> Result<Vec<Operation>, failure::Error> {
+        let operations: Vec<Operation> = self.midi_mappings.iter()
+            .filter(|m| m.channel == channel && m.controller == controller)
+            .map(|m| Operation::SetEarHumValue(m.talker_id, m.ear_idx, m.set_idx, m.hum_idx, m.value(value)))
+            .collect();
+
+        for operation in &operations {
+            self.modify(operation)?;
+        }
+        Ok(operations)
+    }
+
+    // The effective band tempo is the one of the transport fed to the talkers
+    pub fn set_tempo(&mut self, tempo: f32) {
+        self.tempo = tempo.max(transport::MIN_TEMPO).min(transport::MAX_TEMPO);
+
+        if self.effective {
+            Transport::set_tempo(self.tempo);
+        }
+    }
+
     pub fn activate_talkers(&self) {
 
         for tkr in self.talkers.values() {
@@ -713,6 +1059,12 @@ pub fn fetch_mixer<'a>(&'a self, mixer_id: &Id) -> Result<&'a RMixer, failure::E
             tkr.deactivate();
         }
     }
+    pub fn reset_talkers(&self) {
+
+        for tkr in self.talkers.values() {
+            tkr.reset();
+        }
+    }
 
     pub fn set_record(&mut self, active:bool) -> Result<(), failure::Error> {
 
@@ -722,6 +1074,13 @@ pub fn fetch_mixer<'a>(&'a self, mixer_id: &Id) -> Result<&'a RMixer, failure::E
         Ok(())
     }
 
+    pub fn set_punch_in(&mut self, active: bool) {
+
+        for rmixer in self.mixers.values() {
+            rmixer.borrow_mut().set_punch_in(active);
+        }
+    }
+
     pub fn open(&mut self) -> Result<(), failure::Error> {
         self.activate_talkers();
 
@@ -747,6 +1106,14 @@ pub fn fetch_mixer<'a>(&'a self, mixer_id: &Id) -> Result<&'a RMixer, failure::E
         Ok(())
     }
 
+    pub fn levels(&self) -> Vec<MixerLevels> {
+        self.mixers.values().map(|m| m.borrow_mut().levels()).collect()
+    }
+
+    pub fn loudness_summary(&self) -> Vec<LoudnessSummary> {
+        self.mixers.values().map(|m| m.borrow().loudness_summary()).collect()
+    }
+
     pub fn play(
         &mut self,
         tick: i64,
@@ -127,4 +127,36 @@ impl Layout {
         vec!["Left", "Right"]
     }
     
-}
\ No newline at end of file
+
+    // Azimuth (clockwise from the front) and elevation in degrees of a channel speaker.
+    // The low frequency channels have no position.
+    pub fn channel_position(channel_name: &str) -> Option<(f32, f32)> {
+        match channel_name {
+            "FC" => Some((0., 0.)),
+            "FL" | "Left" | "left" => Some((-30., 0.)),
+            "FR" | "Right" | "right" => Some((30., 0.)),
+            "FLC" => Some((-15., 0.)),
+            "FRC" => Some((15., 0.)),
+            "WL" => Some((-60., 0.)),
+            "WR" => Some((60., 0.)),
+            "SL" => Some((-90., 0.)),
+            "SR" => Some((90., 0.)),
+            "BL" => Some((-150., 0.)),
+            "BR" => Some((150., 0.)),
+            "BC" => Some((180., 0.)),
+            "TC" => Some((0., 90.)),
+            "TFC" => Some((0., 45.)),
+            "TFL" => Some((-30., 45.)),
+            "TFR" => Some((30., 45.)),
+            "TSL" => Some((-90., 45.)),
+            "TSR" => Some((90., 45.)),
+            "TBL" => Some((-150., 45.)),
+            "TBR" => Some((150., 45.)),
+            "TBC" => Some((180., 45.)),
+            "BFC" => Some((0., -30.)),
+            "BFL" => Some((-30., -30.)),
+            "BFR" => Some((30., -30.)),
+            _ => None,
+        }
+    }
+}
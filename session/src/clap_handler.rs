@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use clap_sys::entry::clap_plugin_entry;
+use clap_sys::ext::latency::{clap_host_latency, CLAP_EXT_LATENCY};
+use clap_sys::ext::params::{clap_host_params, clap_param_clear_flags, clap_param_rescan_flags, CLAP_EXT_PARAMS};
+use clap_sys::ext::state::{clap_host_state, CLAP_EXT_STATE};
+use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+use clap_sys::host::clap_host;
+use clap_sys::id::clap_id;
+use clap_sys::plugin::{clap_plugin, clap_plugin_descriptor};
+use clap_sys::version::CLAP_VERSION;
+
+use crate::util::to_string;
+
+// The CLAP talkers models are the plugins id with this prefix
+pub const MODEL_PREFIX: &str = "clap:";
+
+const CLAP_PATH_VAR: &str = "CLAP_PATH";
+const CLAP_EXTENSION: &str = "clap";
+const CHANNELS_FEATURES: [&str; 4] = ["mono", "stereo", "surround", "ambisonic"];
+
+pub struct Library {
+    // Kept for the entry to stay valid
+    _library: libloading::Library,
+    entry: *const clap_plugin_entry,
+    factory: *const clap_plugin_factory,
+}
+
+// The entry and factory are thread safe by the CLAP specification
+unsafe impl Send for Library {}
+unsafe impl Sync for Library {}
+
+impl Library {
+    fn load(path: &Path) -> Result<Library, failure::Error> {
+        let path_str = path.to_string_lossy().to_string();
+
+        unsafe {
+            let library = libloading::Library::new(path)
+                .map_err(|e| failure::err_msg(format!("CLAP library {} loading failed : {}", path_str, e)))?;
+
+            let entry = *library.get::<*const clap_plugin_entry>(b"clap_entry\0")
+                .map_err(|e| failure::err_msg(format!("CLAP library {} has no entry : {}", path_str, e)))?;
+
+            let init = (*entry).init.ok_or(failure::err_msg(format!("CLAP library {} has no init", path_str)))?;
+            let c_path = CString::new(path_str.clone())?;
+
+            if !init(c_path.as_ptr()) {
+                return Err(failure::err_msg(format!("CLAP library {} initialization failed", path_str)));
+            }
+
+            let factory = match (*entry).get_factory {
+                Some(get_factory) => get_factory(CLAP_PLUGIN_FACTORY_ID.as_ptr()) as *const clap_plugin_factory,
+                None => ptr::null(),
+            };
+
+            if factory.is_null() {
+                if let Some(deinit) = (*entry).deinit {
+                    deinit();
+                }
+                return Err(failure::err_msg(format!("CLAP library {} has no plugin factory", path_str)));
+            }
+
+            Ok(Self { _library: library, entry, factory })
+        }
+    }
+
+    fn descriptors(&self) -> Vec<*const clap_plugin_descriptor> {
+        let mut descriptors = Vec::new();
+
+        unsafe {
+            let factory = &*self.factory;
+
+            if let (Some(get_plugin_count), Some(get_plugin_descriptor)) = (factory.get_plugin_count, factory.get_plugin_descriptor) {
+                for index in 0..get_plugin_count(self.factory) {
+                    let descriptor = get_plugin_descriptor(self.factory, index);
+
+                    if !descriptor.is_null() {
+                        descriptors.push(descriptor);
+                    }
+                }
+            }
+        }
+        descriptors
+    }
+
+    pub fn create_plugin(&self, id: &str, host: &PluginHost) -> Result<*const clap_plugin, failure::Error> {
+        let c_id = CString::new(id)?;
+
+        let plugin = unsafe {
+            match (*self.factory).create_plugin {
+                Some(create_plugin) => create_plugin(self.factory, host.as_ptr(), c_id.as_ptr()),
+                None => ptr::null(),
+            }
+        };
+
+        if plugin.is_null() {
+            return Err(failure::err_msg(format!("CLAP plugin {} creation failed", id)));
+        }
+        unsafe {
+            match (*plugin).init {
+                Some(init) if init(plugin) => Ok(plugin),
+                _ => {
+                    if let Some(destroy) = (*plugin).destroy {
+                        destroy(plugin);
+                    }
+                    Err(failure::err_msg(format!("CLAP plugin {} initialization failed", id)))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Library {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(deinit) = (*self.entry).deinit {
+                deinit();
+            }
+        }
+    }
+}
+
+// Requests of a plugin to its host, served by the talker on the player thread
+#[derive(Default)]
+pub struct HostRequests {
+    pub callback: AtomicBool,
+    pub restart: AtomicBool,
+    pub params_flush: AtomicBool,
+}
+
+// Host given to one plugin, its data being the plugin requests
+pub struct PluginHost {
+    host: clap_host,
+    requests: HostRequests,
+}
+
+impl PluginHost {
+    // Boxed for the host data to keep its address
+    pub fn new() -> Box<PluginHost> {
+        let mut plugin_host = Box::new(PluginHost {
+            host: clap_host {
+                clap_version: CLAP_VERSION,
+                host_data: ptr::null_mut(),
+                name: b"Graffophone\0".as_ptr() as *const c_char,
+                vendor: b"gndl\0".as_ptr() as *const c_char,
+                url: b"https://github.com/gndl/graffophone\0".as_ptr() as *const c_char,
+                version: b"0.6.1\0".as_ptr() as *const c_char,
+                get_extension: Some(host_get_extension),
+                request_restart: Some(host_request_restart),
+                request_process: Some(host_request_process),
+                request_callback: Some(host_request_callback),
+            },
+            requests: HostRequests::default(),
+        });
+        plugin_host.host.host_data = &plugin_host.requests as *const HostRequests as *mut c_void;
+        plugin_host
+    }
+
+    pub fn as_ptr(&self) -> *const clap_host {
+        &self.host
+    }
+
+    pub fn requests(&self) -> &HostRequests {
+        &self.requests
+    }
+}
+
+unsafe fn host_requests<'a>(host: *const clap_host) -> Option<&'a HostRequests> {
+    if host.is_null() {
+        None
+    } else {
+        ((*host).host_data as *const HostRequests).as_ref()
+    }
+}
+
+unsafe extern "C" fn host_get_extension(_host: *const clap_host, extension_id: *const c_char) -> *const c_void {
+    if extension_id.is_null() {
+        return ptr::null();
+    }
+    let id = CStr::from_ptr(extension_id);
+
+    if id == CLAP_EXT_PARAMS {
+        &HOST_PARAMS as *const clap_host_params as *const c_void
+    } else if id == CLAP_EXT_STATE {
+        &HOST_STATE as *const clap_host_state as *const c_void
+    } else if id == CLAP_EXT_LATENCY {
+        &HOST_LATENCY as *const clap_host_latency as *const c_void
+    } else {
+        ptr::null()
+    }
+}
+
+unsafe extern "C" fn host_request_restart(host: *const clap_host) {
+    if let Some(requests) = host_requests(host) {
+        requests.restart.store(true, Ordering::Relaxed);
+    }
+}
+
+// The plugins are processed on each chunk while the band plays
+unsafe extern "C" fn host_request_process(_host: *const clap_host) {}
+
+unsafe extern "C" fn host_request_callback(host: *const clap_host) {
+    if let Some(requests) = host_requests(host) {
+        requests.callback.store(true, Ordering::Relaxed);
+    }
+}
+
+// The talker ears are made from the params at the plugin creation
+unsafe extern "C" fn host_params_rescan(_host: *const clap_host, _flags: clap_param_rescan_flags) {}
+unsafe extern "C" fn host_params_clear(_host: *const clap_host, _param_id: clap_id, _flags: clap_param_clear_flags) {}
+
+unsafe extern "C" fn host_params_request_flush(host: *const clap_host) {
+    if let Some(requests) = host_requests(host) {
+        requests.params_flush.store(true, Ordering::Relaxed);
+    }
+}
+
+// The state is saved with the session and the latency is read when the band graph changes or opens
+unsafe extern "C" fn host_state_mark_dirty(_host: *const clap_host) {}
+unsafe extern "C" fn host_latency_changed(_host: *const clap_host) {}
+
+static HOST_PARAMS: clap_host_params = clap_host_params {
+    rescan: Some(host_params_rescan),
+    clear: Some(host_params_clear),
+    request_flush: Some(host_params_request_flush),
+};
+
+static HOST_STATE: clap_host_state = clap_host_state {
+    mark_dirty: Some(host_state_mark_dirty),
+};
+
+static HOST_LATENCY: clap_host_latency = clap_host_latency {
+    changed: Some(host_latency_changed),
+};
+
+// Loaded libraries by path
+static LIBRARIES: LazyLock<Mutex<HashMap<PathBuf, Arc<Library>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+pub fn library(path: &Path) -> Result<Arc<Library>, failure::Error> {
+    let mut libraries = LIBRARIES.lock()
+        .map_err(|_| failure::err_msg("CLAP libraries access failed on lock!"))?;
+
+    if let Some(library) = libraries.get(path) {
+        return Ok(library.clone());
+    }
+    let library = Arc::new(Library::load(path)?);
+    libraries.insert(path.to_path_buf(), library.clone());
+    Ok(library)
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct PluginDescription {
+    pub path: PathBuf,
+    pub id: String,
+    pub name: String,
+    pub categories: Vec<String>,
+}
+
+impl PluginDescription {
+    pub fn model(&self) -> String {
+        format!("{}{}", MODEL_PREFIX, self.id)
+    }
+
+    // "id<TAB>name<TAB>category|category" as written by the probe
+    pub fn serialize(&self) -> String {
+        let clean = |s: &str| s.replace(['\t', '\n', '|'], " ");
+        let categories: Vec<String> = self.categories.iter().map(|c| clean(c)).collect();
+
+        format!("{}\t{}\t{}", clean(&self.id), clean(&self.name), categories.join("|"))
+    }
+
+    pub fn parse(path: &Path, s: &str) -> Result<PluginDescription, failure::Error> {
+        let fields: Vec<&str> = s.split('\t').collect();
+
+        if fields.len() != 3 || fields[0].is_empty() {
+            return Err(failure::err_msg(format!("CLAP plugin description {} is invalid", s)));
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            id: fields[0].to_string(),
+            name: fields[1].to_string(),
+            categories: fields[2].split('|').filter(|c| !c.is_empty()).map(|c| c.to_string()).collect(),
+        })
+    }
+}
+
+// "audio-effect" gives "Audio effect"
+fn category_of(feature: &str) -> String {
+    let mut category = feature.replace('-', " ");
+
+    if let Some(first) = category.get_mut(0..1) {
+        first.make_ascii_uppercase();
+    }
+    category
+}
+
+fn search_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    if let Ok(clap_path) = std::env::var(CLAP_PATH_VAR) {
+        paths.extend(std::env::split_paths(&clap_path));
+    }
+    if let Some(home) = dirs::home_dir() {
+        paths.push(home.join(".clap"));
+    }
+    paths.push(PathBuf::from("/usr/lib/clap"));
+    paths.push(PathBuf::from("/usr/local/lib/clap"));
+    paths
+}
+
+fn find_bundles(directory: &Path, bundles: &mut Vec<PathBuf>) {
+    if let Ok(entries) = std::fs::read_dir(directory) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            if path.extension().map_or(false, |ext| ext == CLAP_EXTENSION) && path.is_file() {
+                bundles.push(path);
+            } else if path.is_dir() {
+                find_bundles(&path, bundles);
+            }
+        }
+    }
+}
+
+// CLAP bundles found in the search paths
+pub fn bundles() -> Vec<PathBuf> {
+    let mut bundles = Vec::new();
+
+    for path in search_paths() {
+        find_bundles(&path, &mut bundles);
+    }
+    bundles
+}
+
+// Plugins of the bundle. The library is loaded in the calling process so this is only called by the probe
+pub fn describe(path: &Path) -> Result<Vec<PluginDescription>, failure::Error> {
+    let library = Library::load(path)?;
+    let mut descriptions = Vec::new();
+
+    for descriptor in library.descriptors() {
+        let (id, name, categories) = unsafe {
+            let descriptor = &*descriptor;
+            let mut categories = Vec::new();
+            let mut feature = descriptor.features;
+
+            while !feature.is_null() && !(*feature).is_null() {
+                let feature_name = to_string(*feature);
+
+                if !CHANNELS_FEATURES.contains(&feature_name.as_str()) {
+                    categories.push(category_of(&feature_name));
+                }
+                feature = feature.add(1);
+            }
+            (to_string(descriptor.id), to_string(descriptor.name), categories)
+        };
+
+        descriptions.push(PluginDescription { path: path.to_path_buf(), id, name, categories });
+    }
+    Ok(descriptions)
+}
+
+#[test]
+fn test_category_of() {
+    assert_eq!(category_of("audio-effect"), "Audio effect");
+    assert_eq!(category_of("instrument"), "Instrument");
+}
+
+#[test]
+fn test_plugin_description_serialization() {
+    let description = PluginDescription {
+        path: PathBuf::from("/usr/lib/clap/synth.clap"),
+        id: "org.example.synth".to_string(),
+        name: "Synth".to_string(),
+        categories: vec!["Instrument".to_string(), "Synthesizer".to_string()],
+    };
+    let parsed = PluginDescription::parse(&description.path, &description.serialize()).unwrap();
+
+    assert!(parsed == description);
+    assert!(PluginDescription::parse(&description.path, "org.example.synth").is_err());
+}
@@ -3,6 +3,7 @@ use std::rc::Rc;
 
 use talker::identifier::{Id, Index};
 
+use crate::meter::MixerLevels;
 use crate::state::State;
 
 pub enum Notification {
@@ -12,9 +13,11 @@ pub enum Notification {
     SessionSavedAs(String),
     Tick(i64),
     TimeRange(i64, i64),
+    Tempo(f32),
     Pause,
     End,
     Volume(f32),
+    Levels(Vec<MixerLevels>),
     TalkersRange(Vec<(String, Vec<(String, String)>)>),
     NewTalker,
     TalkerChanged,
@@ -1,19 +1,21 @@
 use std::sync::{LazyLock, Mutex};
 
+use audiofile::writer::{Dither, SampleFormat};
 use talker::audio_format::AudioFormat;
 use talker::identifier::RIdentifier;
 use talker::talker::RTalker;
 
-use crate::audiofile_output::AudioFileOutput;
+use crate::audiofile_output::{AudioFileOutput, Tags};
 use crate::{audiofile_output, feedback};
 use crate::feedback::Feedback;
-use crate::mixer::{Mixer, RMixer};
+use crate::mixer::{self, Mixer, RMixer};
 use crate::output::ROutput;
 use crate::plugins_manager::PluginsManager;
+use crate::settings::Settings;
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum OutputParam {
-    File(String, usize, String, String),
+    File(String, usize, String, String, bool, SampleFormat, Dither, Tags),
     Jack,
 }
 
@@ -36,6 +38,14 @@ impl Factory {
         self.plugins_manager.get_categorized_talkers_label_model()
     }
 
+    pub fn disabled_plugins(&self) -> Vec<(String, String, String)> {
+        self.plugins_manager.disabled_plugins().clone()
+    }
+
+    pub fn forget_disabled_plugins(&self) -> Result<(), failure::Error> {
+        self.plugins_manager.forget_disabled_plugins()
+    }
+
     pub fn make_talker(
         &self,
         model: &str,
@@ -52,8 +62,9 @@ impl Factory {
         name: &str,
         oparent: Option<&RMixer>,
         outputs: Vec<ROutput>,
+        configuration: mixer::Configuration,
     ) -> Result<RMixer, failure::Error> {
-        let rmixer = Mixer::new_ref(oparent, outputs)?;
+        let rmixer = Mixer::new_ref(oparent, outputs, configuration)?;
         Factory::set_identity(rmixer.borrow().identifier(), Some(id), Some(name));
         Ok(rmixer)
     }
@@ -75,7 +86,7 @@ impl Factory {
                 None => Err(failure::err_msg(format!("{} output need configuration date!", model))),
             }
         } else if model == feedback::MODEL {
-            let output = Feedback::new_ref(AudioFormat::chunk_size())?;
+            let output = Feedback::new_ref(AudioFormat::chunk_size(), &Settings::load().feedback)?;
             Factory::set_identity(output.borrow().identifier(), oid, oname);
             Ok(output)
         } else {
@@ -89,13 +100,17 @@ impl Factory {
 
         for op in outputs_params {
             match op {
-                OutputParam::File(codec, out_sample_rate, channel_layout, file_path) => {
+                OutputParam::File(codec, out_sample_rate, channel_layout, file_path, stems, sample_format, dither, tags) => {
                     let output = AudioFileOutput::new_ref(
                         codec.as_str(),
                         in_sample_rate,
                         *out_sample_rate,
                         channel_layout,
-                        file_path.as_str())?;
+                        file_path.as_str(),
+                        *stems,
+                        *sample_format,
+                        *dither,
+                        tags.clone())?;
 
                         outputs.push(output);
                 },
@@ -12,6 +12,7 @@ use tables;
 use crate::audio_data::Vector;
 use crate::{channel, output};
 use crate::output::{Output, ROutput};
+use crate::settings::FeedbackSettings;
 
 pub const MODEL: &str = "feedback";
 
@@ -24,17 +25,46 @@ pub struct Feedback {
     sample_rate: usize,
     nb_samples: usize,
     nb_channels: usize,
+    device: Option<String>,
+    buffer_size: Option<usize>,
     audio_stream: Option<AudioStream>,
 }
 
+pub fn output_devices_names() -> Vec<String> {
+    let mut names = Vec::new();
+
+    match cpal::default_host().output_devices() {
+        Ok(devices) => {
+            for device in devices {
+                if let Ok(description) = device.description() {
+                    names.push(description.to_string());
+                }
+            }
+        }
+        Err(e) => eprintln!("Failed to list the output devices : {}", e),
+    }
+    names
+}
+
+fn output_device(device_name: &Option<String>) -> Result<cpal::Device, failure::Error> {
+    let host = cpal::default_host();
+
+    if let Some(name) = device_name {
+        for device in host.output_devices()? {
+            if device.description().map_or(false, |d| d.to_string() == *name) {
+                return Ok(device);
+            }
+        }
+        eprintln!("Output device \"{}\" not found. Fallback to the default device.", name);
+    }
+    host.default_output_device().ok_or(failure::err_msg("Failed to get default output device"))
+}
+
 impl Feedback {
-    pub fn new(nb_samples: usize) -> Result<Feedback, failure::Error> {
-        // Default devices.
-        let output_device = cpal::default_host()
-            .default_output_device()
-            .expect("failed to get default output device");
+    pub fn new(nb_samples: usize, settings: &FeedbackSettings) -> Result<Feedback, failure::Error> {
+        let output_device = output_device(&settings.device)?;
 
-        println!("Using default output device: \"{}\"", output_device.description()?);
+        println!("Using output device: \"{}\"", output_device.description()?);
 
         let config: cpal::StreamConfig = output_device.default_output_config()?.into();
 
@@ -43,26 +73,32 @@ impl Feedback {
             sample_rate: AudioFormat::sample_rate(),
             nb_samples,
             nb_channels: config.channels as usize,
+            device: settings.device.clone(),
+            buffer_size: settings.buffer_size,
             audio_stream: None,
         })
     }
 
-    pub fn new_ref(nb_samples: usize) -> Result<ROutput, failure::Error> {
-        Ok(Rc::new(RefCell::new(Feedback::new(nb_samples)?)))
+    pub fn new_ref(nb_samples: usize, settings: &FeedbackSettings) -> Result<ROutput, failure::Error> {
+        Ok(Rc::new(RefCell::new(Feedback::new(nb_samples, settings)?)))
     }
 
     fn make_audio_stream(
+        device: &Option<String>,
+        buffer_size: Option<usize>,
         nb_channels: usize,
         nb_samples: usize,
     ) -> Result<AudioStream, failure::Error> {
-        let output_device = cpal::default_host()
-            .default_output_device()
-            .expect("failed to get default output device");
+        let output_device = output_device(device)?;
 
         let mut config: cpal::StreamConfig = output_device.default_output_config()?.into();
         config.sample_rate = AudioFormat::sample_rate() as u32;
 
-        let latency_samples = nb_samples * nb_channels as usize;
+        if let Some(size) = buffer_size {
+            config.buffer_size = cpal::BufferSize::Fixed(size as u32);
+        }
+
+        let latency_samples = nb_samples.max(buffer_size.unwrap_or(0)) * nb_channels as usize;
 
         // The buffer to share samples
         let ring = HeapRb::<f32>::new(latency_samples * 5);
@@ -175,6 +211,30 @@ impl Feedback {
         nb_samples_per_channel: usize,
     ) -> Result<(), failure::Error> {
         let fade_tab = tables::create_fadeout(self.sample_rate);
+        self.write_fade_tab(a_channels, b_channels, nb_samples_per_channel, &fade_tab)
+    }
+
+    // Crossfade from a to b over the whole samples
+    pub fn write_crossfade(
+        &mut self,
+        a_channels: &Vec<Vector>,
+        b_channels: &Vec<Vector>,
+        nb_samples_per_channel: usize,
+    ) -> Result<(), failure::Error> {
+        let fade_tab = tables::create_fadeout_of_len(nb_samples_per_channel);
+        self.write_fade_tab(a_channels, b_channels, nb_samples_per_channel, &fade_tab)
+    }
+
+    fn write_fade_tab(
+        &mut self,
+        a_channels: &Vec<Vector>,
+        b_channels: &Vec<Vector>,
+        nb_samples_per_channel: usize,
+        fade_tab: &Vec<f32>,
+    ) -> Result<(), failure::Error> {
+        if fade_tab.is_empty() {
+            return self.write(b_channels, nb_samples_per_channel);
+        }
 
         let a_chan_end = a_channels.len() - 1;
         let mut a_chan_idx = 0;
@@ -241,7 +301,7 @@ impl Output for Feedback {
 
     fn open(&mut self) -> Result<(), failure::Error> {
         println!("Feedback::open");
-        let audio_stream = Feedback::make_audio_stream(self.nb_channels, self.nb_samples)?;
+        let audio_stream = Feedback::make_audio_stream(&self.device, self.buffer_size, self.nb_channels, self.nb_samples)?;
 
         self.audio_stream = Some(audio_stream);
         Ok(())
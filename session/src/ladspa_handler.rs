@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::ffi::{c_char, c_int, c_ulong, c_void};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use crate::util::to_string;
+
+// The LADSPA talkers models are the plugins unique id with this prefix
+pub const MODEL_PREFIX: &str = "ladspa:";
+pub const CATEGORY: &str = "LADSPA";
+
+const LADSPA_PATH_VAR: &str = "LADSPA_PATH";
+const LADSPA_EXTENSION: &str = "so";
+
+pub const PORT_INPUT: c_int = 0x1;
+pub const PORT_OUTPUT: c_int = 0x2;
+pub const PORT_CONTROL: c_int = 0x4;
+pub const PORT_AUDIO: c_int = 0x8;
+
+const HINT_BOUNDED_BELOW: c_int = 0x1;
+const HINT_BOUNDED_ABOVE: c_int = 0x2;
+const HINT_TOGGLED: c_int = 0x4;
+const HINT_SAMPLE_RATE: c_int = 0x8;
+const HINT_LOGARITHMIC: c_int = 0x10;
+const HINT_DEFAULT_MASK: c_int = 0x3C0;
+const HINT_DEFAULT_MINIMUM: c_int = 0x40;
+const HINT_DEFAULT_LOW: c_int = 0x80;
+const HINT_DEFAULT_MIDDLE: c_int = 0xC0;
+const HINT_DEFAULT_HIGH: c_int = 0x100;
+const HINT_DEFAULT_MAXIMUM: c_int = 0x140;
+const HINT_DEFAULT_0: c_int = 0x200;
+const HINT_DEFAULT_1: c_int = 0x240;
+const HINT_DEFAULT_100: c_int = 0x280;
+const HINT_DEFAULT_440: c_int = 0x2C0;
+
+pub type Handle = *mut c_void;
+
+// ladspa.h version 1.1 ABI
+#[repr(C)]
+pub struct PortRangeHint {
+    pub hint_descriptor: c_int,
+    pub lower_bound: f32,
+    pub upper_bound: f32,
+}
+
+#[repr(C)]
+pub struct Descriptor {
+    pub unique_id: c_ulong,
+    pub label: *const c_char,
+    pub properties: c_int,
+    pub name: *const c_char,
+    pub maker: *const c_char,
+    pub copyright: *const c_char,
+    pub port_count: c_ulong,
+    pub port_descriptors: *const c_int,
+    pub port_names: *const *const c_char,
+    pub port_range_hints: *const PortRangeHint,
+    pub implementation_data: *mut c_void,
+    pub instantiate: Option<unsafe extern "C" fn(*const Descriptor, c_ulong) -> Handle>,
+    pub connect_port: Option<unsafe extern "C" fn(Handle, c_ulong, *mut f32)>,
+    pub activate: Option<unsafe extern "C" fn(Handle)>,
+    pub run: Option<unsafe extern "C" fn(Handle, c_ulong)>,
+    pub run_adding: Option<unsafe extern "C" fn(Handle, c_ulong)>,
+    pub set_run_adding_gain: Option<unsafe extern "C" fn(Handle, f32)>,
+    pub deactivate: Option<unsafe extern "C" fn(Handle)>,
+    pub cleanup: Option<unsafe extern "C" fn(Handle)>,
+}
+
+type DescriptorFunction = unsafe extern "C" fn(c_ulong) -> *const Descriptor;
+
+pub struct Library {
+    // Kept for the descriptors to stay valid
+    _library: libloading::Library,
+    descriptors: Vec<*const Descriptor>,
+}
+
+// The descriptors are read only data
+unsafe impl Send for Library {}
+unsafe impl Sync for Library {}
+
+impl Library {
+    fn load(path: &Path) -> Result<Library, failure::Error> {
+        let path_str = path.to_string_lossy().to_string();
+
+        unsafe {
+            let library = libloading::Library::new(path)
+                .map_err(|e| failure::err_msg(format!("LADSPA library {} loading failed : {}", path_str, e)))?;
+
+            let descriptor_fn = *library.get::<DescriptorFunction>(b"ladspa_descriptor\0")
+                .map_err(|e| failure::err_msg(format!("LADSPA library {} has no descriptor : {}", path_str, e)))?;
+
+            let mut descriptors = Vec::new();
+            let mut index = 0;
+
+            loop {
+                let descriptor = descriptor_fn(index);
+
+                if descriptor.is_null() {
+                    break;
+                }
+                descriptors.push(descriptor);
+                index += 1;
+            }
+            Ok(Self { _library: library, descriptors })
+        }
+    }
+
+    pub fn descriptor(&self, unique_id: u64) -> Option<&Descriptor> {
+        self.descriptors.iter()
+            .map(|d| unsafe { &**d })
+            .find(|d| d.unique_id as u64 == unique_id)
+    }
+}
+
+// Loaded libraries by path
+static LIBRARIES: LazyLock<Mutex<HashMap<PathBuf, Arc<Library>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+pub fn library(path: &Path) -> Result<Arc<Library>, failure::Error> {
+    let mut libraries = LIBRARIES.lock()
+        .map_err(|_| failure::err_msg("LADSPA libraries access failed on lock!"))?;
+
+    if let Some(library) = libraries.get(path) {
+        return Ok(library.clone());
+    }
+    let library = Arc::new(Library::load(path)?);
+    libraries.insert(path.to_path_buf(), library.clone());
+    Ok(library)
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct PluginDescription {
+    pub path: PathBuf,
+    pub unique_id: u64,
+    pub name: String,
+}
+
+impl PluginDescription {
+    pub fn model(&self) -> String {
+        format!("{}{}", MODEL_PREFIX, self.unique_id)
+    }
+
+    // "unique_id<TAB>name" as written by the probe
+    pub fn serialize(&self) -> String {
+        format!("{}\t{}", self.unique_id, self.name.replace(['\t', '\n'], " "))
+    }
+
+    pub fn parse(path: &Path, s: &str) -> Result<PluginDescription, failure::Error> {
+        match s.split_once('\t') {
+            Some((unique_id, name)) => Ok(Self {
+                path: path.to_path_buf(),
+                unique_id: unique_id.parse::<u64>()
+                    .map_err(|e| failure::err_msg(format!("LADSPA plugin description {} is invalid : {}", s, e)))?,
+                name: name.to_string(),
+            }),
+            None => Err(failure::err_msg(format!("LADSPA plugin description {} is invalid", s))),
+        }
+    }
+}
+
+pub fn unique_id_of(model: &str) -> Result<u64, failure::Error> {
+    model.strip_prefix(MODEL_PREFIX).unwrap_or(model).parse::<u64>()
+        .map_err(|e| failure::err_msg(format!("LADSPA model {} is invalid : {}", model, e)))
+}
+
+// Range and default value of a control port
+pub fn control_range(hint: &PortRangeHint, sample_rate: f32) -> (Option<f32>, Option<f32>, Option<f32>) {
+    let descriptor = hint.hint_descriptor;
+    let factor = if descriptor & HINT_SAMPLE_RATE != 0 { sample_rate } else { 1. };
+
+    let min = if descriptor & HINT_TOGGLED != 0 {
+        Some(0.)
+    } else if descriptor & HINT_BOUNDED_BELOW != 0 {
+        Some(hint.lower_bound * factor)
+    } else {
+        None
+    };
+    let max = if descriptor & HINT_TOGGLED != 0 {
+        Some(1.)
+    } else if descriptor & HINT_BOUNDED_ABOVE != 0 {
+        Some(hint.upper_bound * factor)
+    } else {
+        None
+    };
+
+    let logarithmic = descriptor & HINT_LOGARITHMIC != 0;
+
+    // Value at the given ratio between the bounds
+    let between = |ratio: f32| match (min, max) {
+        (Some(lo), Some(hi)) if logarithmic && lo > 0. && hi > 0. => Some((lo.ln() * (1. - ratio) + hi.ln() * ratio).exp()),
+        (Some(lo), Some(hi)) => Some(lo * (1. - ratio) + hi * ratio),
+        _ => None,
+    };
+
+    let default = match descriptor & HINT_DEFAULT_MASK {
+        HINT_DEFAULT_MINIMUM => min,
+        HINT_DEFAULT_LOW => between(0.25),
+        HINT_DEFAULT_MIDDLE => between(0.5),
+        HINT_DEFAULT_HIGH => between(0.75),
+        HINT_DEFAULT_MAXIMUM => max,
+        HINT_DEFAULT_0 => Some(0.),
+        HINT_DEFAULT_1 => Some(1.),
+        HINT_DEFAULT_100 => Some(100.),
+        HINT_DEFAULT_440 => Some(440.),
+        _ => None,
+    };
+    (min, max, default)
+}
+
+fn search_paths() -> Vec<PathBuf> {
+    if let Ok(ladspa_path) = std::env::var(LADSPA_PATH_VAR) {
+        return std::env::split_paths(&ladspa_path).collect();
+    }
+    let mut paths = Vec::new();
+
+    if let Some(home) = dirs::home_dir() {
+        paths.push(home.join(".ladspa"));
+    }
+    paths.push(PathBuf::from("/usr/lib/ladspa"));
+    paths.push(PathBuf::from("/usr/local/lib/ladspa"));
+    paths
+}
+
+// LADSPA libraries found in the search paths
+pub fn libraries() -> Vec<PathBuf> {
+    let mut libraries = Vec::new();
+
+    for directory in search_paths() {
+        if let Ok(entries) = std::fs::read_dir(&directory) {
+            libraries.extend(entries.flatten().map(|e| e.path())
+                .filter(|path| path.extension().map_or(false, |ext| ext == LADSPA_EXTENSION)));
+        }
+    }
+    libraries
+}
+
+// Plugins of the library. The library is loaded in the calling process so this is only called by the probe
+pub fn describe(path: &Path) -> Result<Vec<PluginDescription>, failure::Error> {
+    let library = Library::load(path)?;
+    let mut descriptions = Vec::new();
+
+    for descriptor in &library.descriptors {
+        let (unique_id, name) = unsafe {
+            let descriptor = &**descriptor;
+            (descriptor.unique_id as u64, to_string(descriptor.name))
+        };
+        descriptions.push(PluginDescription { path: path.to_path_buf(), unique_id, name });
+    }
+    Ok(descriptions)
+}
+
+#[test]
+fn test_control_range() {
+    let hint = PortRangeHint {
+        hint_descriptor: HINT_BOUNDED_BELOW | HINT_BOUNDED_ABOVE | HINT_LOGARITHMIC | HINT_DEFAULT_MIDDLE,
+        lower_bound: 10.,
+        upper_bound: 1000.,
+    };
+    let (min, max, default) = control_range(&hint, 48000.);
+    assert!(min == Some(10.) && max == Some(1000.));
+    assert!((default.unwrap() - 100.).abs() < 0.01);
+
+    let hint = PortRangeHint {
+        hint_descriptor: HINT_BOUNDED_BELOW | HINT_BOUNDED_ABOVE | HINT_SAMPLE_RATE | HINT_DEFAULT_LOW,
+        lower_bound: 0.,
+        upper_bound: 0.5,
+    };
+    let (_, max, default) = control_range(&hint, 48000.);
+    assert!(max == Some(24000.) && default == Some(6000.));
+
+    let hint = PortRangeHint { hint_descriptor: HINT_TOGGLED | HINT_DEFAULT_1, lower_bound: 0., upper_bound: 0. };
+    assert!(control_range(&hint, 48000.) == (Some(0.), Some(1.), Some(1.)));
+}
+
+#[test]
+fn test_plugin_description_serialization() {
+    let description = PluginDescription {
+        path: PathBuf::from("/usr/lib/ladspa/amp.so"),
+        unique_id: 1048,
+        name: "Mono Amplifier".to_string(),
+    };
+    let parsed = PluginDescription::parse(&description.path, &description.serialize()).unwrap();
+
+    assert!(parsed == description);
+    assert!(PluginDescription::parse(&description.path, "amp\tMono Amplifier").is_err());
+}
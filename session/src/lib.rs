@@ -1,5 +1,8 @@
+extern crate clap_sys;
 extern crate cpal;
 extern crate failure;
+extern crate libloading;
+extern crate midir;
 extern crate livi;
 extern crate nom;
 extern crate ringbuf;
@@ -13,17 +16,26 @@ pub mod audio_data;
 pub mod audiofile_output;
 pub mod band;
 pub mod channel;
+pub mod clap_handler;
 pub mod event_bus;
 pub mod factory;
 pub mod feedback;
+pub mod ladspa_handler;
+pub mod meter;
 pub mod midi;
+pub mod midi_input;
+pub mod midi_mapping;
 pub mod mixer;
+pub mod osc_server;
 pub mod output;
+pub mod panner;
 pub mod parser;
 pub mod player;
 pub mod plugin_handle_manager;
 pub mod plugins_manager;
+pub mod plugins_scan;
 pub mod session;
+pub mod settings;
 pub mod state;
 pub mod tables;
 pub mod talkers;
@@ -0,0 +1,452 @@
+use std::f64::consts::PI;
+
+use talker::audio_format::AudioFormat;
+use talker::identifier::Id;
+
+use crate::audio_data::Vector;
+
+pub const MIN_DB: f32 = -70.;
+
+// Loudness blocks of 400 ms are computed every 100 ms and the short-term loudness is the last 3 s
+const SUB_BLOCKS_PER_SECOND: usize = 10;
+const MOMENTARY_SUB_BLOCKS: usize = 4;
+const SHORT_TERM_SUB_BLOCKS: usize = 30;
+
+const ABSOLUTE_GATE: f64 = -70.;
+const RELATIVE_GATE: f64 = -10.;
+
+// The gated blocks are summed by loudness steps of 0.1 LU up to +10 LUFS
+const GATING_STEP: f64 = 0.1;
+const GATING_BINS: usize = 800;
+
+// BS.1770 channels weights : the surround channels are weighted by 1.41 and the low frequency ones are excluded
+const SURROUND_WEIGHT: f64 = 1.41;
+
+// True peak is measured on a 4 times oversampled signal
+const OVERSAMPLING: usize = 4;
+const PHASE_TAPS: usize = 12;
+
+#[derive(Clone, Debug, Default)]
+pub struct ChannelLevels {
+    pub peak: f32,
+    pub rms: f32,
+    pub true_peak: f32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Levels {
+    pub channels: Vec<ChannelLevels>,
+    pub momentary: f32,
+    pub short_term: f32,
+    pub integrated: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct MixerLevels {
+    pub mixer_id: Id,
+    pub master: Levels,
+    pub tracks: Vec<Levels>,
+    // Compensated latency in samples
+    pub latency: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct LoudnessSummary {
+    pub mixer_id: Id,
+    pub integrated: f32,
+    pub true_peak: f32,
+}
+
+pub enum Metering {
+    Levels(Vec<MixerLevels>),
+    LoudnessSummary(Vec<LoudnessSummary>),
+}
+
+pub fn to_db(v: f32) -> f32 {
+    if v > 0. {
+        (20. * v.log10()).max(MIN_DB)
+    } else {
+        MIN_DB
+    }
+}
+
+#[test]
+fn test_to_db() {
+    assert!(to_db(1.) == 0.);
+    assert!(to_db(0.) == MIN_DB);
+    assert!((to_db(0.5) + 6.0206).abs() < 0.001);
+}
+
+fn channel_weight(channel_name: &str) -> f64 {
+    match channel_name {
+        "LFE" | "LFE2" => 0.,
+        "SL" | "SR" | "BL" | "BR" => SURROUND_WEIGHT,
+        _ => 1.,
+    }
+}
+
+fn energy_to_lufs(energy: f64) -> f64 {
+    if energy > 0. {
+        -0.691 + 10. * energy.log10()
+    } else {
+        MIN_DB as f64
+    }
+}
+
+fn gating_bin(loudness: f64) -> usize {
+    (((loudness - ABSOLUTE_GATE) / GATING_STEP).max(0.) as usize).min(GATING_BINS - 1)
+}
+
+#[derive(Clone, Copy)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    fn new(b0: f64, b1: f64, b2: f64, a1: f64, a2: f64) -> Biquad {
+        Self { b0, b1, b2, a1, a2, z1: 0., z2: 0. }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+
+    fn reset(&mut self) {
+        self.z1 = 0.;
+        self.z2 = 0.;
+    }
+}
+
+// ITU-R BS.1770 K-weighting filter, a high shelf followed by a high pass, for any sample rate
+fn k_weighting(sample_rate: f64) -> (Biquad, Biquad) {
+    let f0 = 1681.974450955533;
+    let g = 3.999843853973347;
+    let q = 0.7071752369554196;
+
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10_f64.powf(g / 20.);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1. + k / q + k * k;
+
+    let shelf = Biquad::new(
+        (vh + vb * k / q + k * k) / a0,
+        2. * (k * k - vh) / a0,
+        (vh - vb * k / q + k * k) / a0,
+        2. * (k * k - 1.) / a0,
+        (1. - k / q + k * k) / a0,
+    );
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1. + k / q + k * k;
+
+    let high_pass = Biquad::new(1., -2., 1., 2. * (k * k - 1.) / a0, (1. - k / q + k * k) / a0);
+
+    (shelf, high_pass)
+}
+
+// Hann windowed sinc interpolation filter split in polyphase components
+fn oversampling_filter() -> Vec<[f64; PHASE_TAPS]> {
+    let taps = OVERSAMPLING * PHASE_TAPS;
+    let center = (taps - 1) as f64 * 0.5;
+    let mut phases = vec![[0.; PHASE_TAPS]; OVERSAMPLING];
+
+    for n in 0..taps {
+        let t = (n as f64 - center) / OVERSAMPLING as f64;
+        let sinc = if t == 0. { 1. } else { (PI * t).sin() / (PI * t) };
+        let window = 0.5 - 0.5 * (2. * PI * (n as f64 + 0.5) / taps as f64).cos();
+
+        phases[n % OVERSAMPLING][n / OVERSAMPLING] = sinc * window;
+    }
+
+    for phase in phases.iter_mut() {
+        let sum: f64 = phase.iter().sum();
+
+        for c in phase.iter_mut() {
+            *c /= sum;
+        }
+    }
+    phases
+}
+
+struct ChannelMeter {
+    weight: f64,
+    shelf: Biquad,
+    high_pass: Biquad,
+    history: [f64; PHASE_TAPS],
+    history_pos: usize,
+    peak: f32,
+    true_peak: f32,
+    max_true_peak: f32,
+    square_sum: f64,
+    weighted_square_sum: f64,
+}
+
+impl ChannelMeter {
+    fn new(sample_rate: f64, weight: f64) -> ChannelMeter {
+        let (shelf, high_pass) = k_weighting(sample_rate);
+
+        Self {
+            weight,
+            shelf,
+            high_pass,
+            history: [0.; PHASE_TAPS],
+            history_pos: 0,
+            peak: 0.,
+            true_peak: 0.,
+            max_true_peak: 0.,
+            square_sum: 0.,
+            weighted_square_sum: 0.,
+        }
+    }
+
+    fn process(&mut self, x: f32, filter: &Vec<[f64; PHASE_TAPS]>) {
+        let v = x as f64;
+        let a = x.abs();
+
+        if a > self.peak {
+            self.peak = a;
+        }
+        self.square_sum += v * v;
+
+        let w = self.high_pass.process(self.shelf.process(v));
+        self.weighted_square_sum += w * w;
+
+        self.history_pos = (self.history_pos + 1) % PHASE_TAPS;
+        self.history[self.history_pos] = v;
+
+        let mut tp = a;
+
+        for phase in filter {
+            let mut y = 0.;
+
+            for (k, c) in phase.iter().enumerate() {
+                y += c * self.history[(self.history_pos + PHASE_TAPS - k) % PHASE_TAPS];
+            }
+            tp = tp.max(y.abs() as f32);
+        }
+
+        if tp > self.true_peak {
+            self.true_peak = tp;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.shelf.reset();
+        self.high_pass.reset();
+        self.history = [0.; PHASE_TAPS];
+        self.peak = 0.;
+        self.true_peak = 0.;
+        self.max_true_peak = 0.;
+        self.square_sum = 0.;
+        self.weighted_square_sum = 0.;
+    }
+}
+
+pub struct Meter {
+    channels: Vec<ChannelMeter>,
+    filter: Vec<[f64; PHASE_TAPS]>,
+    sub_block_len: usize,
+    sub_block_pos: usize,
+    sub_blocks: Vec<f64>,
+    sub_blocks_count: usize,
+    // Energy sum and count of the blocks above the absolute gate, in total and by loudness step
+    gated_energy: f64,
+    gated_count: usize,
+    gating_bins: Vec<(f64, usize)>,
+    levels_len: usize,
+}
+
+impl Meter {
+    pub fn new(channels_names: &[&str]) -> Meter {
+        let sample_rate = AudioFormat::sample_rate();
+        let mut channels = Vec::with_capacity(channels_names.len());
+
+        for name in channels_names {
+            channels.push(ChannelMeter::new(sample_rate as f64, channel_weight(name)));
+        }
+
+        Self {
+            channels,
+            filter: oversampling_filter(),
+            sub_block_len: (sample_rate / SUB_BLOCKS_PER_SECOND).max(1),
+            sub_block_pos: 0,
+            sub_blocks: vec![0.; SHORT_TERM_SUB_BLOCKS],
+            sub_blocks_count: 0,
+            gated_energy: 0.,
+            gated_count: 0,
+            gating_bins: vec![(0., 0); GATING_BINS],
+            levels_len: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for channel in self.channels.iter_mut() {
+            channel.reset();
+        }
+        self.sub_block_pos = 0;
+        self.sub_blocks.fill(0.);
+        self.sub_blocks_count = 0;
+        self.gated_energy = 0.;
+        self.gated_count = 0;
+        self.gating_bins.fill((0., 0));
+        self.levels_len = 0;
+    }
+
+    pub fn process(&mut self, channels: &Vec<Vector>, len: usize) {
+        for i in 0..len {
+            for (meter, channel) in self.channels.iter_mut().zip(channels) {
+                meter.process(channel[i], &self.filter);
+            }
+            self.sub_block_pos += 1;
+
+            if self.sub_block_pos == self.sub_block_len {
+                self.end_sub_block();
+            }
+        }
+        self.levels_len += len;
+    }
+
+    fn end_sub_block(&mut self) {
+        let mut energy = 0.;
+
+        for channel in self.channels.iter_mut() {
+            energy += channel.weight * channel.weighted_square_sum;
+            channel.weighted_square_sum = 0.;
+        }
+        energy /= self.sub_block_len as f64;
+
+        self.sub_blocks[self.sub_blocks_count % SHORT_TERM_SUB_BLOCKS] = energy;
+        self.sub_blocks_count += 1;
+        self.sub_block_pos = 0;
+
+        if self.sub_blocks_count >= MOMENTARY_SUB_BLOCKS {
+            let block_energy = self.mean_energy(MOMENTARY_SUB_BLOCKS);
+            let block_loudness = energy_to_lufs(block_energy);
+
+            if block_loudness > ABSOLUTE_GATE {
+                let bin = &mut self.gating_bins[gating_bin(block_loudness)];
+                bin.0 += block_energy;
+                bin.1 += 1;
+                self.gated_energy += block_energy;
+                self.gated_count += 1;
+            }
+        }
+    }
+
+    fn mean_energy(&self, sub_blocks: usize) -> f64 {
+        let count = sub_blocks.min(self.sub_blocks_count);
+
+        if count == 0 {
+            return 0.;
+        }
+        let mut sum = 0.;
+
+        for i in 0..count {
+            sum += self.sub_blocks[(self.sub_blocks_count - 1 - i) % SHORT_TERM_SUB_BLOCKS];
+        }
+        sum / count as f64
+    }
+
+    // The blocks of the loudness step holding the relative gate are kept
+    pub fn integrated_loudness(&self) -> f32 {
+        if self.gated_count == 0 {
+            return MIN_DB;
+        }
+        let threshold = energy_to_lufs(self.gated_energy / self.gated_count as f64) + RELATIVE_GATE;
+
+        let mut sum = 0.;
+        let mut count = 0;
+
+        for (energy, blocks) in &self.gating_bins[gating_bin(threshold)..] {
+            sum += energy;
+            count += blocks;
+        }
+
+        if count > 0 {
+            energy_to_lufs(sum / count as f64) as f32
+        } else {
+            MIN_DB
+        }
+    }
+
+    pub fn max_true_peak(&self) -> f32 {
+        let mut max = 0.;
+
+        for channel in &self.channels {
+            max = channel.max_true_peak.max(channel.true_peak).max(max);
+        }
+        to_db(max)
+    }
+
+    // Levels since the previous call
+    pub fn levels(&mut self) -> Levels {
+        let len = self.levels_len.max(1) as f64;
+        let mut channels = Vec::with_capacity(self.channels.len());
+
+        for channel in self.channels.iter_mut() {
+            channels.push(ChannelLevels {
+                peak: to_db(channel.peak),
+                rms: to_db((channel.square_sum / len).sqrt() as f32),
+                true_peak: to_db(channel.true_peak),
+            });
+            channel.max_true_peak = channel.max_true_peak.max(channel.true_peak);
+            channel.peak = 0.;
+            channel.true_peak = 0.;
+            channel.square_sum = 0.;
+        }
+        self.levels_len = 0;
+
+        Levels {
+            channels,
+            momentary: energy_to_lufs(self.mean_energy(MOMENTARY_SUB_BLOCKS)) as f32,
+            short_term: energy_to_lufs(self.mean_energy(SHORT_TERM_SUB_BLOCKS)) as f32,
+            integrated: self.integrated_loudness(),
+        }
+    }
+}
+
+#[cfg(test)]
+fn sine_loudness(channels_names: &[&str], amplitudes: &[f32]) -> f32 {
+    let sample_rate = AudioFormat::sample_rate();
+    let chunk_size = 1024;
+    let mut meter = Meter::new(channels_names);
+    let mut channels = vec![vec![0.; chunk_size]; channels_names.len()];
+    let mut t = 0;
+
+    while t < sample_rate * 5 {
+        for (channel, amplitude) in channels.iter_mut().zip(amplitudes) {
+            for i in 0..chunk_size {
+                channel[i] = amplitude * (2. * PI * 997. * (t + i) as f64 / sample_rate as f64).sin() as f32;
+            }
+        }
+        meter.process(&channels, chunk_size);
+        t += chunk_size;
+    }
+    meter.integrated_loudness()
+}
+
+#[test]
+fn test_integrated_loudness() {
+    let db = |v: f32| 10_f32.powf(v / 20.);
+
+    // A 997 Hz sine at -20 dBFS on one channel of a stereo meter
+    assert!((sine_loudness(&["FL", "FR"], &[db(-20.), 0.]) + 23.).abs() < 0.1);
+    // EBU Tech 3341 : a stereo 997 Hz sine at -23 dBFS
+    assert!((sine_loudness(&["FL", "FR"], &[db(-23.), db(-23.)]) + 23.).abs() < 0.1);
+
+    // The surround channels are weighted by 1.41 and the low frequency one is excluded
+    let surround = sine_loudness(&["FL", "FR", "FC", "LFE", "BL", "BR"], &[0., 0., 0., 0., db(-20.), 0.]);
+    assert!((surround + 23. - 1.41_f32.log10() * 10.).abs() < 0.1);
+    assert!(sine_loudness(&["FL", "FR", "FC", "LFE", "BL", "BR"], &[0., 0., 0., db(-20.), 0., 0.]) == MIN_DB);
+}
@@ -0,0 +1,86 @@
+use midir::{Ignore, MidiInput, MidiInputConnection};
+
+use crate::midi;
+use crate::APPLICATION_NAME;
+
+// Connections to the MIDI input ports, closed on drop
+pub struct Input {
+    connections: Vec<MidiInputConnection<()>>,
+}
+
+impl Input {
+    pub fn is_connected(&self) -> bool {
+        !self.connections.is_empty()
+    }
+}
+
+impl Drop for Input {
+    fn drop(&mut self) {
+        for connection in self.connections.drain(..) {
+            connection.close();
+        }
+    }
+}
+
+pub fn input_ports_names() -> Vec<String> {
+    match MidiInput::new(APPLICATION_NAME) {
+        Ok(input) => input.ports().iter().filter_map(|port| input.port_name(port).ok()).collect(),
+        Err(e) => {
+            eprintln!("MIDI input initialization failed : {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// The input ports named in ports_names are listened, every available one if it is empty.
+// The controller changes are given to the callback as channel, controller and value
+pub fn connect<F>(ports_names: &[String], on_controller: F) -> Input
+where
+    F: Fn(u8, u8, u8) + Send + Clone + 'static,
+{
+    let mut connections = Vec::new();
+
+    let ports_count = match MidiInput::new(APPLICATION_NAME) {
+        Ok(input) => input.port_count(),
+        Err(e) => {
+            eprintln!("MIDI input initialization failed : {}", e);
+            return Input { connections };
+        }
+    };
+
+    // A connection consumes its MidiInput so each port is taken from a new one
+    for port_index in 0..ports_count {
+        let mut input = match MidiInput::new(APPLICATION_NAME) {
+            Ok(input) => input,
+            Err(e) => {
+                eprintln!("MIDI input initialization failed : {}", e);
+                break;
+            }
+        };
+        input.ignore(Ignore::All);
+
+        let port = match input.ports().get(port_index) {
+            Some(port) => port.clone(),
+            None => break,
+        };
+
+        let port_name = input.port_name(&port).unwrap_or_default();
+
+        if !ports_names.is_empty() && !ports_names.contains(&port_name) {
+            continue;
+        }
+        let callback = on_controller.clone();
+
+        let connection = input.connect(&port, APPLICATION_NAME, move |_, message, _| {
+            if message.len() == 3 && (message[0] & 0xF0) == midi::CONTROLLER {
+                callback(message[0] & 0x0F, message[1], message[2]);
+            }
+        }, ());
+
+        match connection {
+            Ok(connection) => connections.push(connection),
+            Err(e) => eprintln!("MIDI input {} connection failed : {}", port_name, e),
+        }
+    }
+    Input { connections }
+}
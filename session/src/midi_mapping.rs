@@ -0,0 +1,131 @@
+use std::str::FromStr;
+
+use talker::identifier::{Id, Index};
+
+const MAX_CONTROL_VALUE: f32 = 127.;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Curve {
+    Linear,
+    // The value progresses by the same ratio for each controller step. Suited to frequencies and gains
+    Logarithmic,
+}
+
+impl Curve {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Curve::Linear => "linear",
+            Curve::Logarithmic => "logarithmic",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Result<Curve, failure::Error> {
+        match s {
+            "linear" => Ok(Curve::Linear),
+            "logarithmic" => Ok(Curve::Logarithmic),
+            _ => Err(failure::err_msg(format!("MIDI mapping curve {} is unknown", s))),
+        }
+    }
+
+    pub fn all() -> Vec<Curve> {
+        vec![Curve::Linear, Curve::Logarithmic]
+    }
+}
+
+// A MIDI controller of a channel driving a hum value between min and max
+#[derive(PartialEq, Debug, Clone)]
+pub struct MidiMapping {
+    pub channel: u8,
+    pub controller: u8,
+    pub talker_id: Id,
+    pub ear_idx: Index,
+    pub set_idx: Index,
+    pub hum_idx: Index,
+    pub min: f32,
+    pub max: f32,
+    pub curve: Curve,
+}
+
+impl MidiMapping {
+    pub fn new(talker_id: Id, ear_idx: Index, set_idx: Index, hum_idx: Index, min: f32, max: f32, curve: Curve) -> MidiMapping {
+        Self { channel: 0, controller: 0, talker_id, ear_idx, set_idx, hum_idx, min, max, curve }
+    }
+
+    pub fn hum(&self) -> (Id, Index, Index, Index) {
+        (self.talker_id, self.ear_idx, self.set_idx, self.hum_idx)
+    }
+
+    pub fn is_hum(&self, talker_id: Id, ear_idx: Index, set_idx: Index, hum_idx: Index) -> bool {
+        self.hum() == (talker_id, ear_idx, set_idx, hum_idx)
+    }
+
+    pub fn value(&self, control_value: u8) -> f32 {
+        let ratio = (control_value as f32).min(MAX_CONTROL_VALUE) / MAX_CONTROL_VALUE;
+
+        match self.curve {
+            Curve::Logarithmic if self.min > 0. && self.max > 0. => self.min * (self.max / self.min).powf(ratio),
+            _ => self.min + (self.max - self.min) * ratio,
+        }
+    }
+
+    // "channel controller talker.ear.set.hum min max curve"
+    pub fn serialize(&self) -> String {
+        format!(
+            "{} {} {}.{}.{}.{} {} {} {}",
+            self.channel,
+            self.controller,
+            self.talker_id,
+            self.ear_idx,
+            self.set_idx,
+            self.hum_idx,
+            self.min,
+            self.max,
+            self.curve.to_str()
+        )
+    }
+
+    pub fn parse(s: &str) -> Result<MidiMapping, failure::Error> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+
+        if fields.len() != 6 {
+            return Err(failure::err_msg(format!("MIDI mapping {} is invalid", s)));
+        }
+        let hum: Vec<&str> = fields[2].split('.').collect();
+
+        if hum.len() != 4 {
+            return Err(failure::err_msg(format!("MIDI mapping {} hum {} is invalid", s, fields[2])));
+        }
+        let error = |e: &dyn std::fmt::Display| failure::err_msg(format!("MIDI mapping {} : {}", s, e));
+
+        Ok(Self {
+            channel: u8::from_str(fields[0]).map_err(|e| error(&e))?,
+            controller: u8::from_str(fields[1]).map_err(|e| error(&e))?,
+            talker_id: Id::from_str(hum[0]).map_err(|e| error(&e))?,
+            ear_idx: Index::from_str(hum[1]).map_err(|e| error(&e))?,
+            set_idx: Index::from_str(hum[2]).map_err(|e| error(&e))?,
+            hum_idx: Index::from_str(hum[3]).map_err(|e| error(&e))?,
+            min: f32::from_str(fields[3]).map_err(|e| error(&e))?,
+            max: f32::from_str(fields[4]).map_err(|e| error(&e))?,
+            curve: Curve::from_str(fields[5])?,
+        })
+    }
+}
+
+#[test]
+fn test_midi_mapping() {
+    let mut mapping = MidiMapping::new(12, 1, 0, 2, 20., 20000., Curve::Logarithmic);
+    mapping.channel = 3;
+    mapping.controller = 74;
+
+    assert!(MidiMapping::parse(&mapping.serialize()).unwrap() == mapping);
+    assert!(MidiMapping::parse("3 74 12.1.0 20 20000 linear").is_err());
+
+    assert!(mapping.value(0) == 20.);
+    assert!((mapping.value(127) - 20000.).abs() < 0.1);
+    assert!((mapping.value(64) - 20. * 1000f32.powf(64. / 127.)).abs() < 0.1);
+
+    mapping.curve = Curve::Linear;
+    mapping.min = -1.;
+    mapping.max = 1.;
+    assert!(mapping.value(0) == -1. && mapping.value(127) == 1.);
+}
@@ -1,38 +1,258 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
 
 use talker::audio_format::AudioFormat;
+use talker::dsp;
 use talker::ear;
 use talker::ear::Ear;
 use talker::ear::Init;
 use talker::ear::Set;
 use talker::horn::PortType;
 use talker::identifier::{Id, Identifiable, Index, RIdentifier};
-use talker::talker::{MuteTalker, RTalker, TalkerBase};
+use talker::ctalker;
+use talker::rtalker;
+use talker::talker::{RTalker, Talker, TalkerBase, TalkerCab};
 
 use crate::audio_data::Vector;
+use crate::audiofile_output::AudioFileOutput;
+use crate::meter::{LoudnessSummary, Meter, MixerLevels};
 use crate::output::ROutput;
+use crate::panner::{self, Panner, Panning};
 use tables;
 use crate::track;
+use crate::util;
 
 pub const KIND: &str = "Mixer";
 
 pub const VOLUME_EAR_INDEX: Index = 0;
 pub const TRACKS_EAR_INDEX: Index = 1;
-const INPUT_HUM_INDEX: Index = 0;
+pub const INPUT_HUM_INDEX: Index = 0;
 const GAIN_HUM_INDEX: Index = 1;
 const CHANNELS_HUM_INDEX: Index = 2;
 
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Summing {
+    Sum,
+    Average,
+    SqrtAverage,
+}
+
+pub const SUMMINGS: [&str; 3] = ["sum", "average", "sqrt"];
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Clipping {
+    None,
+    Tanh,
+    Atan,
+}
+
+pub const CLIPPINGS: [&str; 3] = ["none", "tanh", "atan"];
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Configuration {
+    pub buses: usize,
+    pub summing: Summing,
+    pub clipping: Clipping,
+    pub panning: Panning,
+}
+
+impl Configuration {
+    pub fn default() -> Configuration {
+        Self {
+            buses: 0,
+            summing: Summing::Average,
+            clipping: Clipping::None,
+            panning: Panning::None,
+        }
+    }
+
+    pub fn from_backup(backup: &str) -> Result<Configuration, failure::Error> {
+        let mut configuration = Configuration::default();
+
+        for param in backup.split('|') {
+            if let Some((key, value)) = param.split_once('=') {
+                let value = value.trim();
+
+                match key.trim() {
+                    "buses" => {
+                        configuration.buses = value.parse::<usize>().map_err(|e| {
+                            failure::err_msg(format!("{} buses {} : {}", KIND, value, e))
+                        })?
+                    }
+                    "summing" => configuration.summing = Summing::from_index(index_of(&SUMMINGS, value)?),
+                    "clip" => configuration.clipping = Clipping::from_index(index_of(&CLIPPINGS, value)?),
+                    "pan" => configuration.panning = Panning::from_index(index_of(&panner::PANNINGS, value)?),
+                    _ => return Err(failure::err_msg(format!("Unknown {} parameter {}", KIND, key))),
+                }
+            }
+        }
+        Ok(configuration)
+    }
+
+    // Only the values differing from the default are backuped to keep the old sessions unchanged
+    pub fn backup(&self) -> Option<String> {
+        let default = Configuration::default();
+        let mut params = Vec::new();
+
+        if self.buses != default.buses {
+            params.push(format!("buses={}", self.buses));
+        }
+        if self.summing != default.summing {
+            params.push(format!("summing={}", SUMMINGS[self.summing.index()]));
+        }
+        if self.clipping != default.clipping {
+            params.push(format!("clip={}", CLIPPINGS[self.clipping.index()]));
+        }
+        if self.panning != default.panning {
+            params.push(format!("pan={}", panner::PANNINGS[self.panning.index()]));
+        }
+
+        if params.is_empty() {
+            None
+        } else {
+            Some(params.join("|"))
+        }
+    }
+}
+
+#[test]
+fn test_configuration_backup() {
+    assert!(Configuration::default().backup() == None);
+    assert!(Configuration::from_backup("").unwrap() == Configuration::default());
+
+    let configuration = Configuration::from_backup("buses=2|summing=sqrt|clip=tanh|pan=vbap").unwrap();
+    assert!(configuration.buses == 2);
+    assert!(configuration.summing == Summing::SqrtAverage);
+    assert!(configuration.clipping == Clipping::Tanh);
+    assert!(configuration.panning == Panning::Vbap);
+    assert!(configuration.backup() == Some("buses=2|summing=sqrt|clip=tanh|pan=vbap".to_string()));
+}
+
+fn index_of(names: &[&str], name: &str) -> Result<usize, failure::Error> {
+    match names.iter().position(|n| *n == name) {
+        Some(idx) => Ok(idx),
+        None => Err(failure::err_msg(format!("Unknown {} value {}", KIND, name))),
+    }
+}
+
+impl Summing {
+    pub fn from_index(index: usize) -> Summing {
+        match index {
+            0 => Summing::Sum,
+            2 => Summing::SqrtAverage,
+            _ => Summing::Average,
+        }
+    }
+    pub fn index(&self) -> usize {
+        match self {
+            Summing::Sum => 0,
+            Summing::Average => 1,
+            Summing::SqrtAverage => 2,
+        }
+    }
+    fn coefficient(&self, tracks_count: usize) -> f32 {
+        match self {
+            Summing::Sum => 1.,
+            Summing::Average => 1. / tracks_count as f32,
+            Summing::SqrtAverage => 1. / (tracks_count as f32).sqrt(),
+        }
+    }
+}
+
+impl Clipping {
+    pub fn from_index(index: usize) -> Clipping {
+        match index {
+            1 => Clipping::Tanh,
+            2 => Clipping::Atan,
+            _ => Clipping::None,
+        }
+    }
+    pub fn index(&self) -> usize {
+        match self {
+            Clipping::None => 0,
+            Clipping::Tanh => 1,
+            Clipping::Atan => 2,
+        }
+    }
+    fn apply(&self, channels: &mut Vec<Vector>, len: usize) {
+        for ch in channels {
+            match self {
+                Clipping::None => (),
+                Clipping::Tanh => dsp::audioize_buffer_by_tanh(ch, 0, len),
+                Clipping::Atan => dsp::audioize_buffer_by_atan(ch, 0, len),
+            }
+        }
+    }
+}
+
+// The aux buses voices sum the tracks inputs, after their gain, weighted by their send level
+struct Buses {
+    sends_hum_index: Index,
+    return_tracks: Rc<RefCell<Vec<bool>>>,
+}
+
+// A track fed by a talker listening the mixer is a return track so it is not sent to the buses
+fn is_return_track(set: &Set, mixer_id: Id) -> bool {
+    for talk in set.hums()[INPUT_HUM_INDEX].talks() {
+        if talk.talker().is(mixer_id) || talk.talker().depends_of(mixer_id) {
+            return true;
+        }
+    }
+    false
+}
+
+impl Talker for Buses {
+    fn talk(&mut self, base: &TalkerBase, port: usize, tick: i64, len: usize) -> usize {
+        let tracks_ear = base.ear(TRACKS_EAR_INDEX);
+        let send_hum_idx = self.sends_hum_index + port;
+        let return_tracks = self.return_tracks.borrow();
+        let voice_buf = base.voice(port).audio_buffer();
+        let mut ln = len;
+
+        voice_buf[..len].fill(0.);
+
+        for (set_idx, set) in tracks_ear.sets().iter().enumerate() {
+            if return_tracks.get(set_idx).copied().unwrap_or(false) {
+                continue;
+            }
+            ln = tracks_ear.listen_set_hum(tick, ln, set_idx, INPUT_HUM_INDEX);
+            ln = tracks_ear.listen_set_hum(tick, ln, set_idx, GAIN_HUM_INDEX);
+            ln = tracks_ear.listen_set_hum(tick, ln, set_idx, send_hum_idx);
+
+            let in_buf = set.get_hum_audio_buffer(INPUT_HUM_INDEX);
+            let gain_buf = set.get_hum_audio_buffer(GAIN_HUM_INDEX);
+            let send_buf = set.get_hum_cv_buffer(send_hum_idx);
+
+            for i in 0..ln {
+                voice_buf[i] = voice_buf[i] + in_buf[i] * gain_buf[i] * send_buf[i];
+            }
+        }
+        ln
+    }
+}
+
 pub struct Mixer {
     talker: RTalker,
     outputs: Vec<ROutput>,
     is_open: bool,
     record: bool,
+    punch_in: bool,
     buf: Vector,
     tracks_count: usize,
+    configuration: Configuration,
     channels_buffers: Vec<Vector>,
     feedback_buffers: Vec<Vector>,
     audible_tracks: Vec<Index>,
+    stems_outputs: Vec<(Index, ROutput)>,
+    stem_buffers: Vec<Vector>,
+    channels_names: Vec<&'static str>,
+    master_meter: Meter,
+    tracks_meters: Vec<Meter>,
+    panner: Option<Panner>,
+    tracks: Vec<track::Track>,
+    latency: usize,
+    return_tracks: Rc<RefCell<Vec<bool>>>,
 }
 
 pub type RMixer = Rc<RefCell<Mixer>>;
@@ -41,7 +261,9 @@ impl Mixer {
     pub fn new_ref(
         oparent: Option<&RMixer>,
         outputs: Vec<ROutput>,
+        configuration: Configuration,
     ) -> Result<RMixer, failure::Error> {
+        let buses = configuration.buses;
         let mut channels = 0;
         let mut output_idx = usize::MAX;
 
@@ -54,21 +276,41 @@ impl Mixer {
             }
         }
 
+        let sends_tags: Vec<String> = (0..buses).map(|bus_idx| Mixer::bus_tag(bus_idx, "send")).collect();
+
         let mut hums_attributs = vec![
             ("", PortType::Audio, AudioFormat::MIN_AUDIO, AudioFormat::MAX_AUDIO, AudioFormat::DEF_AUDIO, Init::Empty),
             ("gain", PortType::Audio, 0., 1., 1., Init::DefValue),
         ];
 
-        if output_idx < outputs.len() {
-            for chan_name in outputs[output_idx].borrow().channels_names() {
-                hums_attributs.push((chan_name, PortType::Cv, 0., 1., 1., Init::DefValue));
-            }
+        let channels_names = if output_idx < outputs.len() {
+            outputs[output_idx].borrow().channels_names()
         }
         else {
-            hums_attributs.push(("left", PortType::Cv, 0., 1., 1., Init::DefValue));
-            hums_attributs.push(("right", PortType::Cv, 0., 1., 1., Init::DefValue));
             channels = 2;
+            vec!["left", "right"]
+        };
+
+        for chan_name in &channels_names {
+            hums_attributs.push((*chan_name, PortType::Cv, 0., 1., 1., Init::DefValue));
+        }
+
+        for send_tag in &sends_tags {
+            hums_attributs.push((send_tag.as_str(), PortType::Cv, 0., 1., 0., Init::DefValue));
         }
+        let sends_hum_index = CHANNELS_HUM_INDEX + channels;
+        let pan_hum_index = sends_hum_index + buses;
+
+        let opanner = match configuration.panning {
+            Panning::None => None,
+            panning => {
+                for (tag, min, max, def) in Panner::hums_attributs() {
+                    hums_attributs.push((tag, PortType::Cv, min, max, def, Init::DefValue));
+                }
+                Some(Panner::new(&channels_names, panning, pan_hum_index))
+            }
+        };
+
         let stem_track = Set::from_attributs(&hums_attributs)?;
 
         let mut tracks = Vec::new();
@@ -91,6 +333,22 @@ impl Mixer {
                 for hum_idx in CHANNELS_HUM_INDEX..channels_hums_end {
                     track = track.with_hum(hum_idx, |h| Ok(src_track.hums()[hum_idx].with_tag(h.tag())))?;
                 }
+
+                let parent_sends_hum_index = CHANNELS_HUM_INDEX + parent.channels();
+
+                for bus_idx in 0..buses.min(parent.buses()) {
+                    let src_hum_idx = parent_sends_hum_index + bus_idx;
+                    track = track.with_hum(sends_hum_index + bus_idx, |h| Ok(src_track.hums()[src_hum_idx].with_tag(h.tag())))?;
+                }
+
+                if opanner.is_some() && parent.panner.is_some() {
+                    let parent_pan_hum_index = parent_sends_hum_index + parent.buses();
+
+                    for pan_idx in 0..panner::HUMS_COUNT {
+                        let src_hum_idx = parent_pan_hum_index + pan_idx;
+                        track = track.with_hum(pan_hum_index + pan_idx, |_| Ok(src_track.hums()[src_hum_idx].clone()))?;
+                    }
+                }
                 tracks.push(track);
             }
             audible_tracks.extend_from_slice(&parent.audible_tracks);
@@ -104,26 +362,45 @@ impl Mixer {
 
         base.add_ear(Ear::new(Some("Tracks"), true, Some(stem_track), Some(tracks)));
 
+        for bus_idx in 0..buses {
+            base.add_audio_voice(Some(&Mixer::bus_tag(bus_idx, "bus")), 0.);
+        }
+
         let chunk_size = AudioFormat::chunk_size();
 
         let mut channels_buffers = Vec::with_capacity(channels);
         let mut feedback_buffers = Vec::with_capacity(channels);
+        let mut stem_buffers = Vec::with_capacity(channels);
 
         for _ in 0..channels {
             channels_buffers.push(vec![0.; chunk_size]);
             feedback_buffers.push(vec![0.; chunk_size]);
+            stem_buffers.push(vec![0.; chunk_size]);
         }
 
+        let return_tracks = Rc::new(RefCell::new(Vec::new()));
+
         Ok(Rc::new(RefCell::new(Self {
-            talker: MuteTalker::new(base),
+            talker: rtalker!(ctalker!(base, Buses { sends_hum_index, return_tracks: return_tracks.clone() })),
             outputs,
             is_open: false,
             record: false,
+            punch_in: true,
             buf: vec![0.; AudioFormat::chunk_size()],
             tracks_count,
+            configuration,
             channels_buffers,
             feedback_buffers,
             audible_tracks,
+            stems_outputs: Vec::new(),
+            stem_buffers,
+            master_meter: Meter::new(&channels_names),
+            channels_names,
+            tracks_meters: Vec::new(),
+            panner: opanner,
+            tracks: Vec::new(),
+            latency: 0,
+            return_tracks,
         })))
     }
 
@@ -131,6 +408,10 @@ impl Mixer {
         KIND
     }
 
+    fn bus_tag(bus_idx: Index, prefix: &str) -> String {
+        format!("{}{}", prefix, bus_idx + 1)
+    }
+
     pub fn initialize(&mut self) {
         let tracks_ear = &self.talker.ear(TRACKS_EAR_INDEX);
 
@@ -141,6 +422,16 @@ impl Mixer {
         }
     }
 
+    // The tracks dependencies are only looked for when the band graph changes
+    pub fn graph_changed(&mut self) {
+        let mixer_id = self.id();
+        let tracks_ear = self.talker.ear(TRACKS_EAR_INDEX);
+
+        *self.return_tracks.borrow_mut() = tracks_ear.sets().iter().map(|set| is_return_track(set, mixer_id)).collect();
+
+        self.compensate_latency();
+    }
+
     pub fn identifier(&self) -> &RIdentifier {
         self.talker.identifier()
     }
@@ -158,6 +449,23 @@ impl Mixer {
         self.record = active;
         Ok(())
     }
+    // While recording, the outputs are only written when punched in
+    pub fn set_punch_in(&mut self, active: bool) {
+        self.punch_in = active;
+    }
+
+    pub fn buses(&self) -> usize {
+        self.configuration.buses
+    }
+
+    pub fn configuration(&self) -> &Configuration {
+        &self.configuration
+    }
+
+    pub fn set_summing(&mut self, summing: Summing, clipping: Clipping) {
+        self.configuration.summing = summing;
+        self.configuration.clipping = clipping;
+    }
 
     pub fn channels(&self) -> usize {
         self.channels_buffers.len()
@@ -183,13 +491,69 @@ impl Mixer {
         self.audible_tracks = audible_tracks;
         Ok(())
     }
+    pub fn latency(&self) -> usize {
+        self.latency
+    }
+
+    // Each track input is delayed to be aligned with the track of greatest latency.
+    // The graph is walked so this is only called on graph change and opening, out of the audio path
+    fn compensate_latency(&mut self) {
+        let tracks_ear = self.talker.ear(TRACKS_EAR_INDEX);
+        let mut latencies = HashMap::new();
+
+        let tracks_latencies: Vec<usize> = tracks_ear.sets().iter()
+            .map(|set| set.hums()[INPUT_HUM_INDEX].talks().iter()
+                .map(|talk| talk.talker().path_latency(&mut latencies))
+                .max().unwrap_or(0))
+            .collect();
+
+        self.latency = tracks_latencies.iter().copied().max().unwrap_or(0);
+        self.tracks.resize_with(tracks_latencies.len(), Default::default);
+
+        for (track, track_latency) in self.tracks.iter_mut().zip(tracks_latencies) {
+            track.delay.set_len(self.latency - track_latency);
+        }
+    }
+
     pub fn is_open(&self) -> bool {
         self.is_open
     }
+    pub fn levels(&mut self) -> MixerLevels {
+        MixerLevels {
+            mixer_id: self.id(),
+            master: self.master_meter.levels(),
+            tracks: self.tracks_meters.iter_mut().map(|m| m.levels()).collect(),
+            latency: self.latency,
+        }
+    }
+
+    pub fn loudness_summary(&self) -> LoudnessSummary {
+        LoudnessSummary {
+            mixer_id: self.id(),
+            integrated: self.master_meter.integrated_loudness(),
+            true_peak: self.master_meter.max_true_peak(),
+        }
+    }
+
     pub fn open(&mut self) -> Result<(), failure::Error> {
+        self.master_meter.reset();
+
+        // The plugins having run since the last graph change can report another latency
+        self.compensate_latency();
+
+        for meter in self.tracks_meters.iter_mut() {
+            meter.reset();
+        }
 
         if self.record {
+            self.create_stems_outputs()?;
+
             for o in &self.outputs {
+                if !o.borrow().stems() {
+                    o.borrow_mut().open()?;
+                }
+            }
+            for (_, o) in &self.stems_outputs {
                 o.borrow_mut().open()?;
             }
         }
@@ -201,7 +565,7 @@ impl Mixer {
     pub fn pause(&mut self) -> Result<(), failure::Error> {
 
         if self.record {
-            for o in &self.outputs {
+            for o in self.outputs.iter().chain(self.stems_outputs.iter().map(|(_, o)| o)) {
                 o.borrow_mut().pause()?;
             }
         }
@@ -211,7 +575,7 @@ impl Mixer {
     pub fn run(&mut self) -> Result<(), failure::Error> {
 
         if self.record {
-            for o in &self.outputs {
+            for o in self.outputs.iter().chain(self.stems_outputs.iter().map(|(_, o)| o)) {
                 o.borrow_mut().run()?;
             }
         }
@@ -222,9 +586,15 @@ impl Mixer {
         
         if self.record {
             for o in &self.outputs {
+                if !o.borrow().stems() {
+                    o.borrow_mut().close()?;
+                }
+            }
+            for (_, o) in &self.stems_outputs {
                 o.borrow_mut().close()?;
             }
         }
+        self.stems_outputs.clear();
 
         self.is_open = false;
         Ok(())
@@ -237,7 +607,9 @@ impl Mixer {
     ) -> Result<usize, failure::Error> {
         let mut ln = self.talker.listen(tick, len);
 
-        let tracks_ear = &self.talker.ear(TRACKS_EAR_INDEX);
+        // The talker is held apart from self for the stems writing
+        let talker = self.talker.clone();
+        let tracks_ear = talker.ear(TRACKS_EAR_INDEX);
 
         let tracks_count = tracks_ear.sets_len();
         self.tracks_count = tracks_count;
@@ -246,47 +618,71 @@ impl Mixer {
             return Ok(0);
         }
 
+        self.tracks.resize_with(tracks_count, Default::default);
+
         let buf = &mut self.buf;
         let channels = &mut self.channels_buffers;
+        let track_channels = &mut self.stem_buffers;
+        let opanner = self.panner.as_ref();
+        let tracks = &mut self.tracks;
 
-        ln = tracks_ear.visit_set(
-            0,
-            |set, ln| Ok(track::set(set, tick, buf, ln, channels)),
-            ln,
-        )?;
+        while self.tracks_meters.len() < tracks_count {
+            self.tracks_meters.push(Meter::new(&self.channels_names));
+        }
+        self.tracks_meters.truncate(tracks_count);
 
-        for i in 1..tracks_count {
+        // Each track is metered before being summed
+        for i in 0..tracks_count {
             ln = tracks_ear.visit_set(
                 i,
-                |set, ln| Ok(track::add(set, tick, buf, ln, channels)),
+                |set, ln| Ok(track::set(set, tick, buf, ln, track_channels, opanner, &mut tracks[i])),
                 ln,
             )?;
+            self.tracks_meters[i].process(track_channels, ln);
+
+            for (ch, trk_ch) in channels.iter_mut().zip(track_channels.iter()) {
+                if i == 0 {
+                    ch[..ln].copy_from_slice(&trk_ch[..ln]);
+                } else {
+                    for j in 0..ln {
+                        ch[j] = ch[j] + trk_ch[j];
+                    }
+                }
+            }
         }
 
-        let master_volume_buf = self.talker.ear_cv_buffer(VOLUME_EAR_INDEX);
-        let average_coef = 1. / tracks_count as f32;
+        let master_volume_buf = talker.ear_cv_buffer(VOLUME_EAR_INDEX);
+        let summing_coef = self.configuration.summing.coefficient(tracks_count);
 
         for ch in &mut *channels {
             for i in 0..ln {
-                ch[i] = ch[i] * master_volume_buf[i] * average_coef;
+                ch[i] = ch[i] * master_volume_buf[i] * summing_coef;
             }
         }
+        self.configuration.clipping.apply(channels, ln);
+        self.master_meter.process(channels, ln);
 
-        if self.record {
+        if self.record && self.punch_in {
             for o in &self.outputs {
-                o.borrow_mut().write(channels, ln)?;
+                if !o.borrow().stems() {
+                    o.borrow_mut().write(channels, ln)?;
+                }
             }
+            self.write_stems(tick, ln, None)?;
         }
 
         // Compute feedback
         if self.audible_tracks.len() < tracks_count && !self.audible_tracks.is_empty() {
+            let buf = &mut self.buf;
             let channels = &mut self.feedback_buffers;
+            let opanner = self.panner.as_ref();
+            let tracks = &mut self.tracks;
             let trk_idx = self.audible_tracks[0];
 
             if trk_idx < tracks_count {
                 let _ = tracks_ear.visit_set(
                     trk_idx,
-                    |set, ln| Ok(track::set(set, tick, buf, ln, channels)),
+                    |set, ln| Ok(track::set(set, tick, buf, ln, channels, opanner, &mut tracks[trk_idx])),
                     ln,
                 )?;
             }
@@ -297,7 +693,7 @@ impl Mixer {
                 if trk_idx < tracks_count {
                     let _ = tracks_ear.visit_set(
                         trk_idx,
-                        |set, ln| Ok(track::add(set, tick, buf, ln, channels)),
+                        |set, ln| Ok(track::add(set, tick, buf, ln, channels, opanner, &mut tracks[trk_idx])),
                         ln,
                     )?;
                 }
@@ -305,9 +701,10 @@ impl Mixer {
 
             for ch in &mut *channels {
                 for i in 0..ln {
-                    ch[i] = ch[i] * master_volume_buf[i] * average_coef;
+                    ch[i] = ch[i] * master_volume_buf[i] * summing_coef;
                 }
             }
+            self.configuration.clipping.apply(channels, ln);
         }
 
         Ok(ln)
@@ -326,10 +723,13 @@ impl Mixer {
             }
         }
 
-        if record {
+        if record && self.punch_in {
             for o in &self.outputs {
-                o.borrow_mut().write(&self.channels_buffers, ln)?;
+                if !o.borrow().stems() {
+                    o.borrow_mut().write(&self.channels_buffers, ln)?;
+                }
             }
+            self.write_stems(tick, ln, Some(&fadeout_tab))?;
         }
 
         self.record = record;
@@ -338,6 +738,94 @@ impl Mixer {
     }
 }
 
+impl Mixer {
+    fn track_name(&self, trk_idx: Index) -> String {
+        let tracks_ear = self.talker.ear(TRACKS_EAR_INDEX);
+
+        if let Some(set) = tracks_ear.sets().get(trk_idx) {
+            for talk in set.hums()[INPUT_HUM_INDEX].talks() {
+                if !talk.talker().is_hidden() {
+                    let name: String = talk.talker().name().chars()
+                        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                        .collect();
+                    return format!("{}_{}", trk_idx + 1, name);
+                }
+            }
+        }
+        format!("track{}", trk_idx + 1)
+    }
+
+    fn create_stems_outputs(&mut self) -> Result<(), failure::Error> {
+        self.stems_outputs.clear();
+
+        let tracks_count = self.talker.ear(TRACKS_EAR_INDEX).sets_len();
+
+        for routput in &self.outputs {
+            let output = routput.borrow();
+
+            if output.stems() {
+                for trk_idx in 0..tracks_count {
+                    let file_path = util::filename_with_suffix(output.file_path(), &self.track_name(trk_idx));
+
+                    let stem_output = AudioFileOutput::new_ref(
+                        output.codec_name(),
+                        AudioFormat::sample_rate(),
+                        output.sample_rate(),
+                        output.channel_layout(),
+                        &file_path,
+                        false,
+                        output.sample_format(),
+                        output.dither(),
+                        output.tags())?;
+
+                    self.stems_outputs.push((trk_idx, stem_output));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Write each track, after its gain, channels hums and panning, to its stems outputs
+    fn write_stems(&mut self, tick: i64, len: usize, ofade_tab: Option<&Vec<f32>>) -> Result<(), failure::Error> {
+        if self.stems_outputs.is_empty() {
+            return Ok(());
+        }
+        let tracks_ear = &self.talker.ear(TRACKS_EAR_INDEX);
+        self.tracks.resize_with(tracks_ear.sets_len(), Default::default);
+
+        let buf = &mut self.buf;
+        let channels = &mut self.stem_buffers;
+        let opanner = self.panner.as_ref();
+        let tracks = &mut self.tracks;
+        let mut current_trk_idx = usize::MAX;
+        let mut ln = len;
+
+        for (trk_idx, output) in &self.stems_outputs {
+            if *trk_idx >= tracks_ear.sets_len() {
+                continue;
+            }
+            if *trk_idx != current_trk_idx {
+                ln = tracks_ear.visit_set(
+                    *trk_idx,
+                    |set, ln| Ok(track::set(set, tick, buf, ln, channels, opanner, &mut tracks[*trk_idx])),
+                    len,
+                )?;
+
+                if let Some(fade_tab) = ofade_tab {
+                    for ch in &mut *channels {
+                        for i in 0..ln {
+                            ch[i] = ch[i] * fade_tab[i];
+                        }
+                    }
+                }
+                current_trk_idx = *trk_idx;
+            }
+            output.borrow_mut().write(channels, ln)?;
+        }
+        Ok(())
+    }
+}
+
 impl Identifiable for Mixer {
     fn id(&self) -> Id {
         self.talker.id()
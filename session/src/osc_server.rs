@@ -0,0 +1,430 @@
+use std::convert::TryFrom;
+use std::net::{SocketAddr, UdpSocket};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use talker::identifier::{Id, Index};
+
+use crate::band::Operation;
+use crate::event_bus::Notification;
+
+const RECEIVE_TIMEOUT: u64 = 100;
+const PACKET_SIZE: usize = 65536;
+const BUNDLE_TAG: &str = "#bundle";
+const MAX_CLIENTS: usize = 16;
+
+// Addresses of the messages registering the sender to the notifications
+pub const SUBSCRIBE_ADDRESS: &str = "/subscribe";
+pub const UNSUBSCRIBE_ADDRESS: &str = "/unsubscribe";
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Argument {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    Bool(bool),
+}
+
+impl Argument {
+    fn to_f32(&self) -> Option<f32> {
+        match self {
+            Argument::Int(v) => Some(*v as f32),
+            Argument::Long(v) => Some(*v as f32),
+            Argument::Float(v) => Some(*v),
+            Argument::Double(v) => Some(*v as f32),
+            Argument::Bool(v) => Some(if *v { 1. } else { 0. }),
+            Argument::String(_) => None,
+        }
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        match self {
+            Argument::Int(v) => Some(*v as i64),
+            Argument::Long(v) => Some(*v),
+            Argument::Float(v) => Some(v.round() as i64),
+            Argument::Double(v) => Some(v.round() as i64),
+            _ => None,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Message {
+    pub address: String,
+    pub arguments: Vec<Argument>,
+}
+
+impl Message {
+    pub fn new(address: &str, arguments: Vec<Argument>) -> Message {
+        Self { address: address.to_string(), arguments }
+    }
+
+    fn argument(&self) -> Result<&Argument, failure::Error> {
+        self.arguments.first()
+            .ok_or(failure::err_msg(format!("OSC message {} has no argument", self.address)))
+    }
+}
+
+// OSC 1.0 encoding : strings are null terminated and padded to 4 bytes, numbers are big endian
+fn pad(bytes: &mut Vec<u8>) {
+    bytes.push(0);
+
+    while bytes.len() % 4 != 0 {
+        bytes.push(0);
+    }
+}
+
+fn encode_string(s: &str, bytes: &mut Vec<u8>) {
+    bytes.extend_from_slice(s.as_bytes());
+    pad(bytes);
+}
+
+pub fn encode(message: &Message) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    encode_string(&message.address, &mut bytes);
+
+    let mut type_tags = String::from(",");
+    let mut data = Vec::new();
+
+    for argument in &message.arguments {
+        match argument {
+            Argument::Int(v) => {
+                type_tags.push('i');
+                data.extend_from_slice(&v.to_be_bytes());
+            }
+            Argument::Long(v) => {
+                type_tags.push('h');
+                data.extend_from_slice(&v.to_be_bytes());
+            }
+            Argument::Float(v) => {
+                type_tags.push('f');
+                data.extend_from_slice(&v.to_be_bytes());
+            }
+            Argument::Double(v) => {
+                type_tags.push('d');
+                data.extend_from_slice(&v.to_be_bytes());
+            }
+            Argument::String(s) => {
+                type_tags.push('s');
+                encode_string(s, &mut data);
+            }
+            Argument::Bool(v) => type_tags.push(if *v { 'T' } else { 'F' }),
+        }
+    }
+    encode_string(&type_tags, &mut bytes);
+    bytes.extend(data);
+    bytes
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], failure::Error> {
+        if len > self.bytes.len() - self.position {
+            return Err(failure::err_msg("OSC packet is truncated"));
+        }
+        let slice = &self.bytes[self.position..self.position + len];
+        self.position += len;
+        Ok(slice)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], failure::Error> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn string(&mut self) -> Result<String, failure::Error> {
+        let rest = &self.bytes[self.position..];
+        let len = rest.iter().position(|b| *b == 0)
+            .ok_or(failure::err_msg("OSC string is not terminated"))?;
+
+        let s = String::from_utf8_lossy(&rest[..len]).to_string();
+        self.take((len + 4) & !3)?;
+        Ok(s)
+    }
+}
+
+fn decode_message(bytes: &[u8]) -> Result<Message, failure::Error> {
+    let mut reader = Reader { bytes, position: 0 };
+    let address = reader.string()?;
+
+    // The type tags string can be omitted by old implementations
+    if reader.position == bytes.len() {
+        return Ok(Message { address, arguments: Vec::new() });
+    }
+    let type_tags = reader.string()?;
+    let mut arguments = Vec::new();
+
+    for tag in type_tags.chars().skip_while(|c| *c == ',') {
+        let argument = match tag {
+            'i' => Argument::Int(i32::from_be_bytes(reader.take_array()?)),
+            'h' => Argument::Long(i64::from_be_bytes(reader.take_array()?)),
+            'f' => Argument::Float(f32::from_be_bytes(reader.take_array()?)),
+            'd' => Argument::Double(f64::from_be_bytes(reader.take_array()?)),
+            's' | 'S' => Argument::String(reader.string()?),
+            'T' => Argument::Bool(true),
+            'F' => Argument::Bool(false),
+            _ => return Err(failure::err_msg(format!("OSC message {} type {} is not supported", address, tag))),
+        };
+        arguments.push(argument);
+    }
+    Ok(Message { address, arguments })
+}
+
+// A packet is a message or a bundle of packets
+pub fn decode(bytes: &[u8]) -> Result<Vec<Message>, failure::Error> {
+    let mut reader = Reader { bytes, position: 0 };
+
+    if bytes.starts_with(BUNDLE_TAG.as_bytes()) {
+        reader.string()?;
+        // The time tag is ignored, the bundle elements are run at reception
+        reader.take(8)?;
+
+        let mut messages = Vec::new();
+
+        while reader.position < bytes.len() {
+            let size = usize::try_from(i32::from_be_bytes(reader.take_array()?))
+                .map_err(|_| failure::err_msg("OSC bundle element size is negative"))?;
+            messages.extend(decode(reader.take(size)?)?);
+        }
+        Ok(messages)
+    } else {
+        Ok(vec![decode_message(bytes)?])
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Command {
+    Play,
+    Pause,
+    Stop,
+    Record,
+    Seek(i64),
+    ModifyBand(Operation),
+}
+
+fn parse_index<T: FromStr>(address: &str, s: &str) -> Result<T, failure::Error> {
+    T::from_str(s).map_err(|_| failure::err_msg(format!("OSC address {} index {} is invalid", address, s)))
+}
+
+pub fn command(message: &Message) -> Result<Option<Command>, failure::Error> {
+    let address = message.address.as_str();
+    let parts: Vec<&str> = address.split('/').filter(|p| !p.is_empty()).collect();
+
+    // The transport buttons of the control surfaces send 1 on press and 0 on release
+    let released = message.arguments.first().and_then(|a| a.to_f32()) == Some(0.);
+
+    let command = match parts.as_slice() {
+        ["transport", "play"] if !released => Command::Play,
+        ["transport", "pause"] if !released => Command::Pause,
+        ["transport", "stop"] if !released => Command::Stop,
+        ["transport", "record"] if !released => Command::Record,
+        ["transport", "play"] | ["transport", "pause"] | ["transport", "stop"] | ["transport", "record"] => return Ok(None),
+        ["transport", "seek"] => {
+            let tick = message.argument()?.to_i64()
+                .ok_or(failure::err_msg(format!("OSC message {} tick is not a number", address)))?;
+            Command::Seek(tick)
+        }
+        ["talker", talker_id, "ear", ear_idx, "set", set_idx, "hum", hum_idx] => {
+            let value = message.argument()?.to_f32()
+                .ok_or(failure::err_msg(format!("OSC message {} value is not a number", address)))?;
+
+            Command::ModifyBand(Operation::SetEarHumValue(
+                parse_index::<Id>(address, talker_id)?,
+                parse_index::<Index>(address, ear_idx)?,
+                parse_index::<Index>(address, set_idx)?,
+                parse_index::<Index>(address, hum_idx)?,
+                value,
+            ))
+        }
+        _ => return Err(failure::err_msg(format!("OSC address {} is unknown", address))),
+    };
+    Ok(Some(command))
+}
+
+// Message sent to the clients for a notification
+pub fn notification_message(notification: &Notification) -> Option<Message> {
+    match notification {
+        Notification::State(state) => Some(Message::new("/transport/state", vec![Argument::String(state.to_string())])),
+        Notification::Tick(tick) => Some(Message::new("/transport/tick", vec![Argument::Long(*tick)])),
+        Notification::TimeRange(start, end) => Some(Message::new("/transport/range", vec![Argument::Long(*start), Argument::Long(*end)])),
+        Notification::Tempo(tempo) => Some(Message::new("/tempo", vec![Argument::Float(*tempo)])),
+        Notification::End => Some(Message::new("/transport/end", Vec::new())),
+        _ => None,
+    }
+}
+
+// The oldest client is dropped beyond the maximum count
+fn subscribe(clients: &mut Vec<SocketAddr>, client: SocketAddr) {
+    if !clients.contains(&client) {
+        clients.push(client);
+
+        if clients.len() > MAX_CLIENTS {
+            clients.remove(0);
+        }
+    }
+}
+
+// UDP server receiving the commands on a thread. The commands are run by the session owner.
+// The notifications are broadcasted to the addresses that sent a subscribe message
+pub struct OscServer {
+    socket: UdpSocket,
+    commands: Receiver<Command>,
+    clients: Arc<Mutex<Vec<SocketAddr>>>,
+    running: Arc<AtomicBool>,
+}
+
+impl OscServer {
+    pub fn new(address: &str, port: u16) -> Result<OscServer, failure::Error> {
+        let socket = UdpSocket::bind((address, port))
+            .map_err(|e| failure::err_msg(format!("OSC server {}:{} binding failed : {}", address, port, e)))?;
+
+        let receiver_socket = socket.try_clone()?;
+        receiver_socket.set_read_timeout(Some(Duration::from_millis(RECEIVE_TIMEOUT)))?;
+
+        let (sender, commands): (Sender<Command>, Receiver<Command>) = mpsc::channel();
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let receiver_clients = clients.clone();
+        let receiver_running = running.clone();
+
+        let _join_handle = thread::spawn(move || {
+            let mut buffer = vec![0; PACKET_SIZE];
+
+            while receiver_running.load(Ordering::Relaxed) {
+                let (len, client) = match receiver_socket.recv_from(&mut buffer) {
+                    Ok(received) => received,
+                    Err(_) => continue,
+                };
+
+                let messages = match decode(&buffer[..len]) {
+                    Ok(messages) => messages,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        continue;
+                    }
+                };
+
+                for message in messages {
+                    if message.address == SUBSCRIBE_ADDRESS || message.address == UNSUBSCRIBE_ADDRESS {
+                        if let Ok(mut clients) = receiver_clients.lock() {
+                            if message.address == SUBSCRIBE_ADDRESS {
+                                subscribe(&mut clients, client);
+                            } else {
+                                clients.retain(|c| *c != client);
+                            }
+                        }
+                        continue;
+                    }
+                    match command(&message) {
+                        Ok(Some(command)) => {
+                            if sender.send(command).is_err() {
+                                return;
+                            }
+                        }
+                        Ok(None) => (),
+                        Err(e) => eprintln!("{}", e),
+                    }
+                }
+            }
+        });
+
+        Ok(Self { socket, commands, clients, running })
+    }
+
+    pub fn receive_commands(&self) -> Vec<Command> {
+        self.commands.try_iter().collect()
+    }
+
+    pub fn broadcast(&self, notification: &Notification) {
+        if let Some(message) = notification_message(notification) {
+            let packet = encode(&message);
+
+            if let Ok(clients) = self.clients.lock() {
+                for client in clients.iter() {
+                    if let Err(e) = self.socket.send_to(&packet, client) {
+                        eprintln!("OSC message {} sending to {} failed : {}", message.address, client, e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for OscServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn test_osc_codec() {
+    let message = Message::new("/transport/seek", vec![
+        Argument::Int(12), Argument::Long(-3), Argument::Float(0.5), Argument::String("abcd".to_string()), Argument::Bool(true),
+    ]);
+    let bytes = encode(&message);
+    assert!(bytes.len() % 4 == 0);
+    assert!(decode(&bytes).unwrap() == vec![message.clone()]);
+
+    let mut bundle = b"#bundle\0\0\0\0\0\0\0\0\x01".to_vec();
+    bundle.extend_from_slice(&(bytes.len() as i32).to_be_bytes());
+    bundle.extend_from_slice(&bytes);
+    assert!(decode(&bundle).unwrap() == vec![message]);
+
+    assert!(decode(b"/transport/pl").is_err());
+
+    let mut truncated_bundle = b"#bundle\0\0\0\0\0\0\0\0\x01".to_vec();
+    truncated_bundle.extend_from_slice(&(bytes.len() as i32 + 4).to_be_bytes());
+    truncated_bundle.extend_from_slice(&bytes);
+    assert!(decode(&truncated_bundle).is_err());
+    assert!(decode(&truncated_bundle[..truncated_bundle.len() - 2]).is_err());
+
+    let mut negative_size_bundle = b"#bundle\0\0\0\0\0\0\0\0\x01".to_vec();
+    negative_size_bundle.extend_from_slice(&(-1i32).to_be_bytes());
+    negative_size_bundle.extend_from_slice(&bytes);
+    assert!(decode(&negative_size_bundle).is_err());
+}
+
+#[test]
+fn test_osc_command() {
+    let play = Message::new("/transport/play", vec![Argument::Float(1.)]);
+    assert!(command(&play).unwrap() == Some(Command::Play));
+
+    let release = Message::new("/transport/play", vec![Argument::Float(0.)]);
+    assert!(command(&release).unwrap() == None);
+
+    let seek = Message::new("/transport/seek", vec![Argument::Int(44100)]);
+    assert!(command(&seek).unwrap() == Some(Command::Seek(44100)));
+
+    let hum = Message::new("/talker/7/ear/1/set/0/hum/2", vec![Argument::Float(0.25)]);
+    assert!(command(&hum).unwrap() == Some(Command::ModifyBand(Operation::SetEarHumValue(7, 1, 0, 2, 0.25))));
+
+    assert!(command(&Message::new("/talker/x/ear/1/set/0/hum/2", vec![Argument::Float(0.25)])).is_err());
+    assert!(command(&Message::new("/unknown", Vec::new())).is_err());
+}
+
+#[test]
+fn test_osc_subscription() {
+    let mut clients = Vec::new();
+    let client = SocketAddr::from(([127, 0, 0, 1], 9000));
+
+    subscribe(&mut clients, client);
+    subscribe(&mut clients, client);
+    assert!(clients == vec![client]);
+
+    for port in 0..MAX_CLIENTS as u16 {
+        subscribe(&mut clients, SocketAddr::from(([127, 0, 0, 1], port)));
+    }
+    assert!(clients.len() == MAX_CLIENTS && !clients.contains(&client));
+}
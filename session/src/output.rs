@@ -4,9 +4,11 @@ use std::rc::Rc;
 
 extern crate failure;
 
+use audiofile::writer::{Dither, SampleFormat};
 use talker::identifier::{Identifier, RIdentifier};
 
 use crate::audio_data::Vector;
+use crate::audiofile_output::Tags;
 
 pub const KIND: &str = "output";
 
@@ -50,6 +52,23 @@ pub trait Output {
         EMPTY_STR
     }
 
+    // Each track of the mixer is written to its own output instead of the mix
+    fn stems(&self) -> bool {
+        false
+    }
+
+    fn sample_format(&self) -> SampleFormat {
+        SampleFormat::Auto
+    }
+
+    fn dither(&self) -> Dither {
+        Dither::None
+    }
+
+    fn tags(&self) -> Tags {
+        Tags::default()
+    }
+
     fn open(&mut self) -> Result<(), failure::Error>;
 
     fn write(
@@ -0,0 +1,348 @@
+use std::f32::consts::PI;
+
+use talker::ear::Set;
+use talker::identifier::Index;
+
+use crate::channel;
+
+pub const AZIMUTH_HUM_INDEX: Index = 0;
+pub const ELEVATION_HUM_INDEX: Index = 1;
+pub const WIDTH_HUM_INDEX: Index = 2;
+pub const HUMS_COUNT: usize = 3;
+
+// At full width, the source is spread over a half circle
+const MAX_SPREAD: f32 = 90.;
+const EPSILON: f32 = 1e-4;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Panning {
+    None,
+    ConstantPower,
+    Vbap,
+}
+
+pub const PANNINGS: [&str; 3] = ["none", "power", "vbap"];
+
+impl Panning {
+    pub fn from_index(index: usize) -> Panning {
+        match index {
+            1 => Panning::ConstantPower,
+            2 => Panning::Vbap,
+            _ => Panning::None,
+        }
+    }
+    pub fn index(&self) -> usize {
+        match self {
+            Panning::None => 0,
+            Panning::ConstantPower => 1,
+            Panning::Vbap => 2,
+        }
+    }
+}
+
+fn direction(azimuth: f32, elevation: f32) -> [f32; 3] {
+    let az = azimuth * PI / 180.;
+    let el = elevation * PI / 180.;
+    [el.cos() * az.sin(), el.cos() * az.cos(), el.sin()]
+}
+
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + 180.).rem_euclid(360.) - 180.
+}
+
+fn normalize_power(gains: &mut Vec<f32>) {
+    let power: f32 = gains.iter().map(|g| g * g).sum();
+
+    if power > 0. {
+        let coef = 1. / power.sqrt();
+
+        for g in gains.iter_mut() {
+            *g *= coef;
+        }
+    }
+}
+
+fn invert_3x3(m: &[[f32; 3]; 3]) -> Option<[[f32; 3]; 3]> {
+    let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+
+    if det.abs() < EPSILON {
+        return None;
+    }
+    let d = 1. / det;
+
+    Some([
+        [
+            (m[1][1] * m[2][2] - m[1][2] * m[2][1]) * d,
+            (m[0][2] * m[2][1] - m[0][1] * m[2][2]) * d,
+            (m[0][1] * m[1][2] - m[0][2] * m[1][1]) * d,
+        ],
+        [
+            (m[1][2] * m[2][0] - m[1][0] * m[2][2]) * d,
+            (m[0][0] * m[2][2] - m[0][2] * m[2][0]) * d,
+            (m[0][2] * m[1][0] - m[0][0] * m[1][2]) * d,
+        ],
+        [
+            (m[1][0] * m[2][1] - m[1][1] * m[2][0]) * d,
+            (m[0][1] * m[2][0] - m[0][0] * m[2][1]) * d,
+            (m[0][0] * m[1][1] - m[0][1] * m[1][0]) * d,
+        ],
+    ])
+}
+
+// Speakers set with its inverted vector base
+struct Base {
+    speakers: Vec<usize>,
+    inverse: [[f32; 3]; 3],
+}
+
+impl Base {
+    fn gains(&self, source: &[f32; 3]) -> [f32; 3] {
+        let mut gains = [0.; 3];
+
+        for (k, g) in gains.iter_mut().enumerate() {
+            for d in 0..3 {
+                *g += source[d] * self.inverse[d][k];
+            }
+        }
+        gains
+    }
+}
+
+pub struct Panner {
+    panning: Panning,
+    hum_index: Index,
+    channels: usize,
+    // Horizontal speakers sorted by azimuth
+    ring: Vec<(usize, f32)>,
+    bases: Vec<Base>,
+}
+
+impl Panner {
+    pub fn new(channels_names: &Vec<&str>, panning: Panning, hum_index: Index) -> Panner {
+        let mut positions = Vec::new();
+
+        for (idx, name) in channels_names.iter().enumerate() {
+            if let Some(position) = channel::Layout::channel_position(name) {
+                positions.push((idx, position));
+            }
+        }
+
+        let mut ring: Vec<(usize, f32)> = positions.iter()
+            .filter(|(_, (_, el))| el.abs() < EPSILON)
+            .map(|(idx, (az, _))| (*idx, *az))
+            .collect();
+        ring.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let mut bases = Vec::new();
+
+        if panning == Panning::Vbap {
+            let elevated = positions.iter().any(|(_, (_, el))| el.abs() >= EPSILON);
+
+            if elevated {
+                let n = positions.len();
+
+                for i in 0..n {
+                    for j in (i + 1)..n {
+                        for k in (j + 1)..n {
+                            let (si, (azi, eli)) = positions[i];
+                            let (sj, (azj, elj)) = positions[j];
+                            let (sk, (azk, elk)) = positions[k];
+
+                            let m = [direction(azi, eli), direction(azj, elj), direction(azk, elk)];
+
+                            if let Some(inverse) = invert_3x3(&m) {
+                                bases.push(Base { speakers: vec![si, sj, sk], inverse });
+                            }
+                        }
+                    }
+                }
+            } else {
+                // Horizontal only layouts are panned between adjacent speakers pairs,
+                // the third base vector being the vertical axis
+                let n = ring.len();
+
+                let pairs = if n > 2 { n } else { n / 2 };
+
+                for i in 0..pairs {
+                    let (si, azi) = ring[i];
+                    let (sj, azj) = ring[(i + 1) % n];
+
+                    let m = [direction(azi, 0.), direction(azj, 0.), [0., 0., 1.]];
+
+                    if let Some(inverse) = invert_3x3(&m) {
+                        bases.push(Base { speakers: vec![si, sj], inverse });
+                    }
+                }
+            }
+        }
+
+        Self {
+            panning,
+            hum_index,
+            channels: channels_names.len(),
+            ring,
+            bases,
+        }
+    }
+
+    pub fn hums_attributs() -> Vec<(&'static str, f32, f32, f32)> {
+        vec![
+            ("azimuth", -180., 180., 0.),
+            ("elevation", -90., 90., 0.),
+            ("width", 0., 1., 0.),
+        ]
+    }
+
+    pub fn gains(&self, azimuth: f32, elevation: f32, width: f32) -> Vec<f32> {
+        let mut gains = vec![0.; self.channels];
+        let spread = width.max(0.).min(1.) * MAX_SPREAD;
+
+        let sources = if spread > EPSILON {
+            vec![azimuth - spread, azimuth, azimuth + spread]
+        } else {
+            vec![azimuth]
+        };
+
+        for source_azimuth in sources {
+            let mut source_gains = match self.panning {
+                Panning::Vbap if !self.bases.is_empty() => self.vbap_gains(source_azimuth, elevation),
+                _ => self.constant_power_gains(source_azimuth),
+            };
+            // A source out of the speakers bases falls back to the horizontal ring
+            if source_gains.iter().all(|g| *g == 0.) {
+                source_gains = self.constant_power_gains(source_azimuth);
+            }
+            normalize_power(&mut source_gains);
+
+            for (g, sg) in gains.iter_mut().zip(source_gains) {
+                *g += sg;
+            }
+        }
+        normalize_power(&mut gains);
+        gains
+    }
+
+    // Sine law between the two horizontal speakers surrounding the source.
+    // An arc wider than a half turn has no speaker behind so the source is clamped to the nearest speaker
+    fn constant_power_gains(&self, azimuth: f32) -> Vec<f32> {
+        let mut gains = vec![0.; self.channels];
+        let n = self.ring.len();
+
+        if n == 1 {
+            gains[self.ring[0].0] = 1.;
+        } else if n > 1 {
+            let azimuth = wrap_angle(azimuth);
+
+            for i in 0..n {
+                let (si, azi) = self.ring[i];
+                let (sj, azj) = self.ring[(i + 1) % n];
+
+                let arc = (azj - azi).rem_euclid(360.);
+                let offset = (azimuth - azi).rem_euclid(360.);
+
+                if offset <= arc && arc > 0. {
+                    if arc > 180. {
+                        gains[if offset <= arc - offset { si } else { sj }] = 1.;
+                    } else {
+                        let frac = offset / arc;
+                        gains[si] = (frac * PI * 0.5).cos();
+                        gains[sj] = (frac * PI * 0.5).sin();
+                    }
+                    break;
+                }
+            }
+        }
+        gains
+    }
+
+    // The base whose gains are all positive holds the source, else the least negative one is used
+    fn vbap_gains(&self, azimuth: f32, elevation: f32) -> Vec<f32> {
+        let mut gains = vec![0.; self.channels];
+        let source = direction(azimuth, elevation);
+        let mut best: Option<(f32, &Base, [f32; 3])> = None;
+
+        for base in &self.bases {
+            let base_gains = base.gains(&source);
+            let min_gain = base_gains[..base.speakers.len()].iter().fold(f32::MAX, |m, g| m.min(*g));
+
+            match best {
+                Some((best_min, _, _)) if best_min >= min_gain => (),
+                _ => best = Some((min_gain, base, base_gains)),
+            }
+        }
+
+        if let Some((_, base, base_gains)) = best {
+            for (speaker, g) in base.speakers.iter().zip(base_gains) {
+                gains[*speaker] = g.max(0.);
+            }
+        }
+        gains
+    }
+
+    // Azimuth, elevation and width at the start of the chunk then at its end
+    pub fn positions(&self, set: &Set, len: usize) -> [f32; 6] {
+        if len == 0 {
+            return [0.; 6];
+        }
+        let azimuth_buf = set.get_hum_cv_buffer(self.hum_index + AZIMUTH_HUM_INDEX);
+        let elevation_buf = set.get_hum_cv_buffer(self.hum_index + ELEVATION_HUM_INDEX);
+        let width_buf = set.get_hum_cv_buffer(self.hum_index + WIDTH_HUM_INDEX);
+        let end = len - 1;
+
+        [azimuth_buf[0], elevation_buf[0], width_buf[0], azimuth_buf[end], elevation_buf[end], width_buf[end]]
+    }
+
+    // Channels gains and their step per sample, from the start positions to the end ones
+    pub fn ramps(&self, positions: &[f32; 6], len: usize, ramps: &mut Vec<(f32, f32)>) {
+        ramps.clear();
+
+        if len == 0 {
+            ramps.resize(self.channels, (0., 0.));
+            return;
+        }
+        let start_gains = self.gains(positions[0], positions[1], positions[2]);
+        let end_gains = self.gains(positions[3], positions[4], positions[5]);
+        let step = 1. / len as f32;
+
+        ramps.extend(start_gains.iter().zip(end_gains).map(|(g, eg)| (*g, (eg - g) * step)));
+    }
+}
+
+#[test]
+fn test_panner_gains() {
+    let stereo = Panner::new(&vec!["FL", "FR"], Panning::ConstantPower, 0);
+    let center = stereo.gains(0., 0., 0.);
+    assert!((center[0] - center[1]).abs() < EPSILON);
+    assert!((center[0] - 0.5_f32.sqrt()).abs() < EPSILON);
+
+    let left = stereo.gains(-30., 0., 0.);
+    assert!((left[0] - 1.).abs() < EPSILON && left[1].abs() < EPSILON);
+
+    // Past the speakers, the source stays on the nearest one
+    let far_left = stereo.gains(-90., 0., 0.);
+    assert!((far_left[0] - 1.).abs() < EPSILON && far_left[1].abs() < EPSILON);
+
+    let far_right = stereo.gains(150., 0., 0.);
+    assert!(far_right[0].abs() < EPSILON && (far_right[1] - 1.).abs() < EPSILON);
+
+    let surround = Panner::new(&vec!["FL", "FR", "FC", "LFE", "BL", "BR"], Panning::Vbap, 0);
+    let front = surround.gains(0., 0., 0.);
+    assert!((front[2] - 1.).abs() < EPSILON);
+    assert!(front[3] == 0.);
+}
+
+#[test]
+fn test_panner_ramps() {
+    let stereo = Panner::new(&vec!["FL", "FR"], Panning::ConstantPower, 0);
+    let mut ramps = Vec::with_capacity(2);
+
+    stereo.ramps(&[0., 0., 0., 0., 0., 0.], 4, &mut ramps);
+    assert!(ramps.len() == 2 && ramps.iter().all(|(_, step)| *step == 0.));
+
+    // From the center to the left, the left gain grows and the right one decreases
+    stereo.ramps(&[0., 0., 0., -30., 0., 0.], 4, &mut ramps);
+    assert!(ramps[0].1 > 0. && ramps[1].1 < 0.);
+    assert!((ramps[0].0 + ramps[0].1 * 4. - 1.).abs() < EPSILON);
+}
@@ -3,9 +3,13 @@ use std::str::FromStr;
 
 use talker::identifier::{Id, Index};
 
+use crate::midi_mapping::MidiMapping;
 use crate::mixer;
 use crate::output;
 
+pub const TEMPO_TAG: &str = "Tempo ";
+pub const MIDI_MAPPING_TAG: &str = "MidiMapping ";
+
 pub struct PTalkerVoice {
     pub talker: Id,
     pub voice_port: usize,
@@ -186,12 +190,16 @@ pub fn parse<'a>(
         HashMap<Id, PTalker<'a>>,
         HashMap<Id, PMixer<'a>>,
         HashMap<Id, POutput<'a>>,
+        Option<f32>,
+        Vec<MidiMapping>,
     ),
     failure::Error,
 > {
     let mut talkers = HashMap::new();
     let mut mixers = HashMap::new();
     let mut outputs = HashMap::new();
+    let mut tempo = None;
+    let mut midi_mappings = Vec::new();
 
     let mut src = source.as_str();
     let mixer_tag = format!("{} ", mixer::KIND);
@@ -200,8 +208,21 @@ pub fn parse<'a>(
     while src.len() > 0 {
         if src.starts_with("\n") {
             src = src.get("\n".len()..).unwrap();
+        } else if src.starts_with(TEMPO_TAG) {
+            let line_end = src.find("\n").unwrap_or(src.len());
+            let tempo_desc = src.get(TEMPO_TAG.len()..line_end).unwrap().trim();
+
+            tempo = Some(f32::from_str(tempo_desc)
+                .map_err(|e| failure::err_msg(format!("Failed to get tempo from {} : {}!", tempo_desc, e)))?);
+            src = src.get(line_end..).unwrap();
+        } else if src.starts_with(MIDI_MAPPING_TAG) {
+            let line_end = src.find("\n").unwrap_or(src.len());
+
+            midi_mappings.push(MidiMapping::parse(src.get(MIDI_MAPPING_TAG.len()..line_end).unwrap())?);
+            src = src.get(line_end..).unwrap();
         } else if src.starts_with(&mixer_tag) {
             let (rest, id, name) = parse_id_name(src.get(mixer_tag.len()..).unwrap())?;
+            let (rest, data) = parse_data(rest)?;
             let (rest, connections) = parse_connections(rest)?;
             let (rest, outputs) = parse_outputs(rest)?;
 
@@ -210,7 +231,7 @@ pub fn parse<'a>(
                     model: mixer::KIND,
                     id,
                     name,
-                    data: None,
+                    data,
                     connections,
                     state: None,
                 },
@@ -256,5 +277,27 @@ pub fn parse<'a>(
         }
     }
 
-    Ok((talkers, mixers, outputs))
+    Ok((talkers, mixers, outputs, tempo, midi_mappings))
+}
+
+#[test]
+fn test_parse_tempo() {
+    let source = "Tempo 96\nTseq 1#seq\n[:Tempo 120\n:]\n".to_string();
+    let (talkers, _, _, tempo, _) = parse(&source).unwrap();
+
+    assert!(tempo == Some(96.));
+    assert!(talkers[&1].data == Some("Tempo 120\n"));
+
+    // The talkers data lines are not taken for the tempo
+    let source = "Tseq 1#seq\n[:Tempo fast\n:]\n".to_string();
+    assert!(parse(&source).unwrap().3 == None);
+}
+
+#[test]
+fn test_parse_midi_mappings() {
+    let source = "MidiMapping 0 7 1.0.0.0 0 1 linear\nTseq 1#seq\n[:MidiMapping none\n:]\n".to_string();
+    let (_, _, _, _, midi_mappings) = parse(&source).unwrap();
+
+    assert!(midi_mappings.len() == 1);
+    assert!(midi_mappings[0].controller == 7);
 }
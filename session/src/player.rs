@@ -25,16 +25,23 @@ use luil::ui_connector::UiConnector;
 
 use talker::audio_format::AudioFormat;
 use talker::lv2_handler;
+use talker::transport::Transport;
 use talker::identifier::{Id, Index};
 
+use crate::audio_data::Vector;
 use crate::band::{Band, Operation};
 use crate::feedback::Feedback;
+use crate::meter::Metering;
+use crate::midi_input;
+use crate::midi_mapping::MidiMapping;
 use crate::output::Output;
+use crate::settings::{FeedbackSettings, LoopSettings, MidiSettings, Settings};
 use crate::state::State;
 use crate::plugin_handle_manager::PluginHandleManager;
 
 const RECEIVE_TIMEOUT: u64 = 10;
 const IDLE_PERIOD: u64 = 33;
+const METERING_PERIOD: usize = 50;
 
 //#[derive(PartialEq, Debug, Clone)]
 enum Order {
@@ -44,10 +51,16 @@ enum Order {
     Pause,
     Stop,
     SetTimeRange(i64, i64),
+    Seek(i64),
     SetAudibleTracks(Id, Vec<Index>),
     LoadBand(String),
     ModifyBand(Operation),
     AddPluginHandle(Id, UiConnector),
+    SetFeedbackSettings(FeedbackSettings),
+    SetLoopSettings(LoopSettings),
+    LearnMidi(Option<MidiMapping>),
+    // Sent by the MIDI input, without state response
+    MidiControl(u8, u8, u8),
     BandModificationsAndUiCount,
     State,
     Exit,
@@ -58,6 +71,24 @@ enum Response {
     BandModificationsAndUiCount(Vec<Operation>, usize),
 }
 
+// Reports sent by the runner along the playing, without order
+pub enum Report {
+    Metering(Metering),
+    // Transport position
+    Tick(i64),
+    // Modifications made by the player to be reported in the session band
+    BandModifications(Vec<Operation>),
+}
+
+// The MIDI input orders are queued with the session ones
+fn connect_midi_input(order_sender: &Sender<Order>, settings: &MidiSettings) -> midi_input::Input {
+    let midi_order_sender = order_sender.clone();
+
+    midi_input::connect(&settings.inputs, move |channel, controller, value| {
+        let _ = midi_order_sender.send(Order::MidiControl(channel, controller, value));
+    })
+}
+
 fn state_order(state: State) -> Order {
     match state {
         State::Playing => Order::Play,
@@ -71,17 +102,26 @@ fn state_order(state: State) -> Order {
 struct Runner {
     order_receiver: Receiver<Order>,
     response_sender: Sender<Response>,
+    report_sender: Sender<Report>,
     plugin_handle_manager: PluginHandleManager,
+    // Mapping waiting for the next MIDI controller change
+    midi_learning: Option<MidiMapping>,
 }
 
 impl Runner {
     fn new(
         order_receiver: Receiver<Order>,
         response_sender: Sender<Response>,
+        report_sender: Sender<Report>,
     ) -> Runner {
         let plugin_handle_manager = PluginHandleManager::new();
 
-        Runner { order_receiver, response_sender, plugin_handle_manager }
+        Runner { order_receiver, response_sender, report_sender, plugin_handle_manager, midi_learning: None }
+    }
+
+    fn send_report(&self, report: Report) {
+        // The GUI may not listen the reports so a send failure is ignored
+        let _ = self.report_sender.send(report);
     }
 
     fn send_state(&self, state: State) -> State {
@@ -92,9 +132,29 @@ impl Runner {
         state
     }
 
-    fn start(&mut self, band_description: String) -> Result<(), failure::Error> {
+    // The mapped hums values are reported to the session band without undo entry
+    fn midi_control(&mut self, band: &mut Band, channel: u8, controller: u8, value: u8) -> Result<(), failure::Error> {
+        let mut operations = Vec::new();
+
+        if let Some(mut mapping) = self.midi_learning.take() {
+            mapping.channel = channel;
+            mapping.controller = controller;
+
+            let operation = Operation::SetMidiMapping(mapping);
+            band.modify(&operation)?;
+            operations.push(operation);
+        }
+        operations.append(&mut band.midi_control(channel, controller, value)?);
+
+        if !operations.is_empty() {
+            self.send_report(Report::BandModifications(operations));
+        }
+        Ok(())
+    }
+
+    fn start(&mut self, band_description: String, settings: Settings) -> Result<(), failure::Error> {
 
-        let res = self.run(band_description);
+        let res = self.run(band_description, settings);
 
         let _ = self.response_sender.send(Response::State(State::Exited));
 
@@ -105,16 +165,27 @@ impl Runner {
         })
     }
 
-    fn run(&mut self, band_description: String) -> Result<(), failure::Error> {
+    fn run(&mut self, band_description: String, settings: Settings) -> Result<(), failure::Error> {
         let mut tick: i64 = 0;
         let mut start_tick: i64 = 0;
-        let mut end_tick: i64 = i64::max_value();
+        // The loop end is only set by an explicit time range
+        let mut end_tick: Option<i64> = None;
 
         let chunk_size = AudioFormat::chunk_size();
-        let mut feedback = Feedback::new(chunk_size)?;
+        let mut feedback = Feedback::new(chunk_size, &settings.feedback)?;
+        let mut feedback_mixers_names = settings.feedback.mixers;
+        let mut feedback_channels: Vec<Vector> = Vec::new();
+        let mut new_feedback_channels: Vec<Vector> = Vec::new();
+
+        let mut loop_settings = settings.looping;
+        let mut loop_passes: usize = 0;
+
+        let metering_len = AudioFormat::sample_rate() * METERING_PERIOD / 1000;
+        let mut unmetered_len = 0;
+        let mut recording = false;
 
         let mut band = Band::make(&band_description, true)?;
-        let feedback_mixer_id = band.mixers().iter().next().map_or(0, |(k, _)| *k);
+        band.set_feedback_mixers(&feedback_mixers_names);
 
         let mut state = State::Stopped;
         let mut order = self.wait_order()?;
@@ -131,13 +202,15 @@ impl Runner {
                 }
                 Order::Pause => {
                     self.send_state(State::Paused);
-                    
+
+                    // The talkers are informed of the stop by the fade chunk
+                    Transport::set_speed(0.);
+
                     if state == State::Playing || state == State::Recording {
                         let len = band.play(tick, feedback.fade_len())?;
 
-                        if let Some(mxr) = band.find_mixer(feedback_mixer_id) {
-                            feedback.write_fadeout(mxr.borrow().channels_buffers(), len)?;
-                        }
+                        band.feedback_channels(false, &mut feedback_channels, len);
+                        feedback.write_fadeout(&feedback_channels, len)?;
 
                         tick += len as i64;
 
@@ -149,6 +222,7 @@ impl Runner {
                 }
                 Order::Play => {
                     self.send_state(State::Playing);
+                    Transport::set_speed(1.);
 
                     if state == State::Stopped {
                         band.open()?;
@@ -159,9 +233,8 @@ impl Runner {
 
                         let len = band.play(tick, feedback.fade_len())?;
 
-                        if let Some(mxr) = band.find_mixer(feedback_mixer_id) {
-                            feedback.write_fadein(mxr.borrow().channels_buffers(), len)?;
-                        }
+                        band.feedback_channels(false, &mut feedback_channels, len);
+                        feedback.write_fadein(&feedback_channels, len)?;
 
                         tick += len as i64;
                     }
@@ -170,6 +243,7 @@ impl Runner {
                 }
                 Order::Record => {
                     self.send_state(State::Recording);
+                    Transport::set_speed(1.);
 
                     if state == State::Stopped {
                         band.set_record(true)?;
@@ -181,30 +255,37 @@ impl Runner {
                         feedback.run()?;
                     }
 
+                    recording = true;
                     state = State::Recording;
                 }
                 Order::Stop => {
                     self.send_state(State::Stopped);
 
                     if state != State::Stopped {
+                        Transport::set_speed(0.);
                         let len = band.fadeout(tick)?;
 
                         if state == State::Playing || state == State::Recording {
-                            if let Some(mxr) = band.find_mixer(feedback_mixer_id) {
-                                feedback.write(mxr.borrow().channels_buffers(), len)?;
-                            }
+                            band.feedback_channels(false, &mut feedback_channels, len);
+                            feedback.write(&feedback_channels, len)?;
+                        }
+                        if recording {
+                            self.send_report(Report::Metering(Metering::LoudnessSummary(band.loudness_summary())));
+                            recording = false;
                         }
                         band.close()?;
                         feedback.close()?;
                         band.set_record(false)?;
+                        band.set_punch_in(true);
                         tick = start_tick;
+                        loop_passes = 0;
                         state = State::Stopped;
                     }
                     order = self.wait_order()?;
                     continue;
                 }
                 Order::SetAudibleTracks(mxr_id, trks_idx) => {
-                    if mxr_id == feedback_mixer_id {
+                    if band.feedback_mixers_ids().contains(&mxr_id) {
                         if let Some(mxr) = band.find_mixer(mxr_id) {
                             mxr.borrow_mut().set_audible_tracks(trks_idx)?;
                         }
@@ -215,7 +296,36 @@ impl Runner {
                 }
                 Order::SetTimeRange(start, end) => {
                     start_tick = start;
-                    end_tick = end;
+                    end_tick = if end > start { Some(end) } else { None };
+
+                    order = state_order(state);
+                    continue;
+                }
+                Order::Seek(new_tick) => {
+                    match state {
+                        State::Playing | State::Recording => {
+                            // Only the feedback fades from the current position, the outputs are written from the new one
+                            band.set_punch_in(false);
+                            let len = band.play(tick, feedback.fade_len())?;
+                            band.feedback_channels(true, &mut feedback_channels, len);
+
+                            band.reset_talkers();
+                            band.set_punch_in(!loop_settings.punch_in || end_tick.is_none() || new_tick >= start_tick);
+
+                            let len = band.play(new_tick, len)?;
+                            band.feedback_channels(true, &mut new_feedback_channels, len);
+
+                            feedback.write_fade(&feedback_channels, &new_feedback_channels, len)?;
+
+                            tick = new_tick + len as i64;
+                        }
+                        State::Paused => {
+                            band.reset_talkers();
+                            tick = new_tick;
+                        }
+                        _ => tick = new_tick,
+                    }
+                    self.send_report(Report::Tick(tick));
 
                     order = state_order(state);
                     continue;
@@ -227,13 +337,14 @@ impl Runner {
                             let _ = band.close();
 
                             let mut new_band = Band::make(&band_desc, true)?;
+                            new_band.set_feedback_mixers(&feedback_mixers_names);
                             new_band.open()?;
                             let len = new_band.play(tick, len)?;
 
-                            let mxr = band.find_mixer(feedback_mixer_id).unwrap_or_else(|| band.first_mixer());
-                            let new_mxr = new_band.find_mixer(feedback_mixer_id).unwrap_or_else(|| new_band.first_mixer());
+                            band.feedback_channels(false, &mut feedback_channels, len);
+                            new_band.feedback_channels(false, &mut new_feedback_channels, len);
 
-                            feedback.write_fade(mxr.borrow().channels_buffers(), new_mxr.borrow().channels_buffers(), len)?;
+                            feedback.write_fade(&feedback_channels, &new_feedback_channels, len)?;
 
                             tick += len as i64;
                             band = new_band;
@@ -247,6 +358,8 @@ impl Runner {
                         }
                         State::Exited => (),
                     }
+                    band.set_feedback_mixers(&feedback_mixers_names);
+
                     order = state_order(state);
                     continue;
                 }
@@ -262,6 +375,43 @@ impl Runner {
                     order = state_order(state);
                     continue;
                 }
+                Order::SetFeedbackSettings(settings) => {
+                    let mut new_feedback = Feedback::new(chunk_size, &settings)?;
+
+                    if state != State::Stopped {
+                        feedback.close()?;
+                        new_feedback.open()?;
+                    }
+                    feedback = new_feedback;
+                    feedback_mixers_names = settings.mixers;
+                    band.set_feedback_mixers(&feedback_mixers_names);
+
+                    order = state_order(state);
+                    continue;
+                }
+                Order::SetLoopSettings(settings) => {
+                    loop_settings = settings;
+
+                    order = state_order(state);
+                    continue;
+                }
+                Order::LearnMidi(mapping) => {
+                    self.midi_learning = mapping;
+
+                    order = state_order(state);
+                    continue;
+                }
+                Order::MidiControl(channel, controller, value) => {
+                    if let Err(e) = self.midi_control(&mut band, channel, controller, value) {
+                        eprintln!("MIDI control error : {}", e);
+                    }
+
+                    // Out of the playback, the player goes back to wait the orders
+                    if state != State::Playing && state != State::Recording {
+                        order = self.wait_order()?;
+                        continue;
+                    }
+                }
                 Order::State => {
                     order = state_order(state);
                     continue;
@@ -272,26 +422,70 @@ impl Runner {
             // Run LV2 workers
             lv2_handler::run_workers()?;
 
-            if tick > end_tick {
+            let looping = end_tick.is_some();
+            let loop_end = end_tick.unwrap_or(i64::MAX);
+
+            let len = if looping && tick >= loop_end {
+                loop_passes += 1;
+
+                if loop_settings.count > 0 && loop_passes >= loop_settings.count {
+                    // Only the feedback gets the stop fadeout, the last recorded pass ends on the loop end
+                    band.set_punch_in(false);
+                    order = Order::Stop;
+                    continue;
+                }
+
+                // The tail past the loop end is crossfaded with the loop start in the feedback.
+                // It is not written to the outputs so that each recorded pass ends on the loop end
+                let crossfade_len = (loop_settings.crossfade * AudioFormat::sample_rate() / 1000).min(chunk_size);
+                let mut len = crossfade_len;
+
+                if crossfade_len > 0 {
+                    band.set_punch_in(false);
+                    len = band.play(tick, crossfade_len)?;
+                    band.feedback_channels(true, &mut feedback_channels, len);
+                }
+
+                band.reset_talkers();
+                band.set_punch_in(true);
                 tick = start_tick;
-            }
 
-            let mut len = if tick + (chunk_size as i64) < end_tick {
-                chunk_size
+                if crossfade_len > 0 {
+                    len = band.play(tick, len)?;
+                    band.feedback_channels(true, &mut new_feedback_channels, len);
+                    feedback.write_crossfade(&feedback_channels, &new_feedback_channels, len)?;
+                }
+                len
             } else {
-                (end_tick - tick) as usize
-            };
+                let mut len = chunk_size;
 
-            len = band.play(tick, len)?;
+                if looping {
+                    // Chunks are split on the loop bounds to punch in sample accurately
+                    let bound = if tick < start_tick { start_tick } else { loop_end };
+                    len = len.min((bound - tick) as usize);
 
-            if let Some(mxr) = band.find_mixer(feedback_mixer_id) {
-                feedback.write(mxr.borrow().feedback(), len)?;
-            }
+                    band.set_punch_in(!loop_settings.punch_in || tick >= start_tick);
+                }
+
+                len = band.play(tick, len)?;
+
+                band.feedback_channels(true, &mut feedback_channels, len);
+                feedback.write(&feedback_channels, len)?;
+                len
+            };
 
             if self.plugin_handle_manager.has_handle() {
                 self.plugin_handle_manager.transmit(true)?;
             }
 
+            unmetered_len += len;
+
+            if unmetered_len >= metering_len {
+                self.send_report(Report::Metering(Metering::Levels(band.levels())));
+                self.send_report(Report::Tick(tick));
+                unmetered_len = 0;
+            }
+
             tick += len as i64;
 
             match self.order_receiver.try_recv() {
@@ -331,34 +525,57 @@ impl Runner {
 pub struct Player {
     order_sender: Sender<Order>,
     response_receiver: Receiver<Response>,
+    report_receiver: Receiver<Report>,
+    // Owned by the player so that its order sender goes with it
+    midi_input: Option<midi_input::Input>,
     state: State,
 }
 pub type RPlayer = Rc<RefCell<Player>>;
 
 impl Player {
-    pub fn new(band_description: String) -> Result<Player, failure::Error> {
+    pub fn new(band_description: String, settings: Settings) -> Result<Player, failure::Error> {
         let (order_sender, order_receiver): (Sender<Order>, Receiver<Order>) =
             std::sync::mpsc::channel();
         let (response_sender, response_receiver): (Sender<Response>, Receiver<Response>) =
             std::sync::mpsc::channel();
-
-        let state = if band_description.is_empty() {
-            State::Exited
+        let (report_sender, report_receiver): (Sender<Report>, Receiver<Report>) =
+            std::sync::mpsc::channel();
+        let (state, midi_input) = if band_description.is_empty() {
+            (State::Exited, None)
         } else {
+            let midi_input = connect_midi_input(&order_sender, &settings.midi);
+
             let _join_handle = thread::spawn(move || {
-                let mut runner = Runner::new(order_receiver, response_sender);
+                let mut runner = Runner::new(order_receiver, response_sender, report_sender);
 
-                runner.start(band_description)
+                runner.start(band_description, settings)
             });
-            State::Stopped
+            (State::Stopped, Some(midi_input))
         };
         Ok(Self {
             order_sender,
             response_receiver,
+            report_receiver,
+            midi_input,
             state,
         })
     }
 
+    // The previous MIDI input ports are closed before the new ones are opened
+    pub fn set_midi_settings(&mut self, settings: &MidiSettings) {
+        if self.midi_input.take().is_some() {
+            self.midi_input = Some(connect_midi_input(&self.order_sender, settings));
+        }
+    }
+
+    pub fn has_midi_input(&self) -> bool {
+        self.midi_input.as_ref().map_or(false, |input| input.is_connected())
+    }
+
+    pub fn receive_reports(&self) -> Vec<Report> {
+        self.report_receiver.try_iter().collect()
+    }
+
     fn receive_state(&mut self) -> State {
         match self.state {
             State::Exited => {}
@@ -478,6 +695,16 @@ impl Player {
         Ok(self.receive_state())
     }
 
+    pub fn seek(&mut self, tick: i64) -> Result<State, failure::Error> {
+        self.check_not_exited()?;
+
+        self.order_sender
+            .send(Order::Seek(tick))
+            .map_err(|e| failure::err_msg(format!("Player::seek error : {}", e)))?;
+
+        Ok(self.receive_state())
+    }
+
     pub fn load_band(&mut self, band_description: String) -> Result<State, failure::Error> {
         self.check_not_exited()?;
 
@@ -508,6 +735,36 @@ impl Player {
         Ok(self.receive_state())
     }
 
+    pub fn set_feedback_settings(&mut self, settings: FeedbackSettings) -> Result<State, failure::Error> {
+        self.check_not_exited()?;
+
+        self.order_sender
+            .send(Order::SetFeedbackSettings(settings))
+            .map_err(|e| failure::err_msg(format!("Player::set_feedback_settings error : {}", e)))?;
+
+        Ok(self.receive_state())
+    }
+
+    pub fn set_loop_settings(&mut self, settings: LoopSettings) -> Result<State, failure::Error> {
+        self.check_not_exited()?;
+
+        self.order_sender
+            .send(Order::SetLoopSettings(settings))
+            .map_err(|e| failure::err_msg(format!("Player::set_loop_settings error : {}", e)))?;
+
+        Ok(self.receive_state())
+    }
+
+    pub fn learn_midi(&mut self, mapping: Option<MidiMapping>) -> Result<State, failure::Error> {
+        self.check_not_exited()?;
+
+        self.order_sender
+            .send(Order::LearnMidi(mapping))
+            .map_err(|e| failure::err_msg(format!("Player::learn_midi error : {}", e)))?;
+
+        Ok(self.receive_state())
+    }
+
     pub fn band_modifications_and_ui_count(&mut self) -> Result<(Vec<Operation>, usize), failure::Error> {
         self.check_not_exited()?;
 
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::iter::Extend;
+use std::path::PathBuf;
 
 use talker::lv2_handler;
 use talker::rtalker;
@@ -9,16 +10,23 @@ use talkers::accumulator::{self, Accumulators};
 use talkers::adsrp::{self, ADSRp};
 use talkers::audio_switch::{self, AudioSwitch};
 use talkers::audiofile_input::{self, AudioFileInput};
+use talkers::automation::{self, Automation};
 use talkers::bounded_sinusoidal::{self, BoundedSinusoidal};
 use talkers::bounded_square::{self, BoundedSquare};
+use talkers::clap::Clap;
 use talkers::damper::{self, Dampers};
 use talkers::dynamic_modulator::{self, DynamicModulators};
+use talkers::dynamics::{self, Compressors, Gates, Limiters};
 use talkers::envelope_shaper::{self, EnvelopeShaper};
 use talkers::fuzz::{self, Fuzz};
+use talkers::granular::{self, Granular};
 use talkers::hub::{self, Hub};
+use talkers::ladspa::Ladspa;
+use talkers::lfo::{self, Lfo};
 use talkers::lv2::Lv2;
 use talkers::math::{self, Average, Product, Sum, AtanSum, TanhSum};
 use talkers::parabolic::{self, Parabolic};
+use talkers::polyblep::{self, BlPulse, BlSaw, BlTriangle};
 use talkers::regulator::{self, Regulators};
 use talkers::round::{self, Round};
 use talkers::second_degree_frequency_progression::{self, SecondDegreeFrequencyProgression};
@@ -27,10 +35,17 @@ use talkers::sinusoidal_fptg::{self, SinusoidalFPTG};
 use talkers::speed_modulator::{self, SpeedModulators};
 use talkers::square::{self, Square};
 use talkers::tseq::tseq::{self, Tseq};
+use talkers::wavetable::{self, Wavetable};
+
+use crate::clap_handler;
+use crate::ladspa_handler;
+use crate::plugins_scan::{PluginsScan, Status};
 
 enum PluginType {
     Internal,
     Lv2,
+    Clap(PathBuf),
+    Ladspa(PathBuf),
 }
 
 pub struct PluginHandler {
@@ -40,6 +55,8 @@ pub struct PluginHandler {
 
 pub struct PluginsManager {
     handlers: HashMap<String, PluginHandler>,
+    // Uri, name and reason of the plugins disabled by the scan
+    disabled_plugins: Vec<(String, String, String)>,
 }
 
 impl PluginsManager {
@@ -53,12 +70,22 @@ impl PluginsManager {
         )
     }
 
-    fn make_plugins_handlers() -> HashMap<String, PluginHandler> {
+    fn make_plugins_handlers() -> (HashMap<String, PluginHandler>, Vec<(String, String, String)>) {
         println!("make_plugins_handlers start");
         let mut handlers = HashMap::new();
+        let mut disabled_plugins = Vec::new();
+        let mut scan = PluginsScan::load();
 
         lv2_handler::visit(|lv2_handler| {
+            let uris: Vec<String> = lv2_handler.world.iter_plugins().map(|plugin| plugin.uri()).collect();
+            scan.probe_unknown(&uris);
+
             for plugin in lv2_handler.world.iter_plugins() {
+                if let Status::Disabled(reason) = scan.status(&plugin.uri()) {
+                    disabled_plugins.push((plugin.uri(), plugin.name(), reason));
+                    continue;
+                }
+
                 let mut categories = Vec::new();
                 for classe in plugin.classes() {
                     let category = match classe.find(" Plugin") {
@@ -84,6 +111,58 @@ impl PluginsManager {
         })
         .unwrap_or_else(|e| eprintln!("PluginsManager::make_plugins_handlers failed : {:?}", e));
 
+        for bundle in scan.clap_bundles(&clap_handler::bundles()) {
+            if let Status::Disabled(reason) = bundle.status {
+                let name = bundle.path.file_name().map_or(String::new(), |n| n.to_string_lossy().to_string());
+                disabled_plugins.push((bundle.path.to_string_lossy().to_string(), format!("{} (CLAP)", name), reason));
+                continue;
+            }
+
+            for desc in bundle.plugins {
+                let model = desc.model();
+                let categories = if desc.categories.is_empty() { vec!["Plugin".to_string()] } else { desc.categories };
+
+                handlers.insert(
+                    model.clone(),
+                    PluginHandler {
+                        base: TalkerHandlerBase::with_multi_categories(
+                            categories,
+                            &model,
+                            &format!("{} (CLAP)", desc.name),
+                        ),
+                        plugin_type: PluginType::Clap(desc.path),
+                    },
+                );
+            }
+        }
+
+        for library in scan.ladspa_libraries(&ladspa_handler::libraries()) {
+            if let Status::Disabled(reason) = library.status {
+                let name = library.path.file_name().map_or(String::new(), |n| n.to_string_lossy().to_string());
+                disabled_plugins.push((library.path.to_string_lossy().to_string(), format!("{} (LADSPA)", name), reason));
+                continue;
+            }
+
+            for desc in library.plugins {
+                let model = desc.model();
+
+                handlers.insert(
+                    model.clone(),
+                    PluginHandler {
+                        base: TalkerHandlerBase::with_multi_categories(
+                            vec![ladspa_handler::CATEGORY.to_string()],
+                            &model,
+                            &desc.name,
+                        ),
+                        plugin_type: PluginType::Ladspa(desc.path),
+                    },
+                );
+            }
+        }
+
+        scan.save().unwrap_or_else(|e| eprintln!("Plugins scan saving failed : {:?}", e));
+        disabled_plugins.sort_by(|a, b| a.1.cmp(&b.1));
+
         handlers.extend(vec![
             PluginsManager::tkr_hr_kv(Accumulators::descriptor()),
             PluginsManager::tkr_hr_kv(ADSRp::descriptor()),
@@ -91,13 +170,21 @@ impl PluginsManager {
             PluginsManager::tkr_hr_kv(AudioSwitch::descriptor()),
             PluginsManager::tkr_hr_kv(AudioFileInput::descriptor()),
             PluginsManager::tkr_hr_kv(Average::descriptor()),
+            PluginsManager::tkr_hr_kv(BlPulse::descriptor()),
+            PluginsManager::tkr_hr_kv(BlSaw::descriptor()),
+            PluginsManager::tkr_hr_kv(BlTriangle::descriptor()),
             PluginsManager::tkr_hr_kv(BoundedSinusoidal::descriptor()),
             PluginsManager::tkr_hr_kv(BoundedSquare::descriptor()),
+            PluginsManager::tkr_hr_kv(Compressors::descriptor()),
             PluginsManager::tkr_hr_kv(Dampers::descriptor()),
             PluginsManager::tkr_hr_kv(DynamicModulators::descriptor()),
             PluginsManager::tkr_hr_kv(EnvelopeShaper::descriptor()),
             PluginsManager::tkr_hr_kv(Fuzz::descriptor()),
+            PluginsManager::tkr_hr_kv(Gates::descriptor()),
+            PluginsManager::tkr_hr_kv(Granular::descriptor()),
             PluginsManager::tkr_hr_kv(Hub::descriptor()),
+            PluginsManager::tkr_hr_kv(Lfo::descriptor()),
+            PluginsManager::tkr_hr_kv(Limiters::descriptor()),
             PluginsManager::tkr_hr_kv(Parabolic::descriptor()),
             PluginsManager::tkr_hr_kv(Product::descriptor()),
             PluginsManager::tkr_hr_kv(Regulators::descriptor()),
@@ -110,18 +197,33 @@ impl PluginsManager {
             PluginsManager::tkr_hr_kv(Sum::descriptor()),
             PluginsManager::tkr_hr_kv(TanhSum::descriptor()),
             PluginsManager::tkr_hr_kv(Tseq::descriptor()),
+            PluginsManager::tkr_hr_kv(Wavetable::descriptor()),
         ]);
 
         println!("make_plugins_handlers end");
-        handlers
+        (handlers, disabled_plugins)
     }
 
     pub fn new() -> Self {
+        let (handlers, disabled_plugins) = PluginsManager::make_plugins_handlers();
+
         Self {
-            handlers: PluginsManager::make_plugins_handlers(),
+            handlers,
+            disabled_plugins,
         }
     }
 
+    pub fn disabled_plugins(&self) -> &Vec<(String, String, String)> {
+        &self.disabled_plugins
+    }
+
+    // The disabled plugins will be probed again at the next start
+    pub fn forget_disabled_plugins(&self) -> Result<(), failure::Error> {
+        let mut scan = PluginsScan::load();
+        scan.forget_disabled();
+        scan.save()
+    }
+
     pub fn make_internal_talker(&self, model: &String, base: TalkerBase) -> Result<RTalker, failure::Error> {
         if model == accumulator::MODEL {
             Ok(rtalker!(Accumulators::new(base)?))
@@ -135,10 +237,14 @@ impl PluginsManager {
             Ok(rtalker!(AudioFileInput::new(base)?))
         } else if model == hub::MODEL {
             Ok(rtalker!(Hub::new(base)?))
+        } else if model == lfo::MODEL {
+            Ok(rtalker!(Lfo::new(base)?))
         } else if model == bounded_sinusoidal::MODEL {
             Ok(rtalker!(BoundedSinusoidal::new(base)?))
         } else if model == bounded_square::MODEL {
             Ok(rtalker!(BoundedSquare::new(base)?))
+        } else if model == dynamics::COMPRESSOR_MODEL {
+            Ok(rtalker!(Compressors::new(base)?))
         } else if model == damper::MODEL {
             Ok(rtalker!(Dampers::new(base)?))
         } else if model == dynamic_modulator::MODEL {
@@ -149,8 +255,20 @@ impl PluginsManager {
             Ok(rtalker!(EnvelopeShaper::new(base)?))
         } else if model == fuzz::MODEL {
             Ok(rtalker!(Fuzz::new(base)?))
+        } else if model == dynamics::GATE_MODEL {
+            Ok(rtalker!(Gates::new(base)?))
+        } else if model == granular::MODEL {
+            Ok(rtalker!(Granular::new(base)?))
+        } else if model == dynamics::LIMITER_MODEL {
+            Ok(rtalker!(Limiters::new(base)?))
         } else if model == parabolic::MODEL {
             Ok(rtalker!(Parabolic::new(base)?))
+        } else if model == polyblep::PULSE_MODEL {
+            Ok(rtalker!(BlPulse::new(base)?))
+        } else if model == polyblep::SAW_MODEL {
+            Ok(rtalker!(BlSaw::new(base)?))
+        } else if model == polyblep::TRIANGLE_MODEL {
+            Ok(rtalker!(BlTriangle::new(base)?))
         } else if model == math::PRODUCT_MODEL {
             Ok(rtalker!(Product::new(base)?))
         } else if model == regulator::MODEL {
@@ -173,6 +291,8 @@ impl PluginsManager {
             Ok(rtalker!(TanhSum::new(base)?))
         } else if model == tseq::MODEL {
             Ok(rtalker!(Tseq::new(base)?))
+        } else if model == wavetable::MODEL {
+            Ok(rtalker!(Wavetable::new(base)?))
         } else {
             Err(failure::err_msg(format!("Unknown talker model {}.", model)))
         }
@@ -185,6 +305,14 @@ impl PluginsManager {
                 let base = TalkerBase::new(ph.base.label(), ph.base.model(), effective);
                 Ok(rtalker!(Lv2::new(lv2_handler, ph.base.model(), base)?))
             }),
+            PluginType::Clap(path) => {
+                let base = TalkerBase::new(ph.base.label(), ph.base.model(), effective);
+                Ok(rtalker!(Clap::new(path, ph.base.model(), base)?))
+            },
+            PluginType::Ladspa(path) => {
+                let base = TalkerBase::new(ph.base.label(), ph.base.model(), effective);
+                Ok(rtalker!(Ladspa::new(path, ph.base.model(), base)?))
+            },
             PluginType::Internal => {
                 let base = TalkerBase::new(ph.base.label(), ph.base.model(), effective);
                 self.make_internal_talker(ph.base.model(), base)
@@ -195,6 +323,11 @@ impl PluginsManager {
     pub fn make_talker(&self, model: &str, effective: bool) -> Result<RTalker, failure::Error> {
         match self.handlers.get(model) {
             Some(ph) => self.mk_tkr(ph, effective),
+            // The automation talkers are made by the band, they are not in the talkers menu
+            None if model == automation::MODEL => {
+                let base = TalkerBase::new("", model, effective);
+                Ok(rtalker!(Automation::new(base)?))
+            }
             None => Err(failure::err_msg(format!("Unknown talker URI {}.", model))),
         }
     }
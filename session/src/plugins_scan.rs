@@ -0,0 +1,541 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use talker::audio_format::AudioFormat;
+use talker::lv2_handler;
+
+use crate::clap_handler;
+use crate::ladspa_handler;
+use crate::util;
+
+const SCAN_FILENAME: &str = "plugins_scan";
+const CLAP_SCAN_FILENAME: &str = "clap_plugins_scan";
+const LADSPA_SCAN_FILENAME: &str = "ladspa_plugins_scan";
+const ENABLED_STATUS: &str = "enabled";
+const DISABLED_STATUS: &str = "disabled";
+const PLUGIN_STATUS: &str = "plugin";
+
+// Command line arguments making the application probe a plugin, a CLAP bundle or a LADSPA library then exit
+pub const PROBE_ARG: &str = "--probe-lv2";
+pub const CLAP_PROBE_ARG: &str = "--probe-clap";
+pub const LADSPA_PROBE_ARG: &str = "--probe-ladspa";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+const PROBE_POLLING_PERIOD: Duration = Duration::from_millis(20);
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Status {
+    Enabled,
+    Disabled(String),
+}
+
+// Description of a bundle plugin as written by its probe
+pub trait Description: Sized {
+    fn serialize(&self) -> String;
+    fn parse(path: &Path, s: &str) -> Result<Self, failure::Error>;
+}
+
+impl Description for clap_handler::PluginDescription {
+    fn serialize(&self) -> String {
+        clap_handler::PluginDescription::serialize(self)
+    }
+    fn parse(path: &Path, s: &str) -> Result<Self, failure::Error> {
+        clap_handler::PluginDescription::parse(path, s)
+    }
+}
+
+impl Description for ladspa_handler::PluginDescription {
+    fn serialize(&self) -> String {
+        ladspa_handler::PluginDescription::serialize(self)
+    }
+    fn parse(path: &Path, s: &str) -> Result<Self, failure::Error> {
+        ladspa_handler::PluginDescription::parse(path, s)
+    }
+}
+
+// Bundle already probed with its modification time and its plugins
+#[derive(PartialEq, Debug, Clone)]
+pub struct Bundle<D> {
+    pub path: PathBuf,
+    pub modified: u64,
+    pub status: Status,
+    pub plugins: Vec<D>,
+}
+
+pub type ClapBundle = Bundle<clap_handler::PluginDescription>;
+pub type LadspaLibrary = Bundle<ladspa_handler::PluginDescription>;
+
+// Plugins already probed, stored as "uri<TAB>status<TAB>reason" lines.
+// The CLAP bundles and the LADSPA libraries are stored as "path<TAB>modified<TAB>status<TAB>reason" lines
+// followed by a "path<TAB>modified<TAB>plugin<TAB>description" line per plugin
+#[derive(PartialEq, Debug, Default)]
+pub struct PluginsScan {
+    statuses: HashMap<String, Status>,
+    clap_bundles: HashMap<PathBuf, ClapBundle>,
+    ladspa_libraries: HashMap<PathBuf, LadspaLibrary>,
+    modified: bool,
+}
+
+impl PluginsScan {
+    pub fn load() -> PluginsScan {
+        let directory = util::configuration_path();
+
+        let mut scan = match fs::read_to_string(directory.join(SCAN_FILENAME)) {
+            Ok(content) => PluginsScan::parse(&content),
+            Err(_) => PluginsScan::default(),
+        };
+        if let Ok(content) = fs::read_to_string(directory.join(CLAP_SCAN_FILENAME)) {
+            parse_bundles(&mut scan.clap_bundles, &content);
+        }
+        if let Ok(content) = fs::read_to_string(directory.join(LADSPA_SCAN_FILENAME)) {
+            parse_bundles(&mut scan.ladspa_libraries, &content);
+        }
+        scan
+    }
+
+    pub fn save(&mut self) -> Result<(), failure::Error> {
+        if self.modified {
+            let directory = util::configuration_path();
+            fs::create_dir_all(&directory)?;
+            fs::write(directory.join(SCAN_FILENAME), self.serialize())?;
+            fs::write(directory.join(CLAP_SCAN_FILENAME), serialize_bundles(&self.clap_bundles))?;
+            fs::write(directory.join(LADSPA_SCAN_FILENAME), serialize_bundles(&self.ladspa_libraries))?;
+            self.modified = false;
+        }
+        Ok(())
+    }
+
+    fn parse(content: &str) -> PluginsScan {
+        let mut scan = PluginsScan::default();
+
+        for line in content.lines() {
+            let mut fields = line.splitn(3, '\t');
+
+            match (fields.next(), fields.next()) {
+                (Some(uri), Some(ENABLED_STATUS)) => {
+                    scan.statuses.insert(uri.to_string(), Status::Enabled);
+                }
+                (Some(uri), Some(DISABLED_STATUS)) => {
+                    let reason = fields.next().unwrap_or("").to_string();
+                    scan.statuses.insert(uri.to_string(), Status::Disabled(reason));
+                }
+                _ => eprintln!("Unknown plugins scan line {}", line),
+            }
+        }
+        scan
+    }
+
+    fn serialize(&self) -> String {
+        let mut uris: Vec<&String> = self.statuses.keys().collect();
+        uris.sort();
+
+        let mut content = String::new();
+
+        for uri in uris {
+            match &self.statuses[uri] {
+                Status::Enabled => content.push_str(&format!("{}\t{}\n", uri, ENABLED_STATUS)),
+                Status::Disabled(reason) => {
+                    let reason = reason.replace(['\t', '\n'], " ");
+                    content.push_str(&format!("{}\t{}\t{}\n", uri, DISABLED_STATUS, reason))
+                }
+            }
+        }
+        content
+    }
+
+    // Status of the plugin, probed if it is unknown
+    pub fn status(&mut self, uri: &str) -> Status {
+        if let Some(status) = self.statuses.get(uri) {
+            return status.clone();
+        }
+        match probe(PROBE_ARG, uri).map(to_status) {
+            Some(status) => {
+                self.statuses.insert(uri.to_string(), status.clone());
+                self.modified = true;
+                status
+            }
+            None => Status::Enabled,
+        }
+    }
+
+    // The unknown plugins are probed in parallel since each probe waits for its child process
+    pub fn probe_unknown(&mut self, uris: &[String]) {
+        let unknown: Vec<String> = uris.iter().filter(|uri| !self.statuses.contains_key(*uri)).cloned().collect();
+
+        for (uri, result) in probe_all(PROBE_ARG, &unknown) {
+            self.statuses.insert(uri, to_status(result));
+            self.modified = true;
+        }
+    }
+
+    // The CLAP bundles, probed if they are unknown or modified since their last probe
+    pub fn clap_bundles(&mut self, paths: &[PathBuf]) -> Vec<ClapBundle> {
+        self.modified = probe_bundles(&mut self.clap_bundles, CLAP_PROBE_ARG, paths) || self.modified;
+        paths.iter().filter_map(|path| self.clap_bundles.get(path).cloned()).collect()
+    }
+
+    // The LADSPA libraries, probed if they are unknown or modified since their last probe
+    pub fn ladspa_libraries(&mut self, paths: &[PathBuf]) -> Vec<LadspaLibrary> {
+        self.modified = probe_bundles(&mut self.ladspa_libraries, LADSPA_PROBE_ARG, paths) || self.modified;
+        paths.iter().filter_map(|path| self.ladspa_libraries.get(path).cloned()).collect()
+    }
+
+    // The disabled plugins will be probed again on the next scan
+    pub fn forget_disabled(&mut self) {
+        let count = self.statuses.len() + self.clap_bundles.len() + self.ladspa_libraries.len();
+        self.statuses.retain(|_, status| *status == Status::Enabled);
+        self.clap_bundles.retain(|_, bundle| bundle.status == Status::Enabled);
+        self.ladspa_libraries.retain(|_, library| library.status == Status::Enabled);
+        self.modified = self.modified || self.statuses.len() + self.clap_bundles.len() + self.ladspa_libraries.len() != count;
+    }
+}
+
+fn parse_bundles<D: Description>(bundles: &mut HashMap<PathBuf, Bundle<D>>, content: &str) {
+    for line in content.lines() {
+        let mut fields = line.splitn(4, '\t');
+
+        let (path, modified) = match (fields.next(), fields.next().and_then(|m| m.parse::<u64>().ok())) {
+            (Some(path), Some(modified)) => (PathBuf::from(path), modified),
+            _ => {
+                eprintln!("Unknown plugins scan line {}", line);
+                continue;
+            }
+        };
+        let status = fields.next();
+        let value = fields.next().unwrap_or("");
+
+        let bundle = bundles.entry(path.clone()).or_insert_with(|| Bundle {
+            path: path.clone(),
+            modified,
+            status: Status::Enabled,
+            plugins: Vec::new(),
+        });
+
+        match status {
+            Some(ENABLED_STATUS) => (),
+            Some(DISABLED_STATUS) => bundle.status = Status::Disabled(value.to_string()),
+            Some(PLUGIN_STATUS) => match D::parse(&path, value) {
+                Ok(description) => bundle.plugins.push(description),
+                Err(e) => eprintln!("{}", e),
+            },
+            _ => eprintln!("Unknown plugins scan line {}", line),
+        }
+    }
+}
+
+fn serialize_bundles<D: Description>(bundles: &HashMap<PathBuf, Bundle<D>>) -> String {
+    let mut paths: Vec<&PathBuf> = bundles.keys().collect();
+    paths.sort();
+
+    let mut content = String::new();
+
+    for path in paths {
+        let bundle = &bundles[path];
+        let prefix = format!("{}\t{}", path.to_string_lossy(), bundle.modified);
+
+        match &bundle.status {
+            Status::Enabled => content.push_str(&format!("{}\t{}\n", prefix, ENABLED_STATUS)),
+            Status::Disabled(reason) => {
+                let reason = reason.replace(['\t', '\n'], " ");
+                content.push_str(&format!("{}\t{}\t{}\n", prefix, DISABLED_STATUS, reason))
+            }
+        }
+        for plugin in &bundle.plugins {
+            content.push_str(&format!("{}\t{}\t{}\n", prefix, PLUGIN_STATUS, plugin.serialize()));
+        }
+    }
+    content
+}
+
+// The removed bundles are forgotten then the unknown or modified ones are probed.
+// Returns true if the bundles changed
+fn probe_bundles<D: Description>(bundles: &mut HashMap<PathBuf, Bundle<D>>, arg: &str, paths: &[PathBuf]) -> bool {
+    let count = bundles.len();
+    bundles.retain(|path, _| paths.contains(path));
+    let mut modified = bundles.len() != count;
+
+    let outdated: Vec<String> = paths.iter()
+        .filter(|path| bundles.get(*path).map_or(true, |b| b.modified != modification_time(path)))
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+
+    for (target, result) in probe_all(arg, &outdated) {
+        let path = PathBuf::from(target);
+        let mut plugins = Vec::new();
+
+        if let Ok(output) = &result {
+            for line in output.lines().filter(|l| !l.trim().is_empty()) {
+                match D::parse(&path, line) {
+                    Ok(description) => plugins.push(description),
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+        }
+        let bundle = Bundle {
+            modified: modification_time(&path),
+            status: to_status(result),
+            plugins,
+            path: path.clone(),
+        };
+        bundles.insert(path, bundle);
+        modified = true;
+    }
+    modified
+}
+
+fn modification_time(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_secs())
+}
+
+fn to_status(result: Result<String, String>) -> Status {
+    match result {
+        Ok(_) => Status::Enabled,
+        Err(reason) => Status::Disabled(reason),
+    }
+}
+
+// The targets are probed in parallel since each probe waits for its child process.
+// The targets whose probe could not be run are omitted
+fn probe_all(arg: &str, targets: &[String]) -> Vec<(String, Result<String, String>)> {
+    if targets.is_empty() {
+        return Vec::new();
+    }
+    let workers = thread::available_parallelism().map_or(1, |n| n.get()).min(targets.len());
+    let next = AtomicUsize::new(0);
+    let probed = Mutex::new(Vec::with_capacity(targets.len()));
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while let Some(target) = targets.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    if let Some(result) = probe(arg, target) {
+                        probed.lock().unwrap_or_else(|e| e.into_inner()).push((target.to_string(), result));
+                    }
+                }
+            });
+        }
+    });
+
+    probed.into_inner().unwrap_or_else(|e| e.into_inner())
+}
+
+// The plugin is instantiated in a child process so that its crash does not take the application down.
+// Gives the probe output or the failure reason, None if the probe could not be run
+fn probe(arg: &str, uri: &str) -> Option<Result<String, String>> {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            eprintln!("Plugin {} probe unavailable : {}", uri, e);
+            return None;
+        }
+    };
+
+    let mut child = match Command::new(exe)
+        .arg(arg)
+        .arg(uri)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            eprintln!("Plugin {} probe unavailable : {}", uri, e);
+            return None;
+        }
+    };
+
+    // The outputs are drained while the probe runs so that a verbose plugin can not block on a full pipe
+    let output_reader = child.stdout.take().map(|mut stdout| {
+        thread::spawn(move || {
+            let mut output = String::new();
+            let _ = stdout.read_to_string(&mut output);
+            output
+        })
+    });
+    let error_reader = child.stderr.take().map(|mut stderr| {
+        thread::spawn(move || {
+            let mut error = String::new();
+            let _ = stderr.read_to_string(&mut error);
+            error
+        })
+    });
+
+    let start = Instant::now();
+
+    loop {
+        match child.try_wait() {
+            Ok(Some(exit_status)) => {
+                if exit_status.success() {
+                    return Some(Ok(output_reader.and_then(|reader| reader.join().ok()).unwrap_or_default()));
+                }
+                let error = error_reader.and_then(|reader| reader.join().ok()).unwrap_or_default();
+
+                let reason = match error.lines().rev().find(|l| !l.trim().is_empty()) {
+                    Some(line) => format!("Probe failed ({}) : {}", exit_status, line.trim()),
+                    None => format!("Probe failed ({})", exit_status),
+                };
+                return Some(Err(reason));
+            }
+            Ok(None) => {
+                if start.elapsed() > PROBE_TIMEOUT {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Some(Err(format!("Probe timed out after {} s", PROBE_TIMEOUT.as_secs())));
+                }
+                thread::sleep(PROBE_POLLING_PERIOD);
+            }
+            Err(e) => {
+                eprintln!("Plugin {} probe failed : {}", uri, e);
+                return None;
+            }
+        }
+    }
+}
+
+fn run_probe(uri: &str) -> Result<(), failure::Error> {
+    lv2_handler::visit(|lv2_handler| {
+        match lv2_handler.world.plugin_by_uri(uri) {
+            Some(plugin) => {
+                match unsafe {
+                    plugin.instantiate(
+                        lv2_handler.features.clone(),
+                        AudioFormat::sample_rate() as f64,
+                    )
+                } {
+                    Ok(_) => Ok(()),
+                    _ => Err(failure::err_msg("PluginInstantiationError")),
+                }
+            }
+            None => Err(failure::err_msg(format!("LV2 plugin {} not found.", uri))),
+        }
+    })
+}
+
+// The bundle plugins descriptions are written on the standard output for the application to read them
+fn run_clap_probe(path: &str) -> Result<(), failure::Error> {
+    for description in clap_handler::describe(Path::new(path))? {
+        println!("{}", description.serialize());
+    }
+    Ok(())
+}
+
+fn run_ladspa_probe(path: &str) -> Result<(), failure::Error> {
+    for description in ladspa_handler::describe(Path::new(path))? {
+        println!("{}", description.serialize());
+    }
+    Ok(())
+}
+
+// To call first in main : when the application is launched as a probe, the plugin is probed then the process exits
+pub fn probe_on_request() {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() > 2 && (args[1] == PROBE_ARG || args[1] == CLAP_PROBE_ARG || args[1] == LADSPA_PROBE_ARG) {
+        let result = if args[1] == PROBE_ARG {
+            run_probe(&args[2])
+        } else if args[1] == CLAP_PROBE_ARG {
+            run_clap_probe(&args[2])
+        } else {
+            run_ladspa_probe(&args[2])
+        };
+
+        let code = match result {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("{}", e);
+                1
+            }
+        };
+        std::process::exit(code);
+    }
+}
+
+#[test]
+fn test_plugins_scan_serialization() {
+    let mut scan = PluginsScan::default();
+    scan.statuses.insert("urn:ok".to_string(), Status::Enabled);
+    scan.statuses.insert("urn:ko".to_string(), Status::Disabled("Probe failed (signal: 11)".to_string()));
+
+    let parsed = PluginsScan::parse(&scan.serialize());
+    assert!(parsed.statuses == scan.statuses);
+    assert!(parsed.statuses["urn:ko"] == Status::Disabled("Probe failed (signal: 11)".to_string()));
+
+    // The known plugins are not probed again
+    let mut parsed = parsed;
+    parsed.probe_unknown(&vec!["urn:ok".to_string(), "urn:ko".to_string()]);
+    assert!(!parsed.modified);
+}
+
+#[test]
+fn test_clap_plugins_scan_serialization() {
+    let path = PathBuf::from("/usr/lib/clap/synth.clap");
+    let mut scan = PluginsScan::default();
+
+    scan.clap_bundles.insert(path.clone(), ClapBundle {
+        path: path.clone(),
+        modified: 1700000000,
+        status: Status::Enabled,
+        plugins: vec![clap_handler::PluginDescription {
+            path: path.clone(),
+            id: "org.example.synth".to_string(),
+            name: "Synth".to_string(),
+            categories: vec!["Instrument".to_string()],
+        }],
+    });
+    let ko_path = PathBuf::from("/usr/lib/clap/crash.clap");
+    scan.clap_bundles.insert(ko_path.clone(), ClapBundle {
+        path: ko_path.clone(),
+        // Modification time of a missing file so that the bundle is not probed again
+        modified: 0,
+        status: Status::Disabled("Probe failed (signal: 11)".to_string()),
+        plugins: Vec::new(),
+    });
+
+    let mut parsed = PluginsScan::default();
+    parse_bundles(&mut parsed.clap_bundles, &serialize_bundles(&scan.clap_bundles));
+    assert!(parsed.clap_bundles == scan.clap_bundles);
+
+    // The removed bundles are forgotten, the disabled ones are kept until the user forgets them
+    assert!(parsed.clap_bundles(&vec![ko_path.clone()]).len() == 1);
+    assert!(parsed.modified && !parsed.clap_bundles.contains_key(&path));
+
+    parsed.forget_disabled();
+    assert!(parsed.clap_bundles.is_empty());
+}
+
+#[test]
+fn test_ladspa_plugins_scan_serialization() {
+    let path = PathBuf::from("/usr/lib/ladspa/amp.so");
+    let mut scan = PluginsScan::default();
+
+    scan.ladspa_libraries.insert(path.clone(), LadspaLibrary {
+        path: path.clone(),
+        // Modification time of a missing file so that the library is not probed again
+        modified: 0,
+        status: Status::Enabled,
+        plugins: vec![ladspa_handler::PluginDescription {
+            path: path.clone(),
+            unique_id: 1048,
+            name: "Mono Amplifier".to_string(),
+        }],
+    });
+
+    let mut parsed = PluginsScan::default();
+    parse_bundles(&mut parsed.ladspa_libraries, &serialize_bundles(&scan.ladspa_libraries));
+    assert!(parsed.ladspa_libraries == scan.ladspa_libraries);
+
+    // The known libraries are not probed again
+    assert!(parsed.ladspa_libraries(&vec![path.clone()]) == vec![scan.ladspa_libraries[&path].clone()]);
+    assert!(!parsed.modified);
+}
@@ -22,13 +22,18 @@ use std::io::Write;
 use luil::ui_connector::UiConnector;
 
 use talker::identifier::{self, Id, Index};
+use talker::lv2_handler;
 use talker::talker::RTalker;
 use talker::audio_format::AudioFormat;
 
 use crate::band::{Band, EarHum, Operation};
+use crate::midi_mapping::MidiMapping;
 use crate::mixer::RMixer;
-use crate::player::Player;
+use crate::player::{Player, Report};
+use crate::settings::{FeedbackSettings, LoopSettings, MidiSettings, OscSettings, Settings};
 use crate::state::State;
+use crate::talkers::automation::{self, Breakpoint};
+use crate::talkers::lv2;
 
 pub const SESSION_FILE_EXT: &str = ".gsr";
 pub const NEW_SESSION_FILENAME: &str = "new_session.gsr";
@@ -41,16 +46,20 @@ pub struct Session {
     filename: String,
     band: Band,
     player: Player,
+    settings: Settings,
     start_tick: i64,
     end_tick: i64,
 }
 
 impl Session {
     pub fn new(band_description: String) -> Result<Session, failure::Error> {
+        let settings = Settings::load();
+
         Ok(Self {
             filename: NEW_SESSION_FILENAME.to_string(),
             band: Band::make(&band_description, false)?,
-            player: Player::new(band_description)?,
+            player: Player::new(band_description, settings.clone())?,
+            settings,
             start_tick: 0,
             end_tick: 0,
         })
@@ -62,10 +71,13 @@ impl Session {
         let mut f = File::open(filename)?;
         f.read_to_string(&mut band_description)?;
 
+        let settings = Settings::load();
+
         Ok(Self {
             filename: filename.to_string(),
             band: Band::make(&band_description, false)?,
-            player: Player::new(band_description)?,
+            player: Player::new(band_description, settings.clone())?,
+            settings,
             start_tick: 0,
             end_tick: 0,
         })
@@ -113,6 +125,10 @@ impl Session {
         self.player.set_time_range(self.start_tick, self.end_tick)
     }
 
+    pub fn tempo(&self) -> f32 {
+        self.band.tempo()
+    }
+
     pub fn end_tick(&self) -> i64 {
         self.end_tick
     }
@@ -123,25 +139,73 @@ impl Session {
         self.player.set_time_range(self.start_tick, self.end_tick)
     }
 
+    pub fn seek(&mut self, t: i64) -> Result<State, failure::Error> {
+        self.player.seek(t)
+    }
+
     pub fn player<'a>(&'a mut self) -> &'a Player {
         &self.player
     }
+
+    pub fn receive_reports(&self) -> Vec<Report> {
+        self.player.receive_reports()
+    }
+
+    pub fn feedback_settings(&self) -> &FeedbackSettings {
+        &self.settings.feedback
+    }
+
+    pub fn set_feedback_settings(&mut self, feedback_settings: FeedbackSettings) -> Result<State, failure::Error> {
+        self.settings.feedback = feedback_settings;
+        self.settings.save()?;
+        self.player.set_feedback_settings(self.settings.feedback.clone())
+    }
+
+    pub fn loop_settings(&self) -> &LoopSettings {
+        &self.settings.looping
+    }
+
+    pub fn set_loop_settings(&mut self, loop_settings: LoopSettings) -> Result<State, failure::Error> {
+        self.settings.looping = loop_settings;
+        self.settings.save()?;
+        self.player.set_loop_settings(self.settings.looping.clone())
+    }
+
+    pub fn osc_settings(&self) -> &OscSettings {
+        &self.settings.osc
+    }
+
+    pub fn midi_settings(&self) -> &MidiSettings {
+        &self.settings.midi
+    }
+
+    pub fn set_midi_settings(&mut self, midi_settings: MidiSettings) -> Result<(), failure::Error> {
+        self.settings.midi = midi_settings;
+        self.settings.save()?;
+        self.player.set_midi_settings(&self.settings.midi);
+        Ok(())
+    }
+
+    pub fn has_midi_input(&self) -> bool {
+        self.player.has_midi_input()
+    }
+
     pub fn new_band(&mut self) -> Result<(), failure::Error> {
         self.band = Band::empty(false);
-        self.player = Player::new("".to_string())?;
+        self.player = Player::new("".to_string(), self.settings.clone())?;
         Ok(())
     }
 
     pub fn init(&mut self, band_description: String) -> Result<(), failure::Error> {
         self.band = Band::make(&band_description, false)?;
-        self.player = Player::new(band_description)?;
+        self.player = Player::new(band_description, self.settings.clone())?;
         Ok(())
     }
 
     fn check_not_exited(&mut self) -> Result<(), failure::Error> {
 
         if self.player.state() == State::Exited {
-            self.player = Player::new(self.band.serialize()?)?;
+            self.player = Player::new(self.band.serialize()?, self.settings.clone())?;
         }
         Ok(())
     }
@@ -182,10 +246,47 @@ impl Session {
         Ok((modification_count, ui_count))
     }
 
+    // The modifications made by the player are only applied on the session band
+    pub fn apply_player_modification(&mut self, operation: &Operation) -> Result<(), failure::Error> {
+        self.band.modify(operation)
+    }
+
+    pub fn midi_mapping(&self, talker_id: Id, ear_idx: Index, set_idx: Index, hum_idx: Index) -> Option<MidiMapping> {
+        self.band.find_midi_mapping(talker_id, ear_idx, set_idx, hum_idx).cloned()
+    }
+
+    // The mapping controller will be the next one moved. None cancels the learning
+    pub fn learn_midi(&mut self, mapping: Option<MidiMapping>) -> Result<State, failure::Error> {
+        self.player.learn_midi(mapping)
+    }
+
     pub fn backup_ear_hum(&self, talker_id: Id, ear_idx: Index, set_idx: Index, hum_idx: Index) -> Result<EarHum, failure::Error> {
         self.band.backup_ear_hum(talker_id, ear_idx, set_idx, hum_idx)
     }
 
+    // Breakpoints of the hum automation, None if the hum is not automated
+    pub fn automation(&self, talker_id: Id, ear_idx: Index, set_idx: Index, hum_idx: Index) -> Result<Option<Vec<Breakpoint>>, failure::Error> {
+        match self.band.find_automation(talker_id, ear_idx, set_idx, hum_idx)? {
+            Some(tkr) => Ok(Some(automation::parse_breakpoints(&tkr.data_string().unwrap_or_default())?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn lv2_presets(&self, talker_id: Id) -> Result<Vec<(String, String)>, failure::Error> {
+        let tkr = self.band.fetch_talker(&talker_id)?;
+        lv2::presets(&tkr.model())
+    }
+
+    pub fn lv2_control_inputs(&self, talker_id: Id) -> Result<Vec<lv2_handler::ControlInput>, failure::Error> {
+        let tkr = self.band.fetch_talker(&talker_id)?;
+        lv2_handler::get_control_inputs(&tkr.model())
+    }
+
+    pub fn save_lv2_preset(&self, talker_id: Id, name: &str) -> Result<String, failure::Error> {
+        let tkr = self.band.fetch_talker(&talker_id)?;
+        lv2::save_preset(tkr, name)
+    }
+
     pub fn serialize_band(&self) -> Result<String, failure::Error> {
         self.band.serialize()
     }
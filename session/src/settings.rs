@@ -0,0 +1,183 @@
+use std::fs;
+use std::str::FromStr;
+
+use crate::util;
+
+const SETTINGS_FILENAME: &str = "settings";
+
+const FEEDBACK_DEVICE_KEY: &str = "feedback_device";
+const FEEDBACK_BUFFER_SIZE_KEY: &str = "feedback_buffer_size";
+const FEEDBACK_MIXERS_KEY: &str = "feedback_mixers";
+const LOOP_CROSSFADE_KEY: &str = "loop_crossfade";
+const LOOP_COUNT_KEY: &str = "loop_count";
+const LOOP_PUNCH_IN_KEY: &str = "loop_punch_in";
+const OSC_PORT_KEY: &str = "osc_port";
+const OSC_ADDRESS_KEY: &str = "osc_address";
+const MIDI_INPUTS_KEY: &str = "midi_inputs";
+
+pub const DEFAULT_OSC_ADDRESS: &str = "127.0.0.1";
+
+pub const DEFAULT_LOOP_CROSSFADE: usize = 10;
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct FeedbackSettings {
+    // None for the host default device
+    pub device: Option<String>,
+    // None for the device default buffer size
+    pub buffer_size: Option<usize>,
+    // Names of the mixers summed to the feedback. Empty for the first mixer
+    pub mixers: Vec<String>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct LoopSettings {
+    // Crossfade duration in milliseconds at the wrap point. 0 for a hard cut
+    pub crossfade: usize,
+    // Number of passes before the player stops. 0 for endless
+    pub count: usize,
+    // When recording, only the loop region is written
+    pub punch_in: bool,
+}
+
+impl Default for LoopSettings {
+    fn default() -> Self {
+        Self {
+            crossfade: DEFAULT_LOOP_CROSSFADE,
+            count: 0,
+            punch_in: false,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct OscSettings {
+    // UDP port of the OSC server. None when the server is disabled
+    pub port: Option<u16>,
+    // Address the server is bound to. None for the local host only
+    pub address: Option<String>,
+}
+
+impl OscSettings {
+    pub fn bind_address(&self) -> &str {
+        self.address.as_deref().unwrap_or(DEFAULT_OSC_ADDRESS)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct MidiSettings {
+    // Names of the listened input ports. Empty for every available port
+    pub inputs: Vec<String>,
+}
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Settings {
+    pub feedback: FeedbackSettings,
+    pub looping: LoopSettings,
+    pub osc: OscSettings,
+    pub midi: MidiSettings,
+}
+
+impl Settings {
+    pub fn load() -> Settings {
+        let path = util::configuration_path().join(SETTINGS_FILENAME);
+
+        match fs::read_to_string(&path) {
+            Ok(content) => Settings::parse(&content),
+            Err(_) => Settings::default(),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), failure::Error> {
+        let directory = util::configuration_path();
+        fs::create_dir_all(&directory)?;
+        fs::write(directory.join(SETTINGS_FILENAME), self.serialize())?;
+        Ok(())
+    }
+
+    fn parse(content: &str) -> Settings {
+        let mut settings = Settings::default();
+
+        for line in content.lines() {
+            if let Some((key, value)) = line.split_once('=') {
+                match key.trim() {
+                    FEEDBACK_DEVICE_KEY => settings.feedback.device = Some(value.to_string()),
+                    FEEDBACK_BUFFER_SIZE_KEY => match usize::from_str(value.trim()) {
+                        Ok(size) => settings.feedback.buffer_size = Some(size),
+                        Err(e) => eprintln!("Setting {} {} : {}", key, value, e),
+                    },
+                    FEEDBACK_MIXERS_KEY => {
+                        settings.feedback.mixers = value.split('|').filter(|n| !n.is_empty()).map(|n| n.to_string()).collect()
+                    }
+                    LOOP_CROSSFADE_KEY => match usize::from_str(value.trim()) {
+                        Ok(crossfade) => settings.looping.crossfade = crossfade,
+                        Err(e) => eprintln!("Setting {} {} : {}", key, value, e),
+                    },
+                    LOOP_COUNT_KEY => match usize::from_str(value.trim()) {
+                        Ok(count) => settings.looping.count = count,
+                        Err(e) => eprintln!("Setting {} {} : {}", key, value, e),
+                    },
+                    LOOP_PUNCH_IN_KEY => settings.looping.punch_in = value.trim() == "true",
+                    OSC_PORT_KEY => match u16::from_str(value.trim()) {
+                        Ok(port) => settings.osc.port = Some(port),
+                        Err(e) => eprintln!("Setting {} {} : {}", key, value, e),
+                    },
+                    OSC_ADDRESS_KEY => settings.osc.address = Some(value.trim().to_string()),
+                    MIDI_INPUTS_KEY => {
+                        settings.midi.inputs = value.split('|').filter(|n| !n.is_empty()).map(|n| n.to_string()).collect()
+                    }
+                    _ => eprintln!("Unknown setting {}", key),
+                }
+            }
+        }
+        settings
+    }
+
+    fn serialize(&self) -> String {
+        let mut content = String::new();
+
+        if let Some(device) = &self.feedback.device {
+            content.push_str(&format!("{}={}\n", FEEDBACK_DEVICE_KEY, device));
+        }
+        if let Some(buffer_size) = self.feedback.buffer_size {
+            content.push_str(&format!("{}={}\n", FEEDBACK_BUFFER_SIZE_KEY, buffer_size));
+        }
+        if !self.feedback.mixers.is_empty() {
+            content.push_str(&format!("{}={}\n", FEEDBACK_MIXERS_KEY, self.feedback.mixers.join("|")));
+        }
+        if self.looping.crossfade != DEFAULT_LOOP_CROSSFADE {
+            content.push_str(&format!("{}={}\n", LOOP_CROSSFADE_KEY, self.looping.crossfade));
+        }
+        if self.looping.count > 0 {
+            content.push_str(&format!("{}={}\n", LOOP_COUNT_KEY, self.looping.count));
+        }
+        if self.looping.punch_in {
+            content.push_str(&format!("{}={}\n", LOOP_PUNCH_IN_KEY, self.looping.punch_in));
+        }
+        if let Some(port) = self.osc.port {
+            content.push_str(&format!("{}={}\n", OSC_PORT_KEY, port));
+        }
+        if let Some(address) = &self.osc.address {
+            content.push_str(&format!("{}={}\n", OSC_ADDRESS_KEY, address));
+        }
+        if !self.midi.inputs.is_empty() {
+            content.push_str(&format!("{}={}\n", MIDI_INPUTS_KEY, self.midi.inputs.join("|")));
+        }
+        content
+    }
+}
+
+#[test]
+fn test_settings_serialization() {
+    let mut settings = Settings::default();
+    assert!(Settings::parse(&settings.serialize()) == settings);
+
+    settings.feedback.device = Some("USB Audio".to_string());
+    settings.feedback.buffer_size = Some(256);
+    settings.feedback.mixers = vec!["main".to_string(), "drums".to_string()];
+    settings.looping = LoopSettings { crossfade: 0, count: 4, punch_in: true };
+    settings.osc.port = Some(9000);
+    assert!(settings.osc.bind_address() == DEFAULT_OSC_ADDRESS);
+    settings.osc.address = Some("0.0.0.0".to_string());
+    settings.midi.inputs = vec!["Midi Through Port-0".to_string(), "nanoKONTROL2".to_string()];
+    assert!(Settings::parse(&settings.serialize()) == settings);
+}
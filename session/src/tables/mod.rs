@@ -28,7 +28,10 @@ pub fn create_fadein_fadeout(sample_rate: usize) -> (Vec<f32>, Vec<f32>) {
 }
 
 pub fn create_fadeout(sample_rate: usize) -> Vec<f32> {
-    let len = fade_len(sample_rate);
+    create_fadeout_of_len(fade_len(sample_rate))
+}
+
+pub fn create_fadeout_of_len(len: usize) -> Vec<f32> {
     let mut fadeout_tab = Vec::with_capacity(len);
 
     for i in 0..len {
@@ -0,0 +1,135 @@
+use std::str::FromStr;
+
+use talker::ctalker;
+use talker::data::Data;
+use talker::identifier::Index;
+use talker::talker::{CTalker, Talker, TalkerBase};
+
+pub const MODEL: &str = "Automation";
+
+// The voice listened depends on the automated hum type
+pub const AUDIO_PORT: Index = 0;
+pub const CV_PORT: Index = 1;
+
+// Value of the automated hum at a tick
+pub type Breakpoint = (i64, f32);
+
+// The breakpoints are stored in the talker data as "tick:value" separated by spaces
+pub fn parse_breakpoints(s: &str) -> Result<Vec<Breakpoint>, failure::Error> {
+    let mut points = Vec::new();
+
+    for point in s.split_whitespace() {
+        let (tick, value) = point.split_once(':')
+            .ok_or(failure::err_msg(format!("{} breakpoint {} has no value", MODEL, point)))?;
+
+        let tick = i64::from_str(tick)
+            .map_err(|e| failure::err_msg(format!("{} breakpoint {} tick : {}", MODEL, point, e)))?;
+        let value = f32::from_str(value)
+            .map_err(|e| failure::err_msg(format!("{} breakpoint {} value : {}", MODEL, point, e)))?;
+
+        points.push((tick, value));
+    }
+    points.sort_by_key(|(tick, _)| *tick);
+    Ok(points)
+}
+
+pub fn serialize_breakpoints(points: &Vec<Breakpoint>) -> String {
+    points.iter()
+        .map(|(tick, value)| format!("{}:{}", tick, value))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// The value is interpolated linearly between the breakpoints and held before the first and after the last
+pub fn value_at(points: &Vec<Breakpoint>, tick: i64) -> f32 {
+    let next = points.partition_point(|(t, _)| *t <= tick);
+
+    if next == 0 {
+        points.first().map_or(0., |(_, v)| *v)
+    } else if next == points.len() {
+        points[next - 1].1
+    } else {
+        let (prev_tick, prev_value) = points[next - 1];
+        let (next_tick, next_value) = points[next];
+        let ratio = (tick - prev_tick) as f32 / (next_tick - prev_tick) as f32;
+
+        prev_value + (next_value - prev_value) * ratio
+    }
+}
+
+// The recorded breakpoints replace the lane breakpoints on the recorded ticks range
+pub fn merge(lane: &Vec<Breakpoint>, recorded: &Vec<Breakpoint>) -> Vec<Breakpoint> {
+    let (first, last) = match (recorded.first(), recorded.last()) {
+        (Some((first, _)), Some((last, _))) => (*first, *last),
+        _ => return lane.clone(),
+    };
+
+    let mut points: Vec<Breakpoint> = lane.iter()
+        .filter(|(tick, _)| *tick < first || *tick > last)
+        .cloned()
+        .collect();
+
+    points.extend(recorded.iter().cloned());
+    points.sort_by_key(|(tick, _)| *tick);
+    points.dedup_by_key(|(tick, _)| *tick);
+    points
+}
+
+pub struct Automation {
+    points: Vec<Breakpoint>,
+}
+
+impl Automation {
+    pub fn new(mut base: TalkerBase) -> Result<CTalker, failure::Error> {
+        base.add_audio_voice(Some("audio"), 0.);
+        base.add_cv_voice(Some("cv"), 0.);
+
+        base.set_data(Data::String(String::new()));
+
+        Ok(ctalker!(base, Self { points: Vec::new() }))
+    }
+}
+
+impl Talker for Automation {
+    fn set_data_update(
+        &mut self,
+        base: &TalkerBase,
+        data: Data,
+    ) -> Result<Option<TalkerBase>, failure::Error> {
+        match data {
+            Data::String(ref s) => {
+                self.points = parse_breakpoints(s)?;
+                base.set_data(data);
+                Ok(None)
+            }
+            _ => Err(failure::err_msg(format!("{} data type {} is not String", MODEL, data.type_str()))),
+        }
+    }
+
+    fn talk(&mut self, base: &TalkerBase, port: usize, tick: i64, len: usize) -> usize {
+        let voice = base.voice(port);
+        let buf = if port == AUDIO_PORT { voice.audio_buffer() } else { voice.cv_buffer() };
+        let ln = len.min(buf.len());
+
+        for (i, v) in buf[..ln].iter_mut().enumerate() {
+            *v = value_at(&self.points, tick + i as i64);
+        }
+        voice.set_tick_len(tick, ln);
+        ln
+    }
+}
+
+#[test]
+fn test_breakpoints() {
+    let points = parse_breakpoints("100:1 0:0 200:0.5").unwrap();
+    assert!(points == vec![(0, 0.), (100, 1.), (200, 0.5)]);
+    assert!(parse_breakpoints(&serialize_breakpoints(&points)).unwrap() == points);
+
+    assert!(value_at(&points, -10) == 0.);
+    assert!(value_at(&points, 50) == 0.5);
+    assert!(value_at(&points, 150) == 0.75);
+    assert!(value_at(&points, 300) == 0.5);
+
+    let merged = merge(&points, &vec![(50, 0.2), (120, 0.3)]);
+    assert!(merged == vec![(0, 0.), (50, 0.2), (120, 0.3), (200, 0.5)]);
+}
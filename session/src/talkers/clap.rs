@@ -0,0 +1,840 @@
+use std::ffi::{c_void, CStr};
+use std::mem::{self, MaybeUninit};
+use std::path::Path;
+use std::ptr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use clap_sys::audio_buffer::clap_audio_buffer;
+use clap_sys::events::{
+    clap_event_header, clap_event_midi, clap_event_note, clap_event_param_value, clap_event_transport,
+    clap_input_events, clap_output_events, CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_MIDI, CLAP_EVENT_NOTE_OFF,
+    CLAP_EVENT_NOTE_ON, CLAP_EVENT_PARAM_VALUE, CLAP_EVENT_TRANSPORT, CLAP_TRANSPORT_HAS_BEATS_TIMELINE,
+    CLAP_TRANSPORT_HAS_SECONDS_TIMELINE, CLAP_TRANSPORT_HAS_TEMPO, CLAP_TRANSPORT_HAS_TIME_SIGNATURE,
+    CLAP_TRANSPORT_IS_PLAYING,
+};
+use clap_sys::ext::audio_ports::{clap_audio_port_info, clap_plugin_audio_ports, CLAP_EXT_AUDIO_PORTS};
+use clap_sys::ext::latency::{clap_plugin_latency, CLAP_EXT_LATENCY};
+use clap_sys::ext::note_ports::{clap_note_port_info, clap_plugin_note_ports, CLAP_EXT_NOTE_PORTS, CLAP_NOTE_DIALECT_MIDI};
+use clap_sys::ext::params::{clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS, CLAP_PARAM_IS_READONLY};
+use clap_sys::ext::state::{clap_plugin_state, CLAP_EXT_STATE};
+use clap_sys::fixedpoint::{clap_beattime, clap_sectime, CLAP_BEATTIME_FACTOR, CLAP_SECTIME_FACTOR};
+use clap_sys::id::clap_id;
+use clap_sys::plugin::clap_plugin;
+use clap_sys::process::clap_process;
+use clap_sys::stream::{clap_istream, clap_ostream};
+
+use talker::audio_format::{self, AudioFormat};
+use talker::ctalker;
+use talker::ear;
+use talker::ear::Init;
+use talker::lv2_handler;
+use talker::talker::{CTalker, Talker, TalkerBase};
+use talker::transport::{self, Transport};
+
+use clap_handler::{self, Library, PluginHost};
+use midi;
+use util;
+
+struct NotePort {
+    tkr_port: usize,
+    midi_dialect: bool,
+}
+
+struct Param {
+    id: clap_id,
+    tkr_port: usize,
+    value: f64,
+}
+
+// Audio port of the plugin and the talker ears or voices of its channels
+struct AudioPort {
+    tkr_ports: Vec<usize>,
+}
+
+// Events sent to the plugin during a process call
+#[derive(Default)]
+struct InputEvents {
+    params: Vec<clap_event_param_value>,
+    midis: Vec<clap_event_midi>,
+    notes: Vec<clap_event_note>,
+    headers: Vec<*const clap_event_header>,
+}
+
+// MIDI data of the note events received from the plugin by output port index
+#[derive(Default)]
+struct OutputEvents {
+    midis: Vec<(usize, u32, [u8; 3])>,
+}
+
+unsafe extern "C" fn input_events_size(list: *const clap_input_events) -> u32 {
+    let events = &*((*list).ctx as *const InputEvents);
+    events.headers.len() as u32
+}
+
+unsafe extern "C" fn input_events_get(list: *const clap_input_events, index: u32) -> *const clap_event_header {
+    let events = &*((*list).ctx as *const InputEvents);
+    events.headers.get(index as usize).copied().unwrap_or(ptr::null())
+}
+
+unsafe extern "C" fn output_events_try_push(list: *const clap_output_events, event: *const clap_event_header) -> bool {
+    let events = &mut *((*list).ctx as *mut OutputEvents);
+    let header = &*event;
+
+    if header.space_id != CLAP_CORE_EVENT_SPACE_ID {
+        return true;
+    }
+    match header.type_ {
+        CLAP_EVENT_MIDI => {
+            let ev = &*(event as *const clap_event_midi);
+            events.midis.push((ev.port_index as usize, header.time, ev.data));
+        }
+        CLAP_EVENT_NOTE_ON | CLAP_EVENT_NOTE_OFF => {
+            let ev = &*(event as *const clap_event_note);
+            let (status, velocity) = if header.type_ == CLAP_EVENT_NOTE_ON {
+                (midi::NOTE_ON, (ev.velocity * 127.).round() as u8)
+            } else {
+                (midi::NOTE_OFF, 0)
+            };
+            let channel = ev.channel.max(0) as u8 & 0x0F;
+            events.midis.push((ev.port_index.max(0) as usize, header.time, [status | channel, ev.key.max(0) as u8 & 0x7F, velocity]));
+        }
+        _ => (),
+    }
+    true
+}
+
+unsafe extern "C" fn ostream_write(stream: *const clap_ostream, buffer: *const c_void, size: u64) -> i64 {
+    let data = &mut *((*stream).ctx as *mut Vec<u8>);
+    data.extend_from_slice(std::slice::from_raw_parts(buffer as *const u8, size as usize));
+    size as i64
+}
+
+struct StateReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+unsafe extern "C" fn istream_read(stream: *const clap_istream, buffer: *mut c_void, size: u64) -> i64 {
+    let reader = &mut *((*stream).ctx as *mut StateReader);
+    let len = (size as usize).min(reader.data.len() - reader.position);
+
+    ptr::copy_nonoverlapping(reader.data[reader.position..].as_ptr(), buffer as *mut u8, len);
+    reader.position += len;
+    len as i64
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Result<Vec<u8>, failure::Error> {
+    let s = s.trim();
+
+    if s.len() % 2 != 0 {
+        return Err(failure::err_msg("CLAP state has an odd length"));
+    }
+    (0..s.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|e| failure::err_msg(format!("CLAP state decoding failed : {}", e))))
+        .collect()
+}
+
+#[test]
+fn test_state_hex() {
+    let data = vec![0, 1, 127, 128, 255];
+    assert!(from_hex(&to_hex(&data)).unwrap() == data);
+}
+
+pub struct Clap {
+    // Kept for the plugin code to stay loaded
+    _library: Option<Arc<Library>>,
+    // Kept for the plugin to reach its host
+    host: Box<PluginHost>,
+    id: String,
+    plugin: *const clap_plugin,
+    audio_inputs: Vec<AudioPort>,
+    audio_outputs: Vec<AudioPort>,
+    // Process buffers allocated once, their channels pointers being set on each chunk
+    inputs_channels: Vec<Vec<*mut f32>>,
+    outputs_channels: Vec<Vec<*mut f32>>,
+    inputs_buffers: Vec<clap_audio_buffer>,
+    outputs_buffers: Vec<clap_audio_buffer>,
+    transport: clap_event_transport,
+    note_inputs: Vec<NotePort>,
+    note_outputs: Vec<NotePort>,
+    params: Vec<Param>,
+    midi_urid: lv2_raw::LV2Urid,
+    active: bool,
+    processing: bool,
+    input_events: InputEvents,
+    output_events: OutputEvents,
+}
+
+impl Clap {
+    unsafe fn extension<T>(plugin: *const clap_plugin, id: &CStr) -> Option<&'static T> {
+        match (*plugin).get_extension {
+            Some(get_extension) => (get_extension(plugin, id.as_ptr()) as *const T).as_ref(),
+            None => None,
+        }
+    }
+
+    pub fn new(path: &Path, model: &str, base: TalkerBase) -> Result<CTalker, failure::Error> {
+        let id = model.strip_prefix(clap_handler::MODEL_PREFIX).unwrap_or(model).to_string();
+        let library = clap_handler::library(path)?;
+        let host = PluginHost::new();
+        let plugin = library.create_plugin(&id, &host)?;
+
+        Clap::with_plugin(Some(library), host, id, plugin, base)
+    }
+
+    fn with_plugin(
+        library: Option<Arc<Library>>,
+        host: Box<PluginHost>,
+        id: String,
+        plugin: *const clap_plugin,
+        mut base: TalkerBase,
+    ) -> Result<CTalker, failure::Error> {
+        let mut audio_inputs = Vec::new();
+        let mut audio_outputs = Vec::new();
+        let mut note_inputs = Vec::new();
+        let mut note_outputs = Vec::new();
+        let mut params = Vec::new();
+        let mut inputs_count = 0;
+        let mut outputs_count = 0;
+
+        unsafe {
+            base.set_name(&util::to_string((*(*plugin).desc).name));
+
+            if let Some(audio_ports) = Clap::extension::<clap_plugin_audio_ports>(plugin, CLAP_EXT_AUDIO_PORTS) {
+                if let (Some(count), Some(get)) = (audio_ports.count, audio_ports.get) {
+                    for is_input in [true, false] {
+                        for index in 0..count(plugin, is_input) {
+                            let mut info = MaybeUninit::<clap_audio_port_info>::zeroed();
+
+                            if !get(plugin, index, is_input, info.as_mut_ptr()) {
+                                continue;
+                            }
+                            let info = info.assume_init();
+                            let name = util::to_string(info.name.as_ptr());
+                            let mut tkr_ports = Vec::new();
+
+                            for channel in 0..info.channel_count {
+                                let tag = if info.channel_count > 1 { format!("{} {}", name, channel + 1) } else { name.clone() };
+
+                                if is_input {
+                                    base.add_ear(ear::audio(
+                                        Some(&tag),
+                                        audio_format::MIN_AUDIO,
+                                        audio_format::MAX_AUDIO,
+                                        audio_format::DEF_AUDIO,
+                                        &Init::DefValue,
+                                    )?);
+                                    tkr_ports.push(inputs_count);
+                                    inputs_count += 1;
+                                } else {
+                                    base.add_audio_voice(Some(&tag), 0.);
+                                    tkr_ports.push(outputs_count);
+                                    outputs_count += 1;
+                                }
+                            }
+                            if is_input {
+                                audio_inputs.push(AudioPort { tkr_ports });
+                            } else {
+                                audio_outputs.push(AudioPort { tkr_ports });
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let Some(note_ports) = Clap::extension::<clap_plugin_note_ports>(plugin, CLAP_EXT_NOTE_PORTS) {
+                if let (Some(count), Some(get)) = (note_ports.count, note_ports.get) {
+                    for is_input in [true, false] {
+                        for index in 0..count(plugin, is_input) {
+                            let mut info = MaybeUninit::<clap_note_port_info>::zeroed();
+
+                            if !get(plugin, index, is_input, info.as_mut_ptr()) {
+                                continue;
+                            }
+                            let info = info.assume_init();
+                            let name = util::to_string(info.name.as_ptr());
+                            let midi_dialect = info.supported_dialects & CLAP_NOTE_DIALECT_MIDI != 0;
+
+                            if is_input {
+                                let ear = lv2_handler::visit(|h| ear::atom(Some(&name), Some(h)))?;
+                                base.add_ear(ear);
+                                note_inputs.push(NotePort { tkr_port: inputs_count, midi_dialect });
+                                inputs_count += 1;
+                            } else {
+                                lv2_handler::visit(|h| Ok(base.add_atom_voice(Some(&name), Some(h))))?;
+                                note_outputs.push(NotePort { tkr_port: outputs_count, midi_dialect });
+                                outputs_count += 1;
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let Some(plugin_params) = Clap::extension::<clap_plugin_params>(plugin, CLAP_EXT_PARAMS) {
+                if let (Some(count), Some(get_info)) = (plugin_params.count, plugin_params.get_info) {
+                    for index in 0..count(plugin) {
+                        let mut info = MaybeUninit::<clap_param_info>::zeroed();
+
+                        if !get_info(plugin, index, info.as_mut_ptr()) {
+                            continue;
+                        }
+                        let info = info.assume_init();
+
+                        if info.flags & CLAP_PARAM_IS_READONLY != 0 {
+                            continue;
+                        }
+                        let name = util::to_string(info.name.as_ptr());
+
+                        base.add_ear(ear::control(
+                            Some(&name),
+                            info.min_value as f32,
+                            info.max_value as f32,
+                            info.default_value as f32,
+                        )?);
+                        params.push(Param { id: info.id, tkr_port: inputs_count, value: f64::NAN });
+                        inputs_count += 1;
+                    }
+                }
+            }
+        }
+
+        let midi_urid = lv2_handler::visit(|lv2_handler| Ok(lv2_handler.features.midi_urid()))?;
+
+        let mut inputs_channels: Vec<Vec<*mut f32>> = audio_inputs.iter().map(|port| vec![ptr::null_mut(); port.tkr_ports.len()]).collect();
+        let mut outputs_channels: Vec<Vec<*mut f32>> = audio_outputs.iter().map(|port| vec![ptr::null_mut(); port.tkr_ports.len()]).collect();
+
+        // The channels vectors are not resized so their pointers stay valid
+        let to_audio_buffer = |channels: &mut Vec<*mut f32>| clap_audio_buffer {
+            data32: channels.as_mut_ptr(),
+            data64: ptr::null_mut(),
+            channel_count: channels.len() as u32,
+            latency: 0,
+            constant_mask: 0,
+        };
+        let inputs_buffers = inputs_channels.iter_mut().map(to_audio_buffer).collect();
+        let outputs_buffers = outputs_channels.iter_mut().map(to_audio_buffer).collect();
+
+        let mut transport: clap_event_transport = unsafe { mem::zeroed() };
+        transport.header = clap_event_header {
+            size: mem::size_of::<clap_event_transport>() as u32,
+            time: 0,
+            space_id: CLAP_CORE_EVENT_SPACE_ID,
+            type_: CLAP_EVENT_TRANSPORT,
+            flags: 0,
+        };
+
+        Ok(ctalker!(
+            base,
+            Self {
+                _library: library,
+                host,
+                id,
+                plugin,
+                audio_inputs,
+                audio_outputs,
+                inputs_channels,
+                outputs_channels,
+                inputs_buffers,
+                outputs_buffers,
+                transport,
+                note_inputs,
+                note_outputs,
+                params,
+                midi_urid,
+                active: false,
+                processing: false,
+                input_events: InputEvents::default(),
+                output_events: OutputEvents::default(),
+            }
+        ))
+    }
+
+    fn start(&mut self) -> bool {
+        unsafe {
+            if !self.active {
+                if let Some(activate) = (*self.plugin).activate {
+                    self.active = activate(
+                        self.plugin,
+                        AudioFormat::sample_rate() as f64,
+                        audio_format::MIN_CHUNK_SIZE as u32,
+                        AudioFormat::chunk_size() as u32,
+                    );
+                }
+            }
+            if self.active && !self.processing {
+                if let Some(start_processing) = (*self.plugin).start_processing {
+                    self.processing = start_processing(self.plugin);
+                }
+            }
+        }
+        self.processing
+    }
+
+    // Serve the plugin requests made from its host since the last chunk
+    fn serve_host_requests(&mut self, base: &TalkerBase) {
+        let requests = self.host.requests();
+        let callback = requests.callback.swap(false, Ordering::Relaxed);
+        let restart = requests.restart.swap(false, Ordering::Relaxed);
+        let params_flush = requests.params_flush.swap(false, Ordering::Relaxed);
+
+        if callback {
+            unsafe {
+                if let Some(on_main_thread) = (*self.plugin).on_main_thread {
+                    on_main_thread(self.plugin);
+                }
+            }
+        }
+        if restart {
+            self.deactivate();
+        }
+        // While processing, the pending parameters are sent by the next process call
+        if params_flush && !self.processing {
+            self.flush_params(base);
+        }
+    }
+
+    fn flush_params(&mut self, base: &TalkerBase) {
+        self.prepare_input_events(base);
+
+        let (in_events, out_events) = self.events_lists();
+
+        unsafe {
+            if let Some(clap_plugin_params { flush: Some(flush), .. }) = Clap::extension::<clap_plugin_params>(self.plugin, CLAP_EXT_PARAMS) {
+                flush(self.plugin, &in_events, &out_events);
+            }
+        }
+        self.output_events.midis.clear();
+    }
+
+    fn events_lists(&mut self) -> (clap_input_events, clap_output_events) {
+        (
+            clap_input_events {
+                ctx: &mut self.input_events as *mut InputEvents as *mut c_void,
+                size: Some(input_events_size),
+                get: Some(input_events_get),
+            },
+            clap_output_events {
+                ctx: &mut self.output_events as *mut OutputEvents as *mut c_void,
+                try_push: Some(output_events_try_push),
+            },
+        )
+    }
+
+    fn update_transport(&mut self, tick: i64) {
+        let tempo = Transport::tempo();
+        let seconds = tick as f64 / AudioFormat::sample_rate() as f64;
+        let beats = seconds * tempo as f64 / 60.;
+        let (bar, _) = Transport::bar_beat(tick, tempo);
+        let t = &mut self.transport;
+
+        t.flags = CLAP_TRANSPORT_HAS_TEMPO
+            | CLAP_TRANSPORT_HAS_BEATS_TIMELINE
+            | CLAP_TRANSPORT_HAS_SECONDS_TIMELINE
+            | CLAP_TRANSPORT_HAS_TIME_SIGNATURE;
+
+        if Transport::speed() > 0. {
+            t.flags |= CLAP_TRANSPORT_IS_PLAYING;
+        }
+        t.song_pos_beats = (beats * CLAP_BEATTIME_FACTOR as f64).round() as clap_beattime;
+        t.song_pos_seconds = (seconds * CLAP_SECTIME_FACTOR as f64).round() as clap_sectime;
+        t.tempo = tempo as f64;
+        t.tempo_inc = 0.;
+        t.bar_start = (bar as f64 * transport::BEATS_PER_BAR as f64 * CLAP_BEATTIME_FACTOR as f64) as clap_beattime;
+        t.bar_number = bar as i32;
+        t.tsig_num = transport::BEATS_PER_BAR as u16;
+        t.tsig_denom = transport::BEAT_UNIT as u16;
+    }
+
+    fn prepare_input_events(&mut self, base: &TalkerBase) {
+        let events = &mut self.input_events;
+        events.params.clear();
+        events.midis.clear();
+        events.notes.clear();
+        events.headers.clear();
+
+        for param in &mut self.params {
+            let value = base.ear(param.tkr_port).get_control_value() as f64;
+
+            if value != param.value {
+                param.value = value;
+                events.params.push(clap_event_param_value {
+                    header: clap_event_header {
+                        size: mem::size_of::<clap_event_param_value>() as u32,
+                        time: 0,
+                        space_id: CLAP_CORE_EVENT_SPACE_ID,
+                        type_: CLAP_EVENT_PARAM_VALUE,
+                        flags: 0,
+                    },
+                    param_id: param.id,
+                    cookie: ptr::null_mut(),
+                    note_id: -1,
+                    port_index: -1,
+                    channel: -1,
+                    key: -1,
+                    value,
+                });
+            }
+        }
+
+        for (port_index, note_port) in self.note_inputs.iter().enumerate() {
+            for ev in base.ear(note_port.tkr_port).get_atom_buffer().iter() {
+                if ev.event.body.mytype != self.midi_urid || ev.data.len() < midi::NOTE_DATA_SIZE {
+                    continue;
+                }
+                let time = ev.event.time_in_frames.max(0) as u32;
+                let data = [ev.data[0], ev.data[1], ev.data[2]];
+                let status = data[0] & 0xF0;
+
+                if note_port.midi_dialect {
+                    events.midis.push(clap_event_midi {
+                        header: clap_event_header {
+                            size: mem::size_of::<clap_event_midi>() as u32,
+                            time,
+                            space_id: CLAP_CORE_EVENT_SPACE_ID,
+                            type_: CLAP_EVENT_MIDI,
+                            flags: 0,
+                        },
+                        port_index: port_index as u16,
+                        data,
+                    });
+                } else if status == midi::NOTE_ON || status == midi::NOTE_OFF {
+                    // A note on with a null velocity is a note off
+                    let note_on = status == midi::NOTE_ON && data[2] > 0;
+
+                    events.notes.push(clap_event_note {
+                        header: clap_event_header {
+                            size: mem::size_of::<clap_event_note>() as u32,
+                            time,
+                            space_id: CLAP_CORE_EVENT_SPACE_ID,
+                            type_: if note_on { CLAP_EVENT_NOTE_ON } else { CLAP_EVENT_NOTE_OFF },
+                            flags: 0,
+                        },
+                        note_id: -1,
+                        port_index: port_index as i16,
+                        channel: (data[0] & 0x0F) as i16,
+                        key: data[1] as i16,
+                        velocity: data[2] as f64 / 127.,
+                    });
+                }
+            }
+        }
+
+        // The headers are set once the events vectors no longer move
+        events.headers.extend(events.params.iter().map(|e| &e.header as *const clap_event_header));
+        events.headers.extend(events.midis.iter().map(|e| &e.header as *const clap_event_header));
+        events.headers.extend(events.notes.iter().map(|e| &e.header as *const clap_event_header));
+        events.headers.sort_by_key(|h| unsafe { (**h).time });
+    }
+
+    fn write_output_events(&mut self, base: &TalkerBase) {
+        for note_port in &self.note_outputs {
+            base.voice(note_port.tkr_port).atom_buffer().clear();
+        }
+
+        for (port_index, time, data) in self.output_events.midis.drain(..) {
+            if let Some(note_port) = self.note_outputs.get(port_index) {
+                let voice_buf = base.voice(note_port.tkr_port).atom_buffer();
+
+                voice_buf.push_midi_event::<{ midi::NOTE_DATA_SIZE }>(time as i64, self.midi_urid, &data)
+                    .unwrap_or_else(|e| eprintln!("{}", e));
+            }
+        }
+    }
+}
+
+impl Talker for Clap {
+    fn activate(&mut self) {
+        self.start();
+    }
+
+    fn deactivate(&mut self) {
+        unsafe {
+            if self.processing {
+                if let Some(stop_processing) = (*self.plugin).stop_processing {
+                    stop_processing(self.plugin);
+                }
+                self.processing = false;
+            }
+            if self.active {
+                if let Some(deactivate) = (*self.plugin).deactivate {
+                    deactivate(self.plugin);
+                }
+                self.active = false;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        if self.active {
+            unsafe {
+                if let Some(reset) = (*self.plugin).reset {
+                    reset(self.plugin);
+                }
+            }
+        }
+    }
+
+    fn latency(&self, _base: &TalkerBase) -> usize {
+        unsafe {
+            match Clap::extension::<clap_plugin_latency>(self.plugin, CLAP_EXT_LATENCY) {
+                Some(clap_plugin_latency { get: Some(get) }) if self.active => get(self.plugin) as usize,
+                _ => 0,
+            }
+        }
+    }
+
+    fn talk(&mut self, base: &TalkerBase, _port: usize, tick: i64, len: usize) -> usize {
+        let ln = base.listen(tick, len);
+
+        self.serve_host_requests(base);
+
+        if !self.start() {
+            for voice in base.voices() {
+                voice.set_tick_len(tick, ln);
+            }
+            return ln;
+        }
+        self.prepare_input_events(base);
+        self.update_transport(tick);
+
+        for (port, channels) in self.audio_inputs.iter().zip(self.inputs_channels.iter_mut()) {
+            for (tkr_port, channel) in port.tkr_ports.iter().zip(channels.iter_mut()) {
+                *channel = base.ear(*tkr_port).get_audio_buffer().as_ptr() as *mut f32;
+            }
+        }
+        for (port, channels) in self.audio_outputs.iter().zip(self.outputs_channels.iter_mut()) {
+            for (tkr_port, channel) in port.tkr_ports.iter().zip(channels.iter_mut()) {
+                *channel = base.voice(*tkr_port).audio_buffer().as_mut_ptr();
+            }
+        }
+
+        let (in_events, out_events) = self.events_lists();
+
+        let process = clap_process {
+            steady_time: tick,
+            frames_count: ln as u32,
+            transport: &self.transport,
+            audio_inputs: self.inputs_buffers.as_ptr(),
+            audio_outputs: self.outputs_buffers.as_mut_ptr(),
+            audio_inputs_count: self.inputs_buffers.len() as u32,
+            audio_outputs_count: self.outputs_buffers.len() as u32,
+            in_events: &in_events,
+            out_events: &out_events,
+        };
+
+        unsafe {
+            if let Some(process_fn) = (*self.plugin).process {
+                process_fn(self.plugin, &process);
+            }
+        }
+
+        self.write_output_events(base);
+
+        for voice in base.voices() {
+            voice.set_tick_len(tick, ln);
+        }
+        ln
+    }
+
+    // The plugin state is saved as hexadecimal
+    fn state(&mut self) -> Result<Option<String>, failure::Error> {
+        unsafe {
+            match Clap::extension::<clap_plugin_state>(self.plugin, CLAP_EXT_STATE) {
+                Some(clap_plugin_state { save: Some(save), .. }) => {
+                    let mut data: Vec<u8> = Vec::new();
+                    let stream = clap_ostream {
+                        ctx: &mut data as *mut Vec<u8> as *mut c_void,
+                        write: Some(ostream_write),
+                    };
+
+                    if save(self.plugin, &stream) {
+                        Ok(Some(to_hex(&data)))
+                    } else {
+                        Err(failure::err_msg(format!("CLAP plugin {} state saving failed.", self.id)))
+                    }
+                }
+                _ => Ok(None),
+            }
+        }
+    }
+
+    fn set_state(&self, state: &str) -> Result<(), failure::Error> {
+        let data = from_hex(state)?;
+
+        unsafe {
+            match Clap::extension::<clap_plugin_state>(self.plugin, CLAP_EXT_STATE) {
+                Some(clap_plugin_state { load: Some(load), .. }) => {
+                    let mut reader = StateReader { data: &data, position: 0 };
+                    let stream = clap_istream {
+                        ctx: &mut reader as *mut StateReader as *mut c_void,
+                        read: Some(istream_read),
+                    };
+
+                    if load(self.plugin, &stream) {
+                        Ok(())
+                    } else {
+                        Err(failure::err_msg(format!("CLAP plugin {} state restoration failed.", self.id)))
+                    }
+                }
+                _ => Err(failure::err_msg(format!("CLAP plugin {} has no state.", self.id))),
+            }
+        }
+    }
+}
+
+impl Drop for Clap {
+    fn drop(&mut self) {
+        self.deactivate();
+
+        unsafe {
+            if let Some(destroy) = (*self.plugin).destroy {
+                destroy(self.plugin);
+            }
+        }
+    }
+}
+
+// Plugin with a stereo audio input, a mono audio output, a gain and a read only param and a state
+#[cfg(test)]
+mod mock_plugin {
+    use std::ffi::{c_char, c_void, CStr};
+    use std::mem;
+    use std::ptr;
+
+    use clap_sys::ext::audio_ports::{clap_audio_port_info, clap_plugin_audio_ports, CLAP_EXT_AUDIO_PORTS};
+    use clap_sys::ext::params::{clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS, CLAP_PARAM_IS_READONLY};
+    use clap_sys::ext::state::{clap_plugin_state, CLAP_EXT_STATE};
+    use clap_sys::plugin::{clap_plugin, clap_plugin_descriptor};
+    use clap_sys::stream::{clap_istream, clap_ostream};
+
+    pub const GAIN_PARAM_ID: u32 = 7;
+
+    fn copy_name(name: &str, dst: &mut [c_char]) {
+        for (d, b) in dst.iter_mut().zip(name.bytes()) {
+            *d = b as c_char;
+        }
+    }
+
+    unsafe extern "C" fn audio_ports_count(_plugin: *const clap_plugin, _is_input: bool) -> u32 {
+        1
+    }
+
+    unsafe extern "C" fn audio_ports_get(_plugin: *const clap_plugin, _index: u32, is_input: bool, info: *mut clap_audio_port_info) -> bool {
+        let info = &mut *info;
+        copy_name(if is_input { "In" } else { "Out" }, &mut info.name);
+        info.channel_count = if is_input { 2 } else { 1 };
+        true
+    }
+
+    unsafe extern "C" fn params_count(_plugin: *const clap_plugin) -> u32 {
+        2
+    }
+
+    unsafe extern "C" fn params_get_info(_plugin: *const clap_plugin, index: u32, info: *mut clap_param_info) -> bool {
+        let info = &mut *info;
+
+        if index == 0 {
+            copy_name("Gain", &mut info.name);
+            info.id = GAIN_PARAM_ID;
+            info.max_value = 2.;
+            info.default_value = 1.;
+        } else {
+            copy_name("Meter", &mut info.name);
+            info.flags = CLAP_PARAM_IS_READONLY;
+        }
+        true
+    }
+
+    unsafe extern "C" fn state_save(plugin: *const clap_plugin, stream: *const clap_ostream) -> bool {
+        let state = &*((*plugin).plugin_data as *const Vec<u8>);
+        let write = (*stream).write.unwrap();
+        write(stream, state.as_ptr() as *const c_void, state.len() as u64) == state.len() as i64
+    }
+
+    unsafe extern "C" fn state_load(plugin: *const clap_plugin, stream: *const clap_istream) -> bool {
+        let state = &mut *((*plugin).plugin_data as *mut Vec<u8>);
+        let read = (*stream).read.unwrap();
+        let mut buffer = [0u8; 4];
+        state.clear();
+
+        loop {
+            let len = read(stream, buffer.as_mut_ptr() as *mut c_void, buffer.len() as u64);
+
+            if len <= 0 {
+                return len == 0;
+            }
+            state.extend_from_slice(&buffer[..len as usize]);
+        }
+    }
+
+    static AUDIO_PORTS: clap_plugin_audio_ports = clap_plugin_audio_ports {
+        count: Some(audio_ports_count),
+        get: Some(audio_ports_get),
+    };
+
+    static PARAMS: clap_plugin_params = clap_plugin_params {
+        count: Some(params_count),
+        get_info: Some(params_get_info),
+        get_value: None,
+        value_to_text: None,
+        text_to_value: None,
+        flush: None,
+    };
+
+    static STATE: clap_plugin_state = clap_plugin_state {
+        save: Some(state_save),
+        load: Some(state_load),
+    };
+
+    unsafe extern "C" fn get_extension(_plugin: *const clap_plugin, id: *const c_char) -> *const c_void {
+        let id = CStr::from_ptr(id);
+
+        if id == CLAP_EXT_AUDIO_PORTS {
+            &AUDIO_PORTS as *const clap_plugin_audio_ports as *const c_void
+        } else if id == CLAP_EXT_PARAMS {
+            &PARAMS as *const clap_plugin_params as *const c_void
+        } else if id == CLAP_EXT_STATE {
+            &STATE as *const clap_plugin_state as *const c_void
+        } else {
+            ptr::null()
+        }
+    }
+
+    pub fn new(state: &[u8]) -> *const clap_plugin {
+        unsafe {
+            let mut desc: clap_plugin_descriptor = mem::zeroed();
+            desc.name = b"Mock\0".as_ptr() as *const c_char;
+
+            let mut plugin: clap_plugin = mem::zeroed();
+            plugin.desc = Box::leak(Box::new(desc));
+            plugin.plugin_data = Box::leak(Box::new(state.to_vec())) as *mut Vec<u8> as *mut c_void;
+            plugin.get_extension = Some(get_extension);
+            Box::leak(Box::new(plugin))
+        }
+    }
+}
+
+#[test]
+fn test_ports_params_and_state_mapping() {
+    let base = TalkerBase::new("", "clap:mock", true);
+    let plugin = mock_plugin::new(&[1, 2, 3, 250, 251]);
+    let (base, mut core) = Clap::with_plugin(None, PluginHost::new(), "mock".to_string(), plugin, base).unwrap();
+
+    let ears: Vec<&str> = base.ears().iter().map(|e| e.tag().as_str()).collect();
+    assert_eq!(ears, vec!["In 1", "In 2", "Gain"]);
+
+    let voices: Vec<&str> = base.voices().iter().map(|v| v.tag().as_str()).collect();
+    assert_eq!(voices, vec!["Out"]);
+
+    let state = core.state().unwrap().unwrap();
+    assert_eq!(state, "010203fafb");
+
+    core.set_state("0405").unwrap();
+    assert_eq!(core.state().unwrap().unwrap(), "0405");
+    assert!(core.set_state("040").is_err());
+}
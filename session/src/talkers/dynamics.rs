@@ -0,0 +1,403 @@
+use std::f32;
+
+use talker::audio_format::AudioFormat;
+use talker::ctalker;
+use talker::ear;
+use talker::ear::Ear;
+use talker::ear::Init;
+use talker::ear::Set;
+use talker::horn::PortType;
+use talker::identifier::Index;
+use talker::talker::{CTalker, Talker, TalkerBase};
+use talker::talker_handler::TalkerHandlerBase;
+use talker::voice;
+
+const INPUTS_EAR_INDEX: Index = 0;
+const IN_HUM_INDEX: Index = 0;
+const SIDECHAIN_EAR_INDEX: Index = 1;
+
+const GAIN_REDUCTION_VOICE_PORT: usize = 0;
+
+const MIN_LEVEL_DB: f32 = -120.;
+
+fn lin_to_db(v: f32) -> f32 {
+    if v > 0. {
+        (20. * v.log10()).max(MIN_LEVEL_DB)
+    }
+    else {
+        MIN_LEVEL_DB
+    }
+}
+
+fn db_to_lin(db: f32) -> f32 {
+    10_f32.powf(db * 0.05)
+}
+
+fn time_coef(time: f32, sample_rate: f32) -> f32 {
+    if time > 0. {
+        (-1. / (time * sample_rate)).exp()
+    }
+    else {
+        0.
+    }
+}
+
+// Shared detection and gain application of the dynamics talkers.
+// All the input sets are processed with the same gain so that linked channels keep their balance.
+struct Dynamics {
+    sample_rate: f32,
+    reduction: f32,
+    levels: Vec<f32>,
+    reductions: Vec<f32>,
+    gains: Vec<f32>,
+}
+
+impl Dynamics {
+    fn new(base: &mut TalkerBase) -> Result<Dynamics, failure::Error> {
+        let stem_set = Set::from_attributs(&vec![
+            ("in", PortType::Audio, -1., 1., 0., Init::DefValue),
+        ])?;
+        base.add_ear(Ear::new(Some("inputs"), true, Some(stem_set), None));
+        base.add_ear(ear::audio(Some("sidechain"), -1., 1., 0., &Init::DefValue)?);
+
+        base.add_cv_voice(Some("gr"), 0.);
+
+        // The frames buffers are allocated once, out of the audio thread
+        let chunk_size = AudioFormat::chunk_size();
+
+        Ok(Self {
+            sample_rate: AudioFormat::sample_rate() as f32,
+            reduction: 0.,
+            levels: vec![MIN_LEVEL_DB; chunk_size],
+            reductions: vec![0.; chunk_size],
+            gains: vec![1.; chunk_size],
+        })
+    }
+
+    fn add_set_to_ear_update(
+        &mut self,
+        base: &TalkerBase,
+        ear_idx: Index,
+        hum_idx: Index,
+        entree: ear::Entree,
+    ) -> Result<Option<TalkerBase>, failure::Error> {
+        let mut new_base = base.clone();
+        new_base.ear(ear_idx).add_set(hum_idx, entree)?;
+
+        if ear_idx == INPUTS_EAR_INDEX {
+            let mut voice = voice::audio(None, 0., base.buffer_len());
+            voice.set_associated_ear_set(ear_idx, new_base.ear(ear_idx).sets_len() - 1);
+            new_base.add_voice(voice);
+        }
+        Ok(Some(new_base))
+    }
+
+    fn sup_ear_set_update(
+        &mut self,
+        base: &TalkerBase,
+        ear_idx: usize,
+        set_idx: usize,
+    ) -> Result<Option<TalkerBase>, failure::Error> {
+        let mut new_base = base.clone();
+        new_base.sup_ear_set_with_associated_voice(ear_idx, set_idx)?;
+        Ok(Some(new_base))
+    }
+
+    fn sidechain_is_connected(base: &TalkerBase) -> bool {
+        match base.ear(SIDECHAIN_EAR_INDEX).sets().first() {
+            Some(set) => set.hums()[0].value().is_none(),
+            None => false,
+        }
+    }
+
+    // Listen the ears and compute the detection level (in dB) of each frame
+    fn detect(&mut self, base: &TalkerBase, tick: i64, len: usize) -> usize {
+        let ln = base.listen(tick, len.min(self.levels.len()));
+        let levels = &mut self.levels[..ln];
+
+        if Dynamics::sidechain_is_connected(base) {
+            let sidechain_buf = base.ear_audio_buffer(SIDECHAIN_EAR_INDEX);
+
+            for (level, sample) in levels.iter_mut().zip(sidechain_buf.iter()) {
+                *level = lin_to_db(sample.abs());
+            }
+        }
+        else {
+            let inputs_ear = base.ear(INPUTS_EAR_INDEX);
+            levels.fill(0.);
+
+            for set_idx in 0..inputs_ear.sets_len() {
+                let input_buf = inputs_ear.get_set_hum_audio_buffer(set_idx, IN_HUM_INDEX);
+
+                for (level, sample) in levels.iter_mut().zip(input_buf.iter()) {
+                    *level = level.max(sample.abs());
+                }
+            }
+            for level in levels.iter_mut() {
+                *level = lin_to_db(*level);
+            }
+        }
+        ln
+    }
+
+    // Move the gain reduction toward the target with the attack time when the reduction
+    // increases and with the release time when it decreases
+    fn follow(&mut self, target: f32, attack: f32, release: f32) -> f32 {
+        let coef = if target > self.reduction {
+            time_coef(attack, self.sample_rate)
+        }
+        else {
+            time_coef(release, self.sample_rate)
+        };
+        self.reduction = target + coef * (self.reduction - target);
+        self.reduction
+    }
+
+    fn set_frame(&mut self, i: usize, reduction: f32, makeup: f32) {
+        self.reductions[i] = reduction;
+        self.gains[i] = db_to_lin(makeup - reduction);
+    }
+
+    fn apply(&self, base: &TalkerBase, tick: i64, ln: usize) -> usize {
+        let inputs_ear = base.ear(INPUTS_EAR_INDEX);
+
+        for set_idx in 0..inputs_ear.sets_len() {
+            let input_buf = inputs_ear.get_set_hum_audio_buffer(set_idx, IN_HUM_INDEX);
+            let voice_buf = base.voice(set_idx + 1).audio_buffer();
+
+            for i in 0..ln {
+                voice_buf[i] = input_buf[i] * self.gains[i];
+            }
+        }
+
+        let gr_buf = base.voice(GAIN_REDUCTION_VOICE_PORT).cv_buffer();
+
+        for i in 0..ln {
+            gr_buf[i] = self.reductions[i];
+        }
+
+        for voice in base.voices() {
+            voice.set_tick_len(tick, ln);
+        }
+        ln
+    }
+}
+
+pub const COMPRESSOR_MODEL: &str = "Compressors";
+
+const COMP_THRESHOLD_EAR_INDEX: Index = 2;
+const COMP_RATIO_EAR_INDEX: Index = 3;
+const COMP_KNEE_EAR_INDEX: Index = 4;
+const COMP_ATTACK_EAR_INDEX: Index = 5;
+const COMP_RELEASE_EAR_INDEX: Index = 6;
+const COMP_MAKEUP_EAR_INDEX: Index = 7;
+
+pub struct Compressors {
+    dynamics: Dynamics,
+}
+impl Compressors {
+    pub fn new(mut base: TalkerBase) -> Result<CTalker, failure::Error> {
+        let dynamics = Dynamics::new(&mut base)?;
+
+        base.add_ear(ear::cv(Some("threshold"), -60., 0., -20., &Init::DefValue)?);
+        base.add_ear(ear::cv(Some("ratio"), 1., 20., 4., &Init::DefValue)?);
+        base.add_ear(ear::cv(Some("knee"), 0., 24., 6., &Init::DefValue)?);
+        base.add_ear(ear::cv(Some("attack"), 0., 1., 0.01, &Init::DefValue)?);
+        base.add_ear(ear::cv(Some("release"), 0., 5., 0.1, &Init::DefValue)?);
+        base.add_ear(ear::cv(Some("makeup"), 0., 40., 0., &Init::DefValue)?);
+
+        Ok(ctalker!(base, Self { dynamics }))
+    }
+
+    pub fn descriptor() -> TalkerHandlerBase {
+        TalkerHandlerBase::builtin("Modulator", COMPRESSOR_MODEL, COMPRESSOR_MODEL)
+    }
+}
+
+// Gain reduction in dB of the static compression curve with a soft knee
+fn compression(over: f32, ratio: f32, knee: f32) -> f32 {
+    let slope = 1. - 1. / ratio.max(1.);
+
+    if 2. * over < -knee {
+        0.
+    }
+    else if 2. * over.abs() <= knee && knee > 0. {
+        let x = over + knee * 0.5;
+        slope * x * x / (2. * knee)
+    }
+    else {
+        slope * over
+    }
+}
+
+#[test]
+fn test_compression() {
+    assert!(compression(-10., 4., 0.) == 0.);
+    assert!(compression(8., 4., 0.) == 6.);
+    assert!(compression(8., 1., 6.) == 0.);
+    assert!(compression(0., 4., 6.) > 0.);
+    assert!(compression(0., 4., 6.) < compression(3., 4., 6.));
+}
+
+impl Talker for Compressors {
+    fn add_set_to_ear_update(
+        &mut self,
+        base: &TalkerBase,
+        ear_idx: Index,
+        hum_idx: Index,
+        entree: ear::Entree,
+    ) -> Result<Option<TalkerBase>, failure::Error> {
+        self.dynamics.add_set_to_ear_update(base, ear_idx, hum_idx, entree)
+    }
+    fn sup_ear_set_update(
+        &mut self,
+        base: &TalkerBase,
+        ear_idx: usize,
+        set_idx: usize,
+    ) -> Result<Option<TalkerBase>, failure::Error> {
+        self.dynamics.sup_ear_set_update(base, ear_idx, set_idx)
+    }
+
+    fn talk(&mut self, base: &TalkerBase, _port: usize, tick: i64, len: usize) -> usize {
+        let ln = self.dynamics.detect(base, tick, len);
+        let threshold_buf = base.ear_cv_buffer(COMP_THRESHOLD_EAR_INDEX);
+        let ratio_buf = base.ear_cv_buffer(COMP_RATIO_EAR_INDEX);
+        let knee_buf = base.ear_cv_buffer(COMP_KNEE_EAR_INDEX);
+        let attack_buf = base.ear_cv_buffer(COMP_ATTACK_EAR_INDEX);
+        let release_buf = base.ear_cv_buffer(COMP_RELEASE_EAR_INDEX);
+        let makeup_buf = base.ear_cv_buffer(COMP_MAKEUP_EAR_INDEX);
+
+        for i in 0..ln {
+            let over = self.dynamics.levels[i] - threshold_buf[i];
+            let target = compression(over, ratio_buf[i], knee_buf[i]);
+            let reduction = self.dynamics.follow(target, attack_buf[i], release_buf[i]);
+            self.dynamics.set_frame(i, reduction, makeup_buf[i]);
+        }
+        self.dynamics.apply(base, tick, ln)
+    }
+}
+
+pub const LIMITER_MODEL: &str = "Limiters";
+
+const LIM_CEILING_EAR_INDEX: Index = 2;
+const LIM_RELEASE_EAR_INDEX: Index = 3;
+
+pub struct Limiters {
+    dynamics: Dynamics,
+}
+impl Limiters {
+    pub fn new(mut base: TalkerBase) -> Result<CTalker, failure::Error> {
+        let dynamics = Dynamics::new(&mut base)?;
+
+        base.add_ear(ear::cv(Some("ceiling"), -30., 0., -0.3, &Init::DefValue)?);
+        base.add_ear(ear::cv(Some("release"), 0., 5., 0.05, &Init::DefValue)?);
+
+        Ok(ctalker!(base, Self { dynamics }))
+    }
+
+    pub fn descriptor() -> TalkerHandlerBase {
+        TalkerHandlerBase::builtin("Modulator", LIMITER_MODEL, LIMITER_MODEL)
+    }
+}
+
+impl Talker for Limiters {
+    fn add_set_to_ear_update(
+        &mut self,
+        base: &TalkerBase,
+        ear_idx: Index,
+        hum_idx: Index,
+        entree: ear::Entree,
+    ) -> Result<Option<TalkerBase>, failure::Error> {
+        self.dynamics.add_set_to_ear_update(base, ear_idx, hum_idx, entree)
+    }
+    fn sup_ear_set_update(
+        &mut self,
+        base: &TalkerBase,
+        ear_idx: usize,
+        set_idx: usize,
+    ) -> Result<Option<TalkerBase>, failure::Error> {
+        self.dynamics.sup_ear_set_update(base, ear_idx, set_idx)
+    }
+
+    fn talk(&mut self, base: &TalkerBase, _port: usize, tick: i64, len: usize) -> usize {
+        let ln = self.dynamics.detect(base, tick, len);
+        let ceiling_buf = base.ear_cv_buffer(LIM_CEILING_EAR_INDEX);
+        let release_buf = base.ear_cv_buffer(LIM_RELEASE_EAR_INDEX);
+
+        for i in 0..ln {
+            let target = (self.dynamics.levels[i] - ceiling_buf[i]).max(0.);
+            // instantaneous attack so that no peak goes over the ceiling
+            let reduction = self.dynamics.follow(target, 0., release_buf[i]);
+            self.dynamics.set_frame(i, reduction, 0.);
+        }
+        self.dynamics.apply(base, tick, ln)
+    }
+}
+
+pub const GATE_MODEL: &str = "Gates";
+
+const GATE_THRESHOLD_EAR_INDEX: Index = 2;
+const GATE_RANGE_EAR_INDEX: Index = 3;
+const GATE_ATTACK_EAR_INDEX: Index = 4;
+const GATE_RELEASE_EAR_INDEX: Index = 5;
+
+pub struct Gates {
+    dynamics: Dynamics,
+}
+impl Gates {
+    pub fn new(mut base: TalkerBase) -> Result<CTalker, failure::Error> {
+        let dynamics = Dynamics::new(&mut base)?;
+
+        base.add_ear(ear::cv(Some("threshold"), -90., 0., -50., &Init::DefValue)?);
+        base.add_ear(ear::cv(Some("range"), 0., 120., 80., &Init::DefValue)?);
+        base.add_ear(ear::cv(Some("attack"), 0., 1., 0.001, &Init::DefValue)?);
+        base.add_ear(ear::cv(Some("release"), 0., 5., 0.1, &Init::DefValue)?);
+
+        Ok(ctalker!(base, Self { dynamics }))
+    }
+
+    pub fn descriptor() -> TalkerHandlerBase {
+        TalkerHandlerBase::builtin("Modulator", GATE_MODEL, GATE_MODEL)
+    }
+}
+
+impl Talker for Gates {
+    fn add_set_to_ear_update(
+        &mut self,
+        base: &TalkerBase,
+        ear_idx: Index,
+        hum_idx: Index,
+        entree: ear::Entree,
+    ) -> Result<Option<TalkerBase>, failure::Error> {
+        self.dynamics.add_set_to_ear_update(base, ear_idx, hum_idx, entree)
+    }
+    fn sup_ear_set_update(
+        &mut self,
+        base: &TalkerBase,
+        ear_idx: usize,
+        set_idx: usize,
+    ) -> Result<Option<TalkerBase>, failure::Error> {
+        self.dynamics.sup_ear_set_update(base, ear_idx, set_idx)
+    }
+
+    fn talk(&mut self, base: &TalkerBase, _port: usize, tick: i64, len: usize) -> usize {
+        let ln = self.dynamics.detect(base, tick, len);
+        let threshold_buf = base.ear_cv_buffer(GATE_THRESHOLD_EAR_INDEX);
+        let range_buf = base.ear_cv_buffer(GATE_RANGE_EAR_INDEX);
+        let attack_buf = base.ear_cv_buffer(GATE_ATTACK_EAR_INDEX);
+        let release_buf = base.ear_cv_buffer(GATE_RELEASE_EAR_INDEX);
+
+        for i in 0..ln {
+            let target = if self.dynamics.levels[i] < threshold_buf[i] {
+                range_buf[i]
+            }
+            else {
+                0.
+            };
+            // the gate opens (reduction decreases) with the attack time and closes with the release time
+            let reduction = self.dynamics.follow(target, release_buf[i], attack_buf[i]);
+            self.dynamics.set_frame(i, reduction, 0.);
+        }
+        self.dynamics.apply(base, tick, ln)
+    }
+}
@@ -0,0 +1,232 @@
+extern crate audiofile;
+
+use std::f32;
+
+use talker::ctalker;
+use talker::audio_format::AudioFormat;
+use talker::data::Data;
+use talker::ear;
+use talker::ear::Init;
+use talker::identifier::Index;
+use talker::talker::{CTalker, Talker, TalkerBase};
+use talker::talker_handler::TalkerHandlerBase;
+use audiofile::reader::Reader;
+
+use tables::{round, sinramp};
+
+pub const MODEL: &str = "Granular";
+
+const POSITION_EAR_INDEX: Index = 0;
+const SIZE_EAR_INDEX: Index = 1;
+const DENSITY_EAR_INDEX: Index = 2;
+const PITCH_EAR_INDEX: Index = 3;
+const SPREAD_EAR_INDEX: Index = 4;
+const GAIN_EAR_INDEX: Index = 5;
+const WINDOW_EAR_INDEX: Index = 6;
+
+const LEFT_VOICE_INDEX: Index = 0;
+const RIGHT_VOICE_INDEX: Index = 1;
+
+const MAX_GRAINS: usize = 128;
+const SINRAMP_WINDOW: f32 = 0.;
+
+struct Grain {
+    src_pos: f64,
+    step: f64,
+    win_pos: f32,
+    win_step: f32,
+    left_gain: f32,
+    right_gain: f32,
+}
+
+pub struct Granular {
+    channels: Vec<Vec<f32>>,
+    grains: Vec<Grain>,
+    sample_rate: f32,
+    next_grain_countdown: f32,
+    random_seed: u32,
+    last_tick: i64,
+}
+
+impl Granular {
+    pub fn new(mut base: TalkerBase) -> Result<CTalker, failure::Error> {
+        base.add_ear(ear::cv(Some("position"), 0., 1., 0., &Init::DefValue)?);
+        base.add_ear(ear::cv(Some("size"), 0.001, 2., 0.1, &Init::DefValue)?);
+        base.add_ear(ear::cv(Some("density"), 0., 1000., 20., &Init::DefValue)?);
+        base.add_ear(ear::cv(Some("pitch"), 0., 8., 1., &Init::DefValue)?);
+        base.add_ear(ear::cv(Some("spread"), 0., 1., 0.5, &Init::DefValue)?);
+        base.add_ear(ear::audio(Some("gain"), -1., 1., 1., &Init::DefValue)?);
+        base.add_ear(ear::control(Some("window"), 0., 1., SINRAMP_WINDOW)?);
+
+        base.add_audio_voice(Some("left"), 0.);
+        base.add_audio_voice(Some("right"), 0.);
+
+        base.set_data(Data::File("Click here to select a file".to_string()));
+
+        Ok(ctalker!(
+            base,
+            Self {
+                channels: Vec::new(),
+                grains: Vec::with_capacity(MAX_GRAINS),
+                sample_rate: AudioFormat::sample_rate() as f32,
+                next_grain_countdown: 0.,
+                random_seed: 0x9E3779B9,
+                last_tick: 0,
+            }
+        ))
+    }
+
+    pub fn descriptor() -> TalkerHandlerBase {
+        TalkerHandlerBase::builtin("Generator", MODEL, MODEL)
+    }
+
+    // xorshift32 giving a value in [0, 1[
+    fn random(&mut self) -> f32 {
+        let mut x = self.random_seed;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.random_seed = x;
+        (x >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn start_grain(&mut self, position: f32, size: f32, pitch: f32, spread: f32) {
+        if self.grains.len() == MAX_GRAINS || self.channels.is_empty() {
+            return;
+        }
+        let src_len = self.channels[0].len();
+
+        if src_len == 0 {
+            return;
+        }
+        let grain_len = (size * self.sample_rate).max(2.);
+        let jitter = (self.random() - 0.5) * spread * size * self.sample_rate;
+        let src_pos = (position.max(0.).min(1.) * src_len as f32 + jitter).max(0.) as f64;
+
+        // constant power panning around the center, spread to the sides
+        let pan = 0.5 + (self.random() - 0.5) * spread;
+        let angle = pan * f32::consts::FRAC_PI_2;
+
+        self.grains.push(Grain {
+            src_pos: src_pos % src_len as f64,
+            step: pitch.max(0.) as f64,
+            win_pos: 0.,
+            win_step: 1. / grain_len,
+            left_gain: angle.cos(),
+            right_gain: angle.sin(),
+        });
+    }
+}
+
+fn sinramp_window(pos: f32) -> f32 {
+    let p = if pos < 0.5 { pos * 2. } else { (1. - pos) * 2. };
+    sinramp::TAB[((p * (sinramp::LEN - 1) as f32) as usize).min(sinramp::LEN - 1)]
+}
+
+fn round_window(pos: f32) -> f32 {
+    let half_len = round::LEN / 2;
+    round::TAB[((pos * half_len as f32) as usize).min(half_len)]
+}
+
+fn read_sample(channel: &Vec<f32>, pos: f64) -> f32 {
+    let len = channel.len();
+    let idx = pos as usize % len;
+    let next_idx = (idx + 1) % len;
+    let frac = pos.fract() as f32;
+    channel[idx] + (channel[next_idx] - channel[idx]) * frac
+}
+
+impl Talker for Granular {
+    fn set_data_update(
+        &mut self,
+        base: &TalkerBase,
+        data: Data,
+    ) -> Result<Option<TalkerBase>, failure::Error> {
+        match data {
+            Data::File(ref filename) => {
+                if base.is_effective() {
+                    let mut file_reader = Reader::new(filename, AudioFormat::sample_rate())?;
+                    self.channels = file_reader.read_all_samples()?;
+                    self.grains.clear();
+                }
+                base.set_data(data);
+                Ok(None)
+            }
+            _ => Err(failure::err_msg(format!("{} data type {} is not File", MODEL, data.type_str()))),
+        }
+    }
+
+    fn activate(&mut self) {
+        self.grains.clear();
+        self.next_grain_countdown = 0.;
+    }
+
+    fn talk(&mut self, base: &TalkerBase, _port: usize, tick: i64, len: usize) -> usize {
+        let ln = base.listen(tick, len);
+        let position_buf = base.ear_cv_buffer(POSITION_EAR_INDEX);
+        let size_buf = base.ear_cv_buffer(SIZE_EAR_INDEX);
+        let density_buf = base.ear_cv_buffer(DENSITY_EAR_INDEX);
+        let pitch_buf = base.ear_cv_buffer(PITCH_EAR_INDEX);
+        let spread_buf = base.ear_cv_buffer(SPREAD_EAR_INDEX);
+        let gain_buf = base.ear_audio_buffer(GAIN_EAR_INDEX);
+        let window = if base.ear(WINDOW_EAR_INDEX).get_control_value() > 0.5 {
+            round_window
+        } else {
+            sinramp_window
+        };
+        let left_buf = base.voice(LEFT_VOICE_INDEX).audio_buffer();
+        let right_buf = base.voice(RIGHT_VOICE_INDEX).audio_buffer();
+
+        if self.last_tick != tick {
+            self.grains.clear();
+            self.next_grain_countdown = 0.;
+        }
+
+        for i in 0..ln {
+            self.next_grain_countdown -= 1.;
+
+            if self.next_grain_countdown <= 0. {
+                let density = density_buf[i];
+
+                if density > 0. {
+                    self.start_grain(position_buf[i], size_buf[i], pitch_buf[i], spread_buf[i]);
+                    // slight jitter of the onset to avoid a buzzing periodicity
+                    let period = self.sample_rate / density;
+                    self.next_grain_countdown += period * (0.75 + self.random() * 0.5);
+                }
+                else {
+                    self.next_grain_countdown = 0.;
+                }
+            }
+
+            let mut left = 0.;
+            let mut right = 0.;
+
+            if !self.channels.is_empty() {
+                let left_channel = &self.channels[0];
+                let right_channel = &self.channels[1.min(self.channels.len() - 1)];
+
+                for grain in self.grains.iter_mut() {
+                    let w = window(grain.win_pos);
+                    left += read_sample(left_channel, grain.src_pos) * w * grain.left_gain;
+                    right += read_sample(right_channel, grain.src_pos) * w * grain.right_gain;
+
+                    grain.src_pos += grain.step;
+                    grain.win_pos += grain.win_step;
+                }
+                self.grains.retain(|grain| grain.win_pos < 1.);
+            }
+
+            let gain = gain_buf[i];
+            left_buf[i] = left * gain;
+            right_buf[i] = right * gain;
+        }
+
+        for voice in base.voices() {
+            voice.set_tick_len(tick, ln);
+        }
+
+        self.last_tick = tick + ln as i64;
+        ln
+    }
+}
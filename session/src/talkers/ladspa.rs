@@ -0,0 +1,214 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use talker::audio_format::{self, AudioFormat};
+use talker::ctalker;
+use talker::ear;
+use talker::ear::Init;
+use talker::talker::{CTalker, Talker, TalkerBase};
+
+use ladspa_handler::{self, Descriptor, Handle, Library};
+use util;
+
+struct Idxs {
+    tkr_port: usize,
+    plugin_port: u64,
+}
+
+pub struct Ladspa {
+    // Kept for the descriptor to stay valid
+    _library: Arc<Library>,
+    descriptor: *const Descriptor,
+    handle: Handle,
+    control_inputs_indexes: Vec<Idxs>,
+    control_outputs_indexes: Vec<Idxs>,
+    audio_inputs_indexes: Vec<Idxs>,
+    audio_outputs_indexes: Vec<Idxs>,
+    // The control outputs are written by the plugin in these values
+    control_outputs: Vec<f32>,
+    active: bool,
+}
+
+impl Ladspa {
+    pub fn new(path: &Path, model: &str, mut base: TalkerBase) -> Result<CTalker, failure::Error> {
+        let unique_id = ladspa_handler::unique_id_of(model)?;
+        let library = ladspa_handler::library(path)?;
+
+        let descriptor = library.descriptor(unique_id)
+            .ok_or(failure::err_msg(format!("LADSPA plugin {} not found.", model)))?;
+
+        let handle = match descriptor.instantiate {
+            Some(instantiate) => unsafe { instantiate(descriptor, AudioFormat::sample_rate() as _) },
+            None => std::ptr::null_mut(),
+        };
+        if handle.is_null() {
+            return Err(failure::err_msg(format!("LADSPA plugin {} instantiation failed.", model)));
+        }
+
+        let sample_rate = AudioFormat::sample_rate() as f32;
+        let mut inputs_count = 0;
+        let mut outputs_count = 0;
+        let mut control_inputs_indexes = Vec::new();
+        let mut control_outputs_indexes = Vec::new();
+        let mut audio_inputs_indexes = Vec::new();
+        let mut audio_outputs_indexes = Vec::new();
+
+        unsafe {
+            base.set_name(&util::to_string(descriptor.name));
+
+            for index in 0..descriptor.port_count as usize {
+                let port_descriptor = *descriptor.port_descriptors.add(index);
+                let name = util::to_string(*descriptor.port_names.add(index));
+                let plugin_port = index as u64;
+                let is_input = port_descriptor & ladspa_handler::PORT_INPUT != 0;
+
+                if port_descriptor & ladspa_handler::PORT_CONTROL != 0 {
+                    if is_input {
+                        let hint = &*descriptor.port_range_hints.add(index);
+                        let (min, max, default) = ladspa_handler::control_range(hint, sample_rate);
+                        let min = min.unwrap_or(audio_format::MIN_CONTROL);
+                        let max = max.unwrap_or(audio_format::MAX_CONTROL);
+
+                        let ear = ear::control(
+                            Some(&name),
+                            min,
+                            max,
+                            default.unwrap_or(min.max(audio_format::DEF_CONTROL.min(max))),
+                        )?;
+                        base.add_ear(ear);
+                        control_inputs_indexes.push(Idxs{tkr_port: inputs_count, plugin_port});
+                        inputs_count = inputs_count + 1;
+                    } else {
+                        base.add_control_voice(Some(&name), audio_format::DEF_CONTROL);
+                        control_outputs_indexes.push(Idxs{tkr_port: outputs_count, plugin_port});
+                        outputs_count = outputs_count + 1;
+                    }
+                } else if port_descriptor & ladspa_handler::PORT_AUDIO != 0 {
+                    if is_input {
+                        let ear = ear::audio(
+                            Some(&name),
+                            audio_format::MIN_AUDIO,
+                            audio_format::MAX_AUDIO,
+                            audio_format::DEF_AUDIO,
+                            &Init::DefValue,
+                        )?;
+                        base.add_ear(ear);
+                        audio_inputs_indexes.push(Idxs{tkr_port: inputs_count, plugin_port});
+                        inputs_count = inputs_count + 1;
+                    } else {
+                        base.add_audio_voice(Some(&name), 0.);
+                        audio_outputs_indexes.push(Idxs{tkr_port: outputs_count, plugin_port});
+                        outputs_count = outputs_count + 1;
+                    }
+                }
+            }
+        }
+
+        let control_outputs = vec![0.; control_outputs_indexes.len()];
+        let descriptor = descriptor as *const Descriptor;
+
+        Ok(ctalker!(
+            base,
+            Self {
+                _library: library,
+                descriptor,
+                handle,
+                control_inputs_indexes,
+                control_outputs_indexes,
+                audio_inputs_indexes,
+                audio_outputs_indexes,
+                control_outputs,
+                active: false,
+            }
+        ))
+    }
+
+    fn connect_ports(&mut self, base: &TalkerBase) {
+        unsafe {
+            let connect_port = match (*self.descriptor).connect_port {
+                Some(connect_port) => connect_port,
+                None => return,
+            };
+
+            for idx in &self.audio_inputs_indexes {
+                connect_port(self.handle, idx.plugin_port as _, base.ear(idx.tkr_port).get_audio_buffer().as_ptr() as *mut f32);
+            }
+            for idx in &self.audio_outputs_indexes {
+                connect_port(self.handle, idx.plugin_port as _, base.voice(idx.tkr_port).audio_buffer().as_mut_ptr());
+            }
+
+            for idx in &self.control_inputs_indexes {
+                connect_port(self.handle, idx.plugin_port as _, base.ear(idx.tkr_port).get_control_buffer().as_ptr() as *mut f32);
+            }
+            for (idx, value) in self.control_outputs_indexes.iter().zip(self.control_outputs.iter_mut()) {
+                connect_port(self.handle, idx.plugin_port as _, value);
+            }
+        }
+    }
+}
+
+impl Talker for Ladspa {
+    fn activate(&mut self) {
+        if !self.active {
+            unsafe {
+                if let Some(activate) = (*self.descriptor).activate {
+                    activate(self.handle);
+                }
+            }
+            self.active = true;
+        }
+    }
+
+    fn deactivate(&mut self) {
+        if self.active {
+            unsafe {
+                if let Some(deactivate) = (*self.descriptor).deactivate {
+                    deactivate(self.handle);
+                }
+            }
+            self.active = false;
+        }
+    }
+
+    // LADSPA has no reset, the plugin is reactivated instead
+    fn reset(&mut self) {
+        if self.active {
+            self.deactivate();
+            self.activate();
+        }
+    }
+
+    fn talk(&mut self, base: &TalkerBase, _port: usize, tick: i64, len: usize) -> usize {
+        let ln = base.listen(tick, len);
+
+        self.activate();
+        self.connect_ports(base);
+
+        unsafe {
+            if let Some(run) = (*self.descriptor).run {
+                run(self.handle, ln as _);
+            }
+        }
+
+        for (idx, value) in self.control_outputs_indexes.iter().zip(self.control_outputs.iter()) {
+            base.voice(idx.tkr_port).set_control_value(*value);
+        }
+
+        for voice in base.voices() {
+            voice.set_tick_len(tick, ln);
+        }
+        ln
+    }
+}
+
+impl Drop for Ladspa {
+    fn drop(&mut self) {
+        self.deactivate();
+
+        unsafe {
+            if let Some(cleanup) = (*self.descriptor).cleanup {
+                cleanup(self.handle);
+            }
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use std::f32;
+use std::f64::consts::PI;
+
+use talker::audio_format::{self, AudioFormat};
+use talker::ctalker;
+use talker::ear;
+use talker::ear::Init;
+use talker::identifier::Index;
+use talker::talker::{CTalker, Talker, TalkerBase};
+use talker::talker_handler::TalkerHandlerBase;
+
+use midi;
+
+pub const MODEL: &str = "LFO";
+
+const RATE_EAR_INDEX: Index = 0;
+const BEATS_EAR_INDEX: Index = 1;
+const BPM_EAR_INDEX: Index = 2;
+const SYNC_EAR_INDEX: Index = 3;
+const SHAPE_EAR_INDEX: Index = 4;
+const MIN_EAR_INDEX: Index = 5;
+const MAX_EAR_INDEX: Index = 6;
+const EVENT_EAR_INDEX: Index = 7;
+
+const UNIPOLAR_VOICE_PORT: usize = 0;
+const BIPOLAR_VOICE_PORT: usize = 1;
+
+const SINE_SHAPE: usize = 0;
+const TRIANGLE_SHAPE: usize = 1;
+const SAW_SHAPE: usize = 2;
+const SQUARE_SHAPE: usize = 3;
+const SAMPLE_AND_HOLD_SHAPE: usize = 4;
+
+pub struct Lfo {
+    sample_rate: f64,
+    phase: f64,
+    held_value: f32,
+    random_seed: u32,
+}
+
+impl Lfo {
+    pub fn new(mut base: TalkerBase) -> Result<CTalker, failure::Error> {
+        base.add_ear(ear::cv(Some("rate"), 0., 100., 1., &Init::DefValue)?);
+        base.add_ear(ear::cv(Some("beats"), 0.0625, 64., 1., &Init::DefValue)?);
+        base.add_ear(ear::cv(Some("bpm"), 1., 999., 120., &Init::DefValue)?);
+        base.add_ear(ear::control(Some("sync"), 0., 1., 0.)?);
+        base.add_ear(ear::control(Some("shape"), SINE_SHAPE as f32, SAMPLE_AND_HOLD_SHAPE as f32, SINE_SHAPE as f32)?);
+        base.add_ear(ear::cv(Some("min"), audio_format::MIN_CV, audio_format::MAX_CV, 0., &Init::DefValue)?);
+        base.add_ear(ear::cv(Some("max"), audio_format::MIN_CV, audio_format::MAX_CV, 1., &Init::DefValue)?);
+        base.add_ear(ear::atom(Some("ev"), None)?);
+
+        base.add_cv_voice(Some("uni"), 0.);
+        base.add_cv_voice(Some("bi"), 0.);
+
+        Ok(ctalker!(
+            base,
+            Self {
+                sample_rate: AudioFormat::sample_rate() as f64,
+                phase: 0.,
+                held_value: 0.,
+                random_seed: 0x2545F491,
+            }
+        ))
+    }
+
+    pub fn descriptor() -> TalkerHandlerBase {
+        TalkerHandlerBase::builtin("Oscillator", MODEL, MODEL)
+    }
+
+    // xorshift32 giving a value in [-1, 1[
+    fn random(&mut self) -> f32 {
+        let mut x = self.random_seed;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.random_seed = x;
+        ((x >> 8) as f32 / (1 << 23) as f32) - 1.
+    }
+
+    fn reset_phase(&mut self) {
+        self.phase = 0.;
+        self.held_value = self.random();
+    }
+
+    fn run(&mut self, base: &TalkerBase, start: usize, end: usize) {
+        let rate_buf = base.ear_cv_buffer(RATE_EAR_INDEX);
+        let beats_buf = base.ear_cv_buffer(BEATS_EAR_INDEX);
+        let bpm_buf = base.ear_cv_buffer(BPM_EAR_INDEX);
+        let min_buf = base.ear_cv_buffer(MIN_EAR_INDEX);
+        let max_buf = base.ear_cv_buffer(MAX_EAR_INDEX);
+        let tempo_sync = base.ear(SYNC_EAR_INDEX).get_control_value() > 0.5;
+        let shape = base.ear(SHAPE_EAR_INDEX).get_control_value().round() as usize;
+        let uni_buf = base.voice(UNIPOLAR_VOICE_PORT).cv_buffer();
+        let bi_buf = base.voice(BIPOLAR_VOICE_PORT).cv_buffer();
+
+        for i in start..end {
+            let freq = if tempo_sync {
+                bpm_buf[i] as f64 / (60. * beats_buf[i] as f64)
+            }
+            else {
+                rate_buf[i] as f64
+            };
+
+            let (u, v) = waveform(shape, self.phase, self.held_value);
+
+            let min = min_buf[i];
+            let max = max_buf[i];
+            let half_range = (max - min) * 0.5;
+
+            // the unipolar voice starts from min, the bipolar one from the middle of min and max
+            uni_buf[i] = min + (max - min) * u;
+            bi_buf[i] = min + half_range + half_range * v;
+
+            self.phase += freq / self.sample_rate;
+
+            if self.phase >= 1. {
+                self.phase = self.phase.fract();
+                self.held_value = self.random();
+            }
+        }
+    }
+}
+
+// Unipolar value in [0, 1] starting from 0 and bipolar value in [-1, 1] starting from 0 at the phase
+fn waveform(shape: usize, phase: f64, held_value: f32) -> (f32, f32) {
+    let p = phase as f32;
+
+    match shape {
+        SINE_SHAPE => {
+            let angle = phase * 2. * PI;
+            ((0.5 - 0.5 * angle.cos()) as f32, angle.sin() as f32)
+        }
+        TRIANGLE_SHAPE => {
+            let u = if p < 0.5 { 2. * p } else { 2. - 2. * p };
+            let v = if p < 0.25 {
+                4. * p
+            } else if p < 0.75 {
+                2. - 4. * p
+            } else {
+                4. * p - 4.
+            };
+            (u, v)
+        }
+        SAW_SHAPE => (p, 2. * (p + 0.5).fract() - 1.),
+        SQUARE_SHAPE => {
+            if p < 0.5 {
+                (1., 1.)
+            } else {
+                (0., -1.)
+            }
+        }
+        _ => ((held_value + 1.) * 0.5, held_value),
+    }
+}
+
+impl Talker for Lfo {
+    fn activate(&mut self) {
+        self.reset_phase();
+    }
+
+    fn talk(&mut self, base: &TalkerBase, _port: usize, tick: i64, len: usize) -> usize {
+        let ln = base.listen(tick, len);
+        let event_buf = base.ear_atom_buffer(EVENT_EAR_INDEX);
+
+        let mut t = 0;
+
+        for ev in event_buf.iter() {
+            if midi::event_is_note_on(&ev.data) {
+                let next_note_t = (ev.event.time_in_frames as usize).min(ln);
+
+                self.run(base, t, next_note_t);
+                self.reset_phase();
+                t = next_note_t;
+            }
+        }
+
+        self.run(base, t, ln);
+
+        for voice in base.voices() {
+            voice.set_tick_len(tick, ln);
+        }
+        ln
+    }
+}
+
+#[test]
+fn test_lfo_waveforms_range() {
+    for shape in SINE_SHAPE..=SAMPLE_AND_HOLD_SHAPE {
+        for step in 0..100 {
+            let (u, v) = waveform(shape, step as f64 / 100., -0.5);
+            assert!(u >= 0. && u <= 1., "shape {} unipolar {}", shape, u);
+            assert!(v >= -1. && v <= 1., "shape {} bipolar {}", shape, v);
+        }
+    }
+}
+
+#[test]
+fn test_lfo_waveforms_phase() {
+    let close = |a: (f32, f32), b: (f32, f32)| (a.0 - b.0).abs() < 1e-5 && (a.1 - b.1).abs() < 1e-5;
+
+    assert!(close(waveform(SINE_SHAPE, 0., 0.), (0., 0.)));
+    assert!(close(waveform(SINE_SHAPE, 0.25, 0.), (0.5, 1.)));
+    assert!(close(waveform(SINE_SHAPE, 0.5, 0.), (1., 0.)));
+    assert!(close(waveform(SINE_SHAPE, 0.75, 0.), (0.5, -1.)));
+
+    assert!(close(waveform(TRIANGLE_SHAPE, 0., 0.), (0., 0.)));
+    assert!(close(waveform(TRIANGLE_SHAPE, 0.25, 0.), (0.5, 1.)));
+    assert!(close(waveform(TRIANGLE_SHAPE, 0.5, 0.), (1., 0.)));
+    assert!(close(waveform(TRIANGLE_SHAPE, 0.75, 0.), (0.5, -1.)));
+
+    assert!(close(waveform(SAW_SHAPE, 0., 0.), (0., 0.)));
+    assert!(close(waveform(SAW_SHAPE, 0.25, 0.), (0.25, 0.5)));
+    assert!(close(waveform(SAW_SHAPE, 0.75, 0.), (0.75, -0.5)));
+
+    assert!(close(waveform(SQUARE_SHAPE, 0.25, 0.), (1., 1.)));
+    assert!(close(waveform(SQUARE_SHAPE, 0.75, 0.), (0., -1.)));
+
+    assert!(close(waveform(SAMPLE_AND_HOLD_SHAPE, 0.3, -0.5), (0.25, -0.5)));
+}
@@ -1,4 +1,7 @@
 use std::ffi::CStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Once;
 
 use livi::{self, PortIndex, Plugin};
 use livi::event::{LV2AtomEventBuilder, LV2AtomSequence};
@@ -11,10 +14,22 @@ use talker::ear::Init;
 use talker::horn::{AtomBuf, AudioBuf, CvBuf, MAtomBuf, MAudioBuf, MCvBuf};
 use talker::identifier::{Identifiable, Index};
 use talker::lv2_handler::{self, Lv2Handler};
-use talker::talker::{CTalker, Talker, TalkerBase};
+use talker::talker::{CTalker, RTalker, Talker, TalkerBase};
+use talker::transport::{self, Transport};
 use talker::data::Data;
 
 const ATOM_SEQUENCE_CAPACITY: usize = 65536;
+const POSITION_EVENT_CAPACITY: usize = 256;
+
+const USER_PRESETS_DIR: &str = "presets";
+const PRESET_STATE_FILE: &str = "state.ttl";
+const PRESET_PREFIXES: &str = "@prefix lv2: <http://lv2plug.in/ns/lv2core#> .
+@prefix pset: <http://lv2plug.in/ns/ext/presets#> .
+@prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .
+
+";
+
+static USER_PRESETS_LOADING: Once = Once::new();
 
 
 fn an_or(v: f32, def: f32) -> f32 {
@@ -25,11 +40,87 @@ fn an_or(v: f32, def: f32) -> f32 {
     }
 }
 
+fn push_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_ne_bytes());
+}
+
+// Atom object property : key, context, value atom header then value body padded to 64 bits
+fn push_property(buf: &mut Vec<u8>, key: u32, value_type: u32, value: &[u8]) {
+    push_u32(buf, key);
+    push_u32(buf, 0);
+    push_u32(buf, value.len() as u32);
+    push_u32(buf, value_type);
+    buf.extend_from_slice(value);
+
+    while buf.len() % 8 != 0 {
+        buf.push(0);
+    }
+}
+
+struct TimeUrids {
+    position: u32,
+    frame: u32,
+    speed: u32,
+    bar: u32,
+    bar_beat: u32,
+    beats_per_bar: u32,
+    beat_unit: u32,
+    beats_per_minute: u32,
+    object: u32,
+    long: u32,
+    float: u32,
+    int: u32,
+}
+
+impl TimeUrids {
+    fn new(lv2_handler: &Lv2Handler) -> TimeUrids {
+        let urid = |uri: &[u8]| lv2_handler.features.urid(CStr::from_bytes_with_nul(uri).unwrap());
+
+        Self {
+            position: urid(lv2_sys::LV2_TIME__Position),
+            frame: urid(lv2_sys::LV2_TIME__frame),
+            speed: urid(lv2_sys::LV2_TIME__speed),
+            bar: urid(lv2_sys::LV2_TIME__bar),
+            bar_beat: urid(lv2_sys::LV2_TIME__barBeat),
+            beats_per_bar: urid(lv2_sys::LV2_TIME__beatsPerBar),
+            beat_unit: urid(lv2_sys::LV2_TIME__beatUnit),
+            beats_per_minute: urid(lv2_sys::LV2_TIME__beatsPerMinute),
+            object: urid(lv2_sys::LV2_ATOM__Object),
+            long: urid(lv2_sys::LV2_ATOM__Long),
+            float: urid(lv2_sys::LV2_ATOM__Float),
+            int: urid(lv2_sys::LV2_ATOM__Int),
+        }
+    }
+
+    // time:Position object body
+    fn position(&self, tick: i64, speed: f32, tempo: f32) -> Vec<u8> {
+        let (bar, bar_beat) = Transport::bar_beat(tick, tempo);
+        let mut body = Vec::with_capacity(POSITION_EVENT_CAPACITY);
+
+        push_u32(&mut body, 0);
+        push_u32(&mut body, self.position);
+        push_property(&mut body, self.frame, self.long, &tick.to_ne_bytes());
+        push_property(&mut body, self.speed, self.float, &speed.to_ne_bytes());
+        push_property(&mut body, self.bar, self.long, &bar.to_ne_bytes());
+        push_property(&mut body, self.bar_beat, self.float, &bar_beat.to_ne_bytes());
+        push_property(&mut body, self.beats_per_bar, self.float, &transport::BEATS_PER_BAR.to_ne_bytes());
+        push_property(&mut body, self.beat_unit, self.int, &transport::BEAT_UNIT.to_ne_bytes());
+        push_property(&mut body, self.beats_per_minute, self.float, &tempo.to_ne_bytes());
+        body
+    }
+}
+
 struct Idxs {tkr_port: usize, plugin_port: usize}
 pub struct Lv2 {
     uri: String,
     urid_event_transfer: u32,
     urid_atom_transfer: u32,
+    time_urids: TimeUrids,
+    // Tick expected on the next talk if the playback runs continuously
+    next_tick: i64,
+    position_speed: f32,
+    position_tempo: f32,
+    position_sequence: LV2AtomSequence,
     control_inputs_indexes: Vec<Idxs>,
     control_outputs_indexes: Vec<Idxs>,
     audio_inputs_indexes: Vec<Idxs>,
@@ -38,6 +129,8 @@ pub struct Lv2 {
     atom_sequence_outputs_indexes: Vec<Idxs>,
     cv_inputs_indexes: Vec<Idxs>,
     cv_outputs_indexes: Vec<Idxs>,
+    // Control output reporting the plugin latency
+    latency_port: Option<usize>,
     instance: livi::Instance,
     save_dir: String,
 }
@@ -141,6 +234,8 @@ impl Lv2 {
                             }
                         }
 
+                        let latency_port = plugin.raw().latency_port_index();
+
                         let save_path = crate::util::backup_path().join(base.identifier().borrow().id().to_string());
                         let save_dir = save_path.to_str().unwrap_or(".").to_string();
 
@@ -150,6 +245,11 @@ impl Lv2 {
                                 uri: uri.to_string(),
                                 urid_event_transfer,
                                 urid_atom_transfer,
+                                time_urids: TimeUrids::new(lv2_handler),
+                                next_tick: i64::MIN,
+                                position_speed: 0.,
+                                position_tempo: 0.,
+                                position_sequence: LV2AtomSequence::new(&lv2_handler.features, ATOM_SEQUENCE_CAPACITY),
                                 control_inputs_indexes,
                                 control_outputs_indexes,
                                 audio_inputs_indexes,
@@ -158,6 +258,7 @@ impl Lv2 {
                                 atom_sequence_outputs_indexes,
                                 cv_inputs_indexes,
                                 cv_outputs_indexes,
+                                latency_port,
                                 instance,
                                 save_dir,
                             }
@@ -204,6 +305,39 @@ impl Lv2 {
         }
     }
 
+    // On a jump in time or a transport change, the position is sent ahead of the first atom input events
+    fn feed_position(&mut self, base: &TalkerBase, tick: i64) {
+        let speed = Transport::speed();
+        let tempo = Transport::tempo();
+
+        if tick == self.next_tick && speed == self.position_speed && tempo == self.position_tempo {
+            return;
+        }
+        if let Some(idx) = self.atom_sequence_inputs_indexes.first() {
+            self.position_speed = speed;
+            self.position_tempo = tempo;
+
+            let body = self.time_urids.position(tick, speed, tempo);
+            self.position_sequence.clear();
+
+            match LV2AtomEventBuilder::<POSITION_EVENT_CAPACITY>::new(0, self.time_urids.object, &body) {
+                Ok(ev_bldr) => self.position_sequence.push_event(&ev_bldr).unwrap_or_else(|e| println!("{}", e)),
+                Err(e) => println!("{}", e),
+            }
+
+            for ev in base.ear(idx.tkr_port).get_atom_buffer().iter() {
+                match LV2AtomEventBuilder::<1024>::new(ev.event.time_in_frames, ev.event.body.mytype, ev.data) {
+                    Ok(ev_bldr) => self.position_sequence.push_event(&ev_bldr).unwrap_or_else(|e| println!("{}", e)),
+                    Err(e) => println!("{}", e),
+                }
+            }
+
+            let livi_active_instance = self.instance.raw_mut();
+            let livi_instance = livi_active_instance.instance_mut();
+
+            unsafe { livi_instance.connect_port(idx.plugin_port, self.position_sequence.as_ptr()); }
+        }
+    }
 
     fn talk0(&mut self, base: &TalkerBase, _port: usize, tick: i64, len: usize) -> usize {
         let ln = base.listen(tick, len);
@@ -332,10 +466,18 @@ impl Talker for Lv2 {
         Ok(ports_events)
     }
 
+    fn latency(&self, _base: &TalkerBase) -> usize {
+        self.latency_port
+            .and_then(|port| self.instance.control_output(PortIndex(port)))
+            .map_or(0, |latency| an_or(latency, 0.).max(0.) as usize)
+    }
+
     fn talk(&mut self, base: &TalkerBase, _port: usize, tick: i64, len: usize) -> usize {
         let ln = base.listen(tick, len);
 
         self.connect_ports(base);
+        self.feed_position(base, tick);
+        self.next_tick = tick + ln as i64;
 
         let livi_active_instance = self.instance.raw_mut();
 
@@ -396,6 +538,162 @@ impl Talker for Lv2 {
     }
 }
 
+pub fn user_presets_path() -> PathBuf {
+    crate::util::configuration_path().join(USER_PRESETS_DIR)
+}
+
+// The user presets bundles are not in the LV2 path so they are loaded on the first presets request
+fn load_user_presets() {
+    USER_PRESETS_LOADING.call_once(|| {
+        if let Ok(entries) = fs::read_dir(user_presets_path()) {
+            for entry in entries.flatten() {
+                if entry.path().is_dir() {
+                    if let Err(e) = lv2_handler::load_bundle(&format!("{}/", file_uri(&entry.path()))) {
+                        eprintln!("{}", e);
+                    }
+                }
+            }
+        }
+    });
+}
+
+// URI and label of the plugin presets, the shipped ones and the user ones
+pub fn presets(plugin_uri: &str) -> Result<Vec<(String, String)>, failure::Error> {
+    load_user_presets();
+    lv2_handler::get_presets(plugin_uri)
+}
+
+// Ear index and value of the plugin control inputs set by the preset
+pub fn preset_ears_values(plugin_uri: &str, preset_uri: &str) -> Result<Vec<(Index, f32)>, failure::Error> {
+    load_user_presets();
+
+    let control_inputs_ears = lv2_handler::get_control_inputs_ears(plugin_uri)?;
+    let mut ears_values = Vec::new();
+
+    for (symbol, value) in lv2_handler::get_preset_port_values(preset_uri)? {
+        match control_inputs_ears.iter().find(|(s, _)| *s == symbol) {
+            Some((_, ear_idx)) => ears_values.push((*ear_idx, value)),
+            None => eprintln!("LV2 preset {} port {} unknown.", preset_uri, symbol),
+        }
+    }
+    Ok(ears_values)
+}
+
+// Percent-encoded URI of an absolute file path
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+// File path of a file URI. None for the other URIs
+fn file_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut path = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+
+    while i < encoded.len() {
+        if encoded[i] == b'%' && i + 2 < encoded.len() {
+            let hex = std::str::from_utf8(&encoded[i + 1..i + 3]).ok()?;
+            path.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            path.push(encoded[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(path).ok().map(PathBuf::from)
+}
+
+// Plugin state saved with a user preset
+pub fn preset_state(preset_uri: &str) -> Result<Option<String>, failure::Error> {
+    let state_path = match file_path(preset_uri).and_then(|path| path.parent().map(|dir| dir.join(PRESET_STATE_FILE))) {
+        Some(path) if path.is_file() => path,
+        _ => return Ok(None),
+    };
+    Ok(Some(fs::read_to_string(state_path)?))
+}
+
+fn escape_literal(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// The bundle directory is named after the plugin and the preset so that the plugins presets of same name don't collide
+fn preset_bundle_name(plugin_uri: &str, name: &str) -> String {
+    let sanitize = |s: &str| -> String { s.chars().map(|c| if c.is_alphanumeric() { c } else { '_' }).collect() };
+    let plugin = plugin_uri.split_once("://").map_or(plugin_uri, |(_, rest)| rest);
+
+    format!("{}-{}", sanitize(plugin), sanitize(name))
+}
+
+// Save the talker control inputs values as a user preset bundle and return the preset URI.
+// The plugin internal state is saved in the bundle through the LV2 state interface
+pub fn save_preset(talker: &RTalker, name: &str) -> Result<String, failure::Error> {
+    let plugin_uri = talker.model();
+
+    let preset_name: String = name.chars().map(|c| if c.is_alphanumeric() { c } else { '_' }).collect();
+
+    if preset_name.is_empty() {
+        return Err(failure::err_msg("LV2 preset name is empty."));
+    }
+
+    // The user presets are loaded before so that a saved bundle is not loaded twice
+    load_user_presets();
+
+    let bundle_path = user_presets_path().join(format!("{}.preset.lv2", preset_bundle_name(&plugin_uri, name)));
+    let bundle_uri = format!("{}/", file_uri(&bundle_path));
+
+    // A preset saved again is unloaded before its bundle is rewritten
+    if bundle_path.exists() {
+        lv2_handler::unload_bundle(&bundle_uri)?;
+    }
+    fs::create_dir_all(&bundle_path)?;
+
+    let preset_file = format!("{}.ttl", preset_name);
+
+    let manifest = format!(
+        "{}<{}>\n    a pset:Preset ;\n    lv2:appliesTo <{}> ;\n    rdfs:seeAlso <{}> .\n",
+        PRESET_PREFIXES, preset_file, plugin_uri, preset_file
+    );
+
+    let mut ports = Vec::new();
+
+    for (symbol, ear_idx) in lv2_handler::get_control_inputs_ears(&plugin_uri)? {
+        let value = talker.ear(ear_idx).talk_value_or_default(0, 0);
+        ports.push(format!("[\n        lv2:symbol \"{}\" ;\n        pset:value {:?}\n    ]", symbol, value));
+    }
+
+    let mut preset = format!(
+        "{}<>\n    a pset:Preset ;\n    lv2:appliesTo <{}> ;\n    rdfs:label \"{}\"",
+        PRESET_PREFIXES, plugin_uri, escape_literal(name)
+    );
+    if !ports.is_empty() {
+        preset.push_str(&format!(" ;\n    lv2:port {}", ports.join(" , ")));
+    }
+    preset.push_str(" .\n");
+
+    fs::write(bundle_path.join("manifest.ttl"), manifest)?;
+    fs::write(bundle_path.join(&preset_file), preset)?;
+
+    let state_path = bundle_path.join(PRESET_STATE_FILE);
+
+    match talker.state()? {
+        Some(state) => fs::write(state_path, state)?,
+        None if state_path.exists() => fs::remove_file(state_path)?,
+        None => (),
+    }
+
+    lv2_handler::load_bundle(&bundle_uri)?;
+
+    Ok(format!("{}{}", bundle_uri, preset_file))
+}
+
 pub fn show_plugin(plugin: &Plugin) {
     println!("plugin {} ({})", plugin.name(), plugin.uri());
 
@@ -479,6 +777,23 @@ fn test_var_len_of() {
     assert_eq!(var_len_of(256), vec![0x82, 0]);
     assert_eq!(var_len_of(0x100000), vec![0xC0, 0x80, 0]);
 }
+
+#[test]
+fn test_preset_file_uri() {
+    let path = Path::new("/home/me/My presets/50% wet#1.preset.lv2");
+    let uri = file_uri(path);
+
+    assert!(uri == "file:///home/me/My%20presets/50%25%20wet%231.preset.lv2");
+    assert!(file_path(&uri) == Some(path.to_path_buf()));
+    assert!(file_path("http://example.org/preset") == None);
+}
+
+#[test]
+fn test_preset_bundle_name() {
+    assert!(preset_bundle_name("http://example.org/plugins/a", "Lead") == "example_org_plugins_a-Lead");
+    assert!(preset_bundle_name("http://example.org/plugins/a", "Lead") != preset_bundle_name("http://example.org/plugins/b", "Lead"));
+    assert!(preset_bundle_name("urn:example:comp", "50% wet") == "urn_example_comp-50__wet");
+}
 /*
 use crate::feedback::Feedback;
 use output::Output;
@@ -585,4 +900,4 @@ fn run_midi() -> Result<(), failure::Error> {
 fn test_run_midi() {
     run_midi().unwrap();
 }
- */
\ No newline at end of file
+ */
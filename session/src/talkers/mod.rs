@@ -2,17 +2,24 @@ pub mod accumulator;
 pub mod adsrp;
 pub mod audio_switch;
 pub mod audiofile_input;
+pub mod automation;
 pub mod bounded_sinusoidal;
 pub mod bounded_square;
+pub mod clap;
 pub mod damper;
 pub mod dynamic_modulator;
+pub mod dynamics;
 pub mod envelope_shaper;
 pub mod speed_modulator;
 pub mod fuzz;
+pub mod granular;
 pub mod hub;
+pub mod ladspa;
+pub mod lfo;
 pub mod lv2;
 pub mod math;
 pub mod parabolic;
+pub mod polyblep;
 pub mod regulator;
 pub mod round;
 pub mod second_degree_frequency_progression;
@@ -21,3 +28,4 @@ pub mod sinusoidal_fptg;
 pub mod square;
 pub mod table_talker;
 pub mod tseq;
+pub mod wavetable;
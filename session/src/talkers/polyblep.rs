@@ -0,0 +1,271 @@
+use std::f32;
+
+use talker::audio_format::AudioFormat;
+use talker::ctalker;
+use talker::ear;
+use talker::ear::Init;
+use talker::identifier::Index;
+use talker::talker::{CTalker, Talker, TalkerBase};
+use talker::talker_handler::TalkerHandlerBase;
+
+const FREQ_EAR_INDEX: Index = 0;
+const PHASE_EAR_INDEX: Index = 1;
+const GAIN_EAR_INDEX: Index = 2;
+const SYNC_EAR_INDEX: Index = 3;
+const WIDTH_EAR_INDEX: Index = 4;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Waveform {
+    Saw,
+    Pulse,
+    Triangle,
+}
+
+// Polynomial band-limited step correction around a discontinuity at t = 0
+fn poly_blep(t: f32, dt: f32) -> f32 {
+    if t < dt {
+        let x = t / dt;
+        x + x - x * x - 1.
+    }
+    else if t > 1. - dt {
+        let x = (t - 1.) / dt;
+        x * x + x + x + 1.
+    }
+    else {
+        0.
+    }
+}
+
+// Polynomial band-limited ramp correction around a slope discontinuity at t = 0, integral of poly_blep
+fn poly_blamp(t: f32, dt: f32) -> f32 {
+    if t < dt {
+        let x = 1. - t / dt;
+        x * x * x / 6.
+    }
+    else if t > 1. - dt {
+        let x = (t - 1.) / dt + 1.;
+        x * x * x / 6.
+    }
+    else {
+        0.
+    }
+}
+
+// Rising from -1 at t = 0 to 1 at t = 0.5 as the integral of the pulse
+fn naive_triangle(t: f32) -> f32 {
+    if t < 0.5 { 4. * t - 1. } else { 3. - 4. * t }
+}
+
+fn naive_value(waveform: Waveform, t: f32, width: f32) -> f32 {
+    match waveform {
+        Waveform::Saw => 2. * t - 1.,
+        Waveform::Pulse => if t < width { 1. } else { -1. },
+        Waveform::Triangle => naive_triangle(t),
+    }
+}
+
+fn band_limited_value(waveform: Waveform, t: f32, dt: f32, width: f32) -> f32 {
+    match waveform {
+        Waveform::Saw => 2. * t - 1. - poly_blep(t, dt),
+        Waveform::Pulse => {
+            naive_value(waveform, t, width) + poly_blep(t, dt) - poly_blep((t + 1. - width).rem_euclid(1.), dt)
+        }
+        // The slope changes by 8 per period at each corner
+        Waveform::Triangle => {
+            naive_triangle(t) + 8. * dt * (poly_blamp(t, dt) - poly_blamp((t + 0.5).rem_euclid(1.), dt))
+        }
+    }
+}
+
+// Correction of the sample following a step of height h by x sample, the previous sample being already played
+fn sync_blep(h: f32, x: f32) -> f32 {
+    let r = 1. - x;
+    -0.5 * h * r * r
+}
+
+#[test]
+fn test_poly_blep() {
+    assert!(poly_blep(0.5, 0.01) == 0.);
+    assert!(poly_blep(0., 0.01) == -1.);
+    assert!(poly_blep(0.999999, 0.01) > 0.99);
+}
+
+#[test]
+fn test_poly_blamp() {
+    assert!(poly_blamp(0.5, 0.01) == 0.);
+    assert!((poly_blamp(0., 0.01) - 1. / 6.).abs() < 1e-6);
+    assert!((poly_blamp(0.999999, 0.01) - 1. / 6.).abs() < 1e-3);
+    assert!(poly_blamp(0.01, 0.01) == 0.);
+}
+
+#[test]
+fn test_band_limited_triangle() {
+    let dt = 0.01;
+    let values: Vec<f32> = (0..100).map(|i| band_limited_value(Waveform::Triangle, i as f32 * dt, dt, 0.5)).collect();
+
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    let max = values.iter().fold(f32::MIN, |m, v| m.max(*v));
+    let min = values.iter().fold(f32::MAX, |m, v| m.min(*v));
+
+    assert!(mean.abs() < 1e-3);
+    // The corners are rounded without overshoot
+    assert!(max < 1. && max > 0.95 && min > -1. && min < -0.95);
+    // Far from the corners the triangle is the naive one
+    assert!((values[25] - naive_triangle(0.25)).abs() < 1e-6);
+}
+
+#[test]
+fn test_sync_blep() {
+    // A step just before the sample is half corrected, a step one sample before is not corrected
+    assert!(sync_blep(-2., 0.) == 1.);
+    assert!(sync_blep(-2., 1.) == 0.);
+}
+
+struct BlOscillator {
+    waveform: Waveform,
+    sample_rate: f32,
+    last_tick: i64,
+    last_pos: f32,
+    last_sync: f32,
+}
+
+impl BlOscillator {
+    fn new(base: &mut TalkerBase, waveform: Waveform) -> Result<BlOscillator, failure::Error> {
+        base.add_ear(ear::cv(Some("freq"), 0., 20000., 440., &Init::DefValue)?);
+        base.add_ear(ear::audio(Some("phase"), -1., 2., 0., &Init::DefValue)?);
+        base.add_ear(ear::audio(Some("gain"), -1., 1., 1., &Init::DefValue)?);
+        base.add_ear(ear::audio(Some("sync"), -1., 1., 0., &Init::DefValue)?);
+
+        if waveform == Waveform::Pulse {
+            base.add_ear(ear::cv(Some("width"), 0.01, 0.99, 0.5, &Init::DefValue)?);
+        }
+
+        base.add_audio_voice(None, 0.);
+
+        Ok(Self {
+            waveform,
+            sample_rate: AudioFormat::sample_rate() as f32,
+            last_tick: 0,
+            last_pos: 0.,
+            last_sync: 0.,
+        })
+    }
+
+    fn talk(&mut self, base: &TalkerBase, port: usize, tick: i64, len: usize) -> usize {
+        let ln = base.listen(tick, len);
+        let freq_buf = base.ear_cv_buffer(FREQ_EAR_INDEX);
+        let phase_buf = base.ear_audio_buffer(PHASE_EAR_INDEX);
+        let gain_buf = base.ear_audio_buffer(GAIN_EAR_INDEX);
+        let sync_buf = base.ear_audio_buffer(SYNC_EAR_INDEX);
+        let width_buf = if self.waveform == Waveform::Pulse { Some(base.ear_cv_buffer(WIDTH_EAR_INDEX)) } else { None };
+        let voice_buf = base.voice(port).audio_buffer();
+
+        if self.last_tick != tick {
+            self.last_pos = 0.;
+            self.last_sync = 0.;
+        }
+
+        let mut pos = self.last_pos;
+        let mut last_sync = self.last_sync;
+
+        for i in 0..ln {
+            let sync = sync_buf[i];
+            let dt = (freq_buf[i] / self.sample_rate).min(0.5);
+            let width = width_buf.map_or(0.5, |buf| buf[i]);
+            // as for the table talkers, a phase of 1 is half a period
+            let phase = phase_buf[i] * 0.5;
+
+            // hard sync on the rising edge of the master signal, x samples before this one
+            let v = if last_sync <= 0. && sync > 0. {
+                let x = sync / (sync - last_sync);
+                let old_t = (pos - x * dt + phase).rem_euclid(1.);
+                let new_t = (x * dt + phase).rem_euclid(1.);
+                let h = naive_value(self.waveform, phase.rem_euclid(1.), width) - naive_value(self.waveform, old_t, width);
+
+                pos = x * dt;
+                naive_value(self.waveform, new_t, width) + sync_blep(h, x)
+            } else {
+                band_limited_value(self.waveform, (pos + phase).rem_euclid(1.), dt, width)
+            };
+            last_sync = sync;
+
+            voice_buf[i] = v * gain_buf[i];
+
+            pos += dt;
+            if pos >= 1. {
+                pos -= 1.;
+            }
+        }
+
+        self.last_pos = pos;
+        self.last_sync = last_sync;
+        self.last_tick = tick + ln as i64;
+        ln
+    }
+}
+
+pub const SAW_MODEL: &str = "BLSaw";
+
+pub struct BlSaw {
+    oscillator: BlOscillator,
+}
+impl BlSaw {
+    pub fn new(mut base: TalkerBase) -> Result<CTalker, failure::Error> {
+        let oscillator = BlOscillator::new(&mut base, Waveform::Saw)?;
+        Ok(ctalker!(base, Self { oscillator }))
+    }
+
+    pub fn descriptor() -> TalkerHandlerBase {
+        TalkerHandlerBase::builtin("Oscillator", SAW_MODEL, "Band-limited Saw")
+    }
+}
+
+impl Talker for BlSaw {
+    fn talk(&mut self, base: &TalkerBase, port: usize, tick: i64, len: usize) -> usize {
+        self.oscillator.talk(base, port, tick, len)
+    }
+}
+
+pub const PULSE_MODEL: &str = "BLPulse";
+
+pub struct BlPulse {
+    oscillator: BlOscillator,
+}
+impl BlPulse {
+    pub fn new(mut base: TalkerBase) -> Result<CTalker, failure::Error> {
+        let oscillator = BlOscillator::new(&mut base, Waveform::Pulse)?;
+        Ok(ctalker!(base, Self { oscillator }))
+    }
+
+    pub fn descriptor() -> TalkerHandlerBase {
+        TalkerHandlerBase::builtin("Oscillator", PULSE_MODEL, "Band-limited Pulse")
+    }
+}
+
+impl Talker for BlPulse {
+    fn talk(&mut self, base: &TalkerBase, port: usize, tick: i64, len: usize) -> usize {
+        self.oscillator.talk(base, port, tick, len)
+    }
+}
+
+pub const TRIANGLE_MODEL: &str = "BLTriangle";
+
+pub struct BlTriangle {
+    oscillator: BlOscillator,
+}
+impl BlTriangle {
+    pub fn new(mut base: TalkerBase) -> Result<CTalker, failure::Error> {
+        let oscillator = BlOscillator::new(&mut base, Waveform::Triangle)?;
+        Ok(ctalker!(base, Self { oscillator }))
+    }
+
+    pub fn descriptor() -> TalkerHandlerBase {
+        TalkerHandlerBase::builtin("Oscillator", TRIANGLE_MODEL, "Band-limited Triangle")
+    }
+}
+
+impl Talker for BlTriangle {
+    fn talk(&mut self, base: &TalkerBase, port: usize, tick: i64, len: usize) -> usize {
+        self.oscillator.talk(base, port, tick, len)
+    }
+}
@@ -0,0 +1,155 @@
+extern crate audiofile;
+
+use std::f32;
+
+use talker::ctalker;
+use talker::audio_format::AudioFormat;
+use talker::data::Data;
+use talker::ear;
+use talker::ear::Init;
+use talker::identifier::Index;
+use talker::talker::{CTalker, Talker, TalkerBase};
+use talker::talker_handler::TalkerHandlerBase;
+use audiofile::reader::Reader;
+
+pub const MODEL: &str = "Wavetable";
+
+const FREQ_EAR_INDEX: Index = 0;
+const PHASE_EAR_INDEX: Index = 1;
+const GAIN_EAR_INDEX: Index = 2;
+const POSITION_EAR_INDEX: Index = 3;
+const FRAME_LEN_EAR_INDEX: Index = 4;
+
+const DEF_FRAME_LEN: f32 = 2048.;
+
+pub struct Wavetable {
+    table: Vec<f32>,
+    sample_rate: f32,
+    last_tick: i64,
+    last_pos: f32,
+}
+
+impl Wavetable {
+    pub fn new(mut base: TalkerBase) -> Result<CTalker, failure::Error> {
+        base.add_ear(ear::cv(Some("freq"), 0., 20000., 440., &Init::DefValue)?);
+        base.add_ear(ear::audio(Some("phase"), -1., 2., 0., &Init::DefValue)?);
+        base.add_ear(ear::audio(Some("gain"), -1., 1., 1., &Init::DefValue)?);
+        base.add_ear(ear::cv(Some("position"), 0., 1., 0., &Init::DefValue)?);
+        base.add_ear(ear::control(Some("frame_len"), 1., 65536., DEF_FRAME_LEN)?);
+
+        base.add_audio_voice(None, 0.);
+
+        base.set_data(Data::File("Click here to select a file".to_string()));
+
+        Ok(ctalker!(
+            base,
+            Self {
+                table: Vec::new(),
+                sample_rate: AudioFormat::sample_rate() as f32,
+                last_tick: 0,
+                last_pos: 0.,
+            }
+        ))
+    }
+
+    pub fn descriptor() -> TalkerHandlerBase {
+        TalkerHandlerBase::builtin("Oscillator", MODEL, MODEL)
+    }
+}
+
+fn read_table(filename: &str) -> Result<Vec<f32>, failure::Error> {
+    // The table is read at its own sample rate to keep the frames length
+    let mut file_reader = Reader::with_file_sample_rate(filename)?;
+
+    let channels = file_reader.read_all_samples()?;
+
+    match channels.first() {
+        Some(first_channel) => {
+            let mut table = first_channel.clone();
+
+            if channels.len() > 1 {
+                let coef = 1. / channels.len() as f32;
+
+                for channel in &channels[1..] {
+                    for (t, v) in table.iter_mut().zip(channel) {
+                        *t += v;
+                    }
+                }
+                for t in table.iter_mut() {
+                    *t *= coef;
+                }
+            }
+            Ok(table)
+        }
+        None => Err(failure::err_msg(format!("{} file {} has no channel", MODEL, filename))),
+    }
+}
+
+impl Talker for Wavetable {
+    fn set_data_update(
+        &mut self,
+        base: &TalkerBase,
+        data: Data,
+    ) -> Result<Option<TalkerBase>, failure::Error> {
+        match data {
+            Data::File(ref filename) => {
+                if base.is_effective() {
+                    self.table = read_table(filename)?;
+                }
+                base.set_data(data);
+                Ok(None)
+            }
+            _ => Err(failure::err_msg(format!("{} data type {} is not File", MODEL, data.type_str()))),
+        }
+    }
+
+    fn talk(&mut self, base: &TalkerBase, port: usize, tick: i64, len: usize) -> usize {
+        let ln = base.listen(tick, len);
+        let freq_buf = base.ear_cv_buffer(FREQ_EAR_INDEX);
+        let phase_buf = base.ear_audio_buffer(PHASE_EAR_INDEX);
+        let gain_buf = base.ear_audio_buffer(GAIN_EAR_INDEX);
+        let position_buf = base.ear_cv_buffer(POSITION_EAR_INDEX);
+        let voice_buf = base.voice(port).audio_buffer();
+
+        let table_len = self.table.len();
+
+        if table_len == 0 {
+            voice_buf[..ln].fill(0.);
+            return ln;
+        }
+
+        // a file shorter than a frame is taken as a single cycle table
+        let frame_len = (base.ear(FRAME_LEN_EAR_INDEX).get_control_value() as usize).max(1).min(table_len);
+        let frames_count = table_len / frame_len;
+        let last_frame = (frames_count - 1) as f32;
+
+        let mut pos = if self.last_tick == tick { self.last_pos } else { 0. };
+
+        for i in 0..ln {
+            // as for the table talkers, a phase of 1 is half a period
+            let p = (pos + phase_buf[i] * 0.5).rem_euclid(1.) * frame_len as f32;
+            let idx = (p as usize).min(frame_len - 1);
+            let next_idx = (idx + 1) % frame_len;
+            let frac = p.fract();
+
+            let frame = position_buf[i].max(0.).min(1.) * last_frame;
+            let frame_a = frame as usize;
+            let frame_b = (frame_a + 1).min(frames_count - 1);
+            let frame_frac = frame.fract();
+
+            let a_start = frame_a * frame_len;
+            let b_start = frame_b * frame_len;
+
+            let va = self.table[a_start + idx] + (self.table[a_start + next_idx] - self.table[a_start + idx]) * frac;
+            let vb = self.table[b_start + idx] + (self.table[b_start + next_idx] - self.table[b_start + idx]) * frac;
+
+            voice_buf[i] = (va + (vb - va) * frame_frac) * gain_buf[i];
+
+            pos = (pos + freq_buf[i] / self.sample_rate).fract();
+        }
+
+        self.last_pos = pos;
+        self.last_tick = tick + ln as i64;
+        ln
+    }
+}
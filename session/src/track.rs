@@ -1,6 +1,8 @@
+use talker::audio_format::AudioFormat;
 use talker::ear::Set;
 use talker::identifier::Index;
 use crate::audio_data::Vector;
+use crate::panner::Panner;
 
 pub const KIND: &str = "track";
 
@@ -8,7 +10,100 @@ const INPUT_INDEX: Index = 0;
 const GAIN_INDEX: Index = 1;
 const CHANNEL_GAIN_INDEX: Index = 2;
 
-fn compute_input_gain(set: &Set, _tick: i64, buf: &mut Vector, len: usize) -> usize {
+// Compensation delay of a track input. The same chunk can be processed several times
+// (mix, feedback, stems) so the input history is kept in a ring from the current chunk start minus the delay
+#[derive(Default)]
+pub struct Delay {
+    len: usize,
+    ring: Vec<f32>,
+    // Tick following the last input sample written in the ring
+    end_tick: i64,
+}
+
+fn ring_index(tick: i64, capacity: usize) -> usize {
+    tick.rem_euclid(capacity as i64) as usize
+}
+
+impl Delay {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    // The ring is sized to the delay plus a chunk so this is called out of the audio path.
+    // The history is kept to change the delay without glitch
+    pub fn set_len(&mut self, len: usize) {
+        self.len = len;
+
+        if len > 0 {
+            self.reserve(len + AudioFormat::chunk_size());
+        }
+    }
+
+    fn reserve(&mut self, capacity: usize) {
+        let old_capacity = self.ring.len();
+
+        if old_capacity < capacity {
+            let mut ring = vec![0.; capacity];
+
+            for tick in self.end_tick - old_capacity as i64..self.end_tick {
+                ring[ring_index(tick, capacity)] = self.ring[ring_index(tick, old_capacity)];
+            }
+            self.ring = ring;
+        }
+    }
+
+    fn process(&mut self, tick: i64, buf: &mut Vector, len: usize) {
+        if self.len == 0 {
+            return;
+        }
+        // Only a chunk longer than the expected size makes the ring grow
+        self.reserve(self.len + len);
+
+        let capacity = self.ring.len();
+        let end_tick = tick + len as i64;
+        let delayed_tick = tick - self.len as i64;
+
+        // Out of the history, the ring restarts from silence
+        if tick > self.end_tick || delayed_tick < self.end_tick - capacity as i64 {
+            self.ring.fill(0.);
+            self.end_tick = tick;
+        }
+
+        for t in self.end_tick.max(tick)..end_tick {
+            self.ring[ring_index(t, capacity)] = buf[(t - tick) as usize];
+        }
+        self.end_tick = self.end_tick.max(end_tick);
+
+        for i in 0..len {
+            buf[i] = self.ring[ring_index(delayed_tick + i as i64, capacity)];
+        }
+    }
+}
+
+// State of a track kept by the mixer from a chunk to the next
+#[derive(Default)]
+pub struct Track {
+    pub delay: Delay,
+    pan_ramps: Vec<(f32, f32)>,
+    // Panning positions and chunk length of the pan ramps
+    pan_key: Option<([f32; 6], usize)>,
+}
+
+impl Track {
+    // The ramps are only computed again when the panning moves. None without panner
+    fn pan_ramps(&mut self, set: &Set, len: usize, channels: usize, opanner: Option<&Panner>) -> Option<&[(f32, f32)]> {
+        let panner = opanner?;
+        let key = (panner.positions(set, len), len);
+
+        if self.pan_key != Some(key) || self.pan_ramps.len() != channels {
+            panner.ramps(&key.0, len, &mut self.pan_ramps);
+            self.pan_key = Some(key);
+        }
+        Some(&self.pan_ramps)
+    }
+}
+
+fn compute_input_gain(set: &Set, tick: i64, buf: &mut Vector, len: usize, delay: &mut Delay) -> usize {
 
     let in_buf = set.get_hum_audio_buffer(INPUT_INDEX);
     let gain_buf = set.get_hum_audio_buffer(GAIN_INDEX);
@@ -16,6 +111,8 @@ fn compute_input_gain(set: &Set, _tick: i64, buf: &mut Vector, len: usize) -> us
     for i in 0..len {
         buf[i] = in_buf[i] * gain_buf[i];
     }
+
+    delay.process(tick, buf, len);
     len
 }
 
@@ -25,15 +122,29 @@ pub fn set(
     buf: &mut Vector,
     len: usize,
     channels: &mut Vec<Vector>,
+    opanner: Option<&Panner>,
+    track: &mut Track,
 ) -> usize {
-    let ln = compute_input_gain(set, tick, buf, len);
+    let ln = compute_input_gain(set, tick, buf, len, &mut track.delay);
+    let pan_ramps = track.pan_ramps(set, ln, channels.len(), opanner);
 
     for i in 0..channels.len() {
         let ch = &mut channels[i];
         let cg = set.get_hum_cv_buffer(CHANNEL_GAIN_INDEX + i);
 
-        for j in 0..ln {
-            ch[j] = cg[j] * buf[j];
+        match pan_ramps {
+            Some(ramps) => {
+                let (pg, pan_step) = ramps[i];
+
+                for j in 0..ln {
+                    ch[j] = cg[j] * buf[j] * (pg + pan_step * j as f32);
+                }
+            }
+            None => {
+                for j in 0..ln {
+                    ch[j] = cg[j] * buf[j];
+                }
+            }
         }
     }
     ln
@@ -45,17 +156,66 @@ pub fn add(
     buf: &mut Vector,
     len: usize,
     channels: &mut Vec<Vector>,
+    opanner: Option<&Panner>,
+    track: &mut Track,
 ) -> usize {
-    let ln = compute_input_gain(set, tick, buf, len);
+    let ln = compute_input_gain(set, tick, buf, len, &mut track.delay);
+    let pan_ramps = track.pan_ramps(set, ln, channels.len(), opanner);
 
     for i in 0..channels.len() {
         let ch = &mut channels[i];
         let cg = set.get_hum_cv_buffer(CHANNEL_GAIN_INDEX + i);
 
-        for j in 0..ln {
-            ch[j] = ch[j] + cg[j] * buf[j];
+        match pan_ramps {
+            Some(ramps) => {
+                let (pg, pan_step) = ramps[i];
+
+                for j in 0..ln {
+                    ch[j] = ch[j] + cg[j] * buf[j] * (pg + pan_step * j as f32);
+                }
+            }
+            None => {
+                for j in 0..ln {
+                    ch[j] = ch[j] + cg[j] * buf[j];
+                }
+            }
         }
     }
 
     ln
 }
+
+#[test]
+fn test_track_delay() {
+    let mut delay = Delay::default();
+    delay.set_len(3);
+
+    let mut buf: Vector = vec![1., 2., 3., 4.];
+    delay.process(0, &mut buf, 4);
+    assert!(buf == vec![0., 0., 0., 1.]);
+
+    // Processing the same chunk again gives the same output
+    let mut buf: Vector = vec![1., 2., 3., 4.];
+    delay.process(0, &mut buf, 4);
+    assert!(buf == vec![0., 0., 0., 1.]);
+
+    let mut buf: Vector = vec![5., 6., 7., 8.];
+    delay.process(4, &mut buf, 4);
+    assert!(buf == vec![2., 3., 4., 5.]);
+
+    // The history is kept when the delay changes
+    delay.set_len(5);
+    let mut buf: Vector = vec![9., 10., 11., 12.];
+    delay.process(8, &mut buf, 4);
+    assert!(buf == vec![4., 5., 6., 7.]);
+
+    delay.set_len(1);
+    let mut buf: Vector = vec![13., 14., 15., 16.];
+    delay.process(12, &mut buf, 4);
+    assert!(buf == vec![12., 13., 14., 15.]);
+
+    // Out of the history, the delay restarts from silence
+    let mut buf: Vector = vec![17., 18., 19., 20.];
+    delay.process(100000, &mut buf, 4);
+    assert!(buf == vec![0., 17., 18., 19.]);
+}
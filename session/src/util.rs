@@ -31,3 +31,29 @@ pub fn backup_path() -> std::path::PathBuf {
         None => configuration_path(),
     }
 }
+
+pub unsafe fn to_string(s: *const std::ffi::c_char) -> String {
+    if s.is_null() {
+        String::new()
+    } else {
+        std::ffi::CStr::from_ptr(s).to_string_lossy().to_string()
+    }
+}
+
+pub fn filename_with_suffix(filename: &str, suffix: &str) -> String {
+    let name_pos = filename.rfind(std::path::MAIN_SEPARATOR).map_or(0, |p| p + 1);
+
+    match filename[name_pos..].rfind(".") {
+        Some(ext_pos) => {
+            let (stem, ext) = filename.split_at(name_pos + ext_pos);
+            format!("{}-{}{}", stem, suffix, ext)
+        }
+        None => format!("{}-{}", filename, suffix),
+    }
+}
+
+#[test]
+fn test_filename_with_suffix() {
+    assert_eq!(filename_with_suffix("song.flac", "bass"), "song-bass.flac");
+    assert_eq!(filename_with_suffix("song", "bass"), "song-bass");
+}
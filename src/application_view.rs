@@ -4,7 +4,9 @@ use std::rc::Rc;
 use gtk::prelude::*;
 use gtk::glib::clone;
 
+use talker::data::Data;
 use talker::identifier::Id;
+use talker::lv2_handler;
 
 use session::event_bus::{Notification, REventBus};
 use session::state::State;
@@ -16,6 +18,8 @@ use crate::session_presenter::RSessionPresenter;
 use crate::settings;
 use crate::talker_data_view::TalkerDataView;
 use crate::talkers_list_view::TalkersListView;
+use crate::ui::{automation_editor, midi_learn, plugin_parameters, plugin_presets};
+use crate::timeline_view::TimelineView;
 
 pub struct ApplicationView {
     window: gtk::ApplicationWindow,
@@ -28,6 +32,7 @@ pub struct ApplicationView {
     talker_data_view: TalkerDataView,
     talkers_list_view: TalkersListView,
     graph_view: RGraphView,
+    timeline_view: TimelineView,
     session_presenter: RSessionPresenter,
     overall_entry_in_progress: bool,
     overall_entry: String,
@@ -179,11 +184,16 @@ impl ApplicationView {
         graph_view.borrow().add_content(|w| split_pane.append(w));
 
 
+        // Timeline view
+        let timeline_view = TimelineView::new(session_presenter);
+
+
         // Vertical box
         let v_box = gtk::Box::new(gtk::Orientation::Vertical, 2);
         v_box.append(&message_view_revealer);
         talker_data_view.add_content(|w| v_box.append(w));
         v_box.append(&split_pane);
+        timeline_view.add_content(|w| v_box.append(w));
 
         window.set_child(Some(&v_box));
 
@@ -213,6 +223,7 @@ impl ApplicationView {
             talker_data_view,
             talkers_list_view,
             graph_view,
+            timeline_view,
             session_presenter: session_presenter.clone(),
             overall_entry_in_progress: false,
             overall_entry: String::new(),
@@ -265,6 +276,60 @@ impl ApplicationView {
         self.event_bus.borrow().notify_notifications_result(res);
     }
 
+    pub fn show_plugin_presets(&self) {
+        match self.graph_presenter().borrow().selected_talker() {
+            Some(talker_id) => plugin_presets::expose(&self.window, &self.session_presenter, talker_id),
+            None => self.display_info_message("Select a plugin to browse its presets."),
+        }
+    }
+
+    pub fn show_plugin_parameters(&self) {
+        let graph_presenter = self.graph_presenter();
+        let selected_talker = graph_presenter.borrow().selected_talker();
+
+        match selected_talker {
+            Some(talker_id) => {
+                // The plugins with their own interface are edited by it
+                let (lv2, native_ui) = match self.session_presenter.borrow().find_talker(talker_id) {
+                    Some(tkr) => (
+                        lv2_handler::visit(|h| Ok(h.world.plugin_by_uri(&tkr.model()).is_some())).unwrap_or(false),
+                        matches!(&*tkr.data().borrow(), Data::UI),
+                    ),
+                    None => (false, false),
+                };
+
+                if !lv2 {
+                    self.display_info_message("Select a LV2 plugin to edit its parameters.");
+                } else if native_ui {
+                    self.display_info_message("This plugin has its own interface to edit its parameters.");
+                } else {
+                    plugin_parameters::expose(&self.window, &self.session_presenter, &graph_presenter, talker_id);
+                }
+            }
+            None => self.display_info_message("Select a plugin to edit its parameters."),
+        }
+    }
+
+    pub fn show_automation(&self) {
+        let graph_presenter = self.graph_presenter();
+        let selected_hum = graph_presenter.borrow().selected_hum();
+
+        match selected_hum {
+            Some(hum) => automation_editor::expose(&self.window, &self.session_presenter, &graph_presenter, hum),
+            None => self.display_info_message("Select an ear input to edit its automation."),
+        }
+    }
+
+    pub fn show_midi_learn(&self) {
+        let graph_presenter = self.graph_presenter();
+        let selected_hum = graph_presenter.borrow().selected_hum();
+
+        match selected_hum {
+            Some(hum) => midi_learn::expose(&self.window, &self.session_presenter, &graph_presenter, hum),
+            None => self.display_info_message("Select an ear input to map it to a MIDI controller."),
+        }
+    }
+
     pub fn duplicate_selected_talkers(&self) {
         self.graph_presenter().borrow().duplicate_selected_talkers();
     }
@@ -452,7 +517,8 @@ impl ApplicationView {
                     obs.borrow().hide_message();
                     obs.borrow().window.set_title(Some(name));
                 }
-                Notification::Tick(tick) => println!("Todo : Applicationview.set_tick {}", tick),
+                Notification::Tick(tick) => obs.borrow().timeline_view.set_tick(*tick),
+                Notification::Tempo(tempo) => obs.borrow().timeline_view.set_tempo(*tempo),
                 Notification::TimeRange(st, et) => {
                     println!("Todo : Applicationview.set_time_range {} <-> {}", st, et)
                 }
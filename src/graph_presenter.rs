@@ -9,6 +9,7 @@ use talker::talker::RTalker;
 
 use session::band::{EarHum, Operation};
 use session::event_bus::{Notification, REventBus};
+use session::meter::MixerLevels;
 use session::mixer;
 
 use crate::session_presenter::RSessionPresenter;
@@ -25,6 +26,7 @@ pub struct GraphPresenter {
     multi_selection: bool,
     solo_track: Option<(Id, Index)>,
     mute_tracks: HashMap<Id, HashSet<Index>>,
+    mixers_levels: HashMap<Id, MixerLevels>,
     session_presenter: RSessionPresenter,
     event_bus: REventBus,
 }
@@ -51,6 +53,7 @@ impl GraphPresenter {
             multi_selection: false,
             solo_track: None,
             mute_tracks,
+            mixers_levels: HashMap::new(),
             session_presenter: session_presenter.clone(),
             event_bus: event_bus.clone(),
         }))
@@ -67,6 +70,7 @@ impl GraphPresenter {
         self.multi_selection = false;
         self.solo_track = None;
         self.mute_tracks.clear();
+        self.mixers_levels.clear();
 
         for mxr_id in self.session_presenter.borrow().mixers().keys() {
             self.mute_tracks.insert(*mxr_id, HashSet::new());
@@ -77,10 +81,30 @@ impl GraphPresenter {
         self.session_presenter.borrow().find_talker(talker_id).unwrap().clone()
     }
 
+    pub fn set_mixers_levels(&mut self, mixers_levels: &Vec<MixerLevels>) {
+        self.mixers_levels.clear();
+
+        for levels in mixers_levels {
+            self.mixers_levels.insert(levels.mixer_id, levels.clone());
+        }
+    }
+
+    pub fn mixer_levels(&self, mixer_id: Id) -> Option<&MixerLevels> {
+        self.mixers_levels.get(&mixer_id)
+    }
+
     pub fn talker_selected(&self, talker_id: Id) -> bool {
         self.selected_talkers.contains(&talker_id)
     }
 
+    pub fn selected_talker(&self) -> Option<Id> {
+        self.selected_talkers.iter().next().cloned()
+    }
+
+    pub fn selected_hum(&self) -> Option<(Id, Index, Index, Index)> {
+        self.selected_hum
+    }
+
     pub fn selected_data_talker(&self) -> Option<Id> {
         self.selected_data_talker
     }
@@ -15,6 +15,7 @@ use talker::talker::RTalker;
 
 use session::event_bus::{Notification, REventBus};
 use session::mixer::Mixer;
+use session::talkers::automation;
 
 use crate::graph_control::{GraphControl, RGraphControl};
 use crate::graph_presenter::{GraphPresenter, RGraphPresenter};
@@ -110,6 +111,7 @@ pub struct GraphView {
     graph_control: RGraphControl,
     graph_presenter: RGraphPresenter,
     drawing_area: gtk::DrawingArea,
+    meters_area: gtk::DrawingArea,
     graph_view_scrolledwindow: gtk::ScrolledWindow,
     talker_controls: HashMap<Id, RTalkerControl>,
     width: f64,
@@ -131,6 +133,18 @@ impl GraphView {
             .valign(gtk::Align::Start)
             .build();
 
+        // Transparent area over the graph where only the mixers meters are drawn
+        let meters_area = gtk::DrawingArea::builder()
+            .can_target(false)
+            .build();
+
+        let graph_overlay = gtk::Overlay::builder()
+            .child(&drawing_area)
+            .halign(gtk::Align::Start)
+            .valign(gtk::Align::Start)
+            .build();
+        graph_overlay.add_overlay(&meters_area);
+
         let popover = gtk::Popover::builder()
             .has_arrow(false)
             .autohide(true)
@@ -144,7 +158,7 @@ impl GraphView {
             .spacing(0)
             .css_classes(["graphview_area"])
             .build();
-        area.append(&drawing_area);
+        area.append(&graph_overlay);
         area.append(&popover);
 
         let graph_view_scrolledwindow = gtk::ScrolledWindow::builder()
@@ -158,6 +172,7 @@ impl GraphView {
             graph_control: GraphControl::new_ref(window, &graph_presenter, event_bus),
             graph_presenter,
             drawing_area,
+            meters_area,
             graph_view_scrolledwindow,
             talker_controls: HashMap::new(),
             width: 0.,
@@ -190,6 +205,9 @@ impl GraphView {
 
         let gv_drawer = rgraphview.clone();
         drawing_area.set_draw_func(move |w, cc, _, _| gv_drawer.borrow_mut().on_draw(w, cc));
+
+        let gv_meters_drawer = rgraphview.clone();
+        rgraphview.borrow().meters_area.set_draw_func(move |_, cc, _, _| gv_meters_drawer.borrow().on_draw_meters(cc));
     }
 
     pub fn init(&mut self) {
@@ -200,10 +218,16 @@ impl GraphView {
     pub fn draw(&mut self) {
         self.build_needed = true;
         self.drawing_area.queue_draw();
+        self.meters_area.queue_draw();
     }
 
     pub fn refresh(&self) {
         self.drawing_area.queue_draw();
+        self.meters_area.queue_draw();
+    }
+
+    pub fn refresh_meters(&self) {
+        self.meters_area.queue_draw();
     }
 
     pub fn graph_presenter(&self) -> RGraphPresenter {
@@ -287,7 +311,7 @@ impl GraphView {
         collector: &mut Collector,
     ) -> Result<(), failure::Error> {
         // Create TalkerControls and define their row and column
-        if talker.is_hidden() {
+        if talker.is_hidden() || talker.model() == Mixer::kind() || talker.model() == automation::MODEL {
             return Ok(());
         } else if let Some(exclude_talkers_ids) = &collector.exclude_talkers_ids {
             if exclude_talkers_ids.contains(&talker.id()) {
@@ -305,6 +329,10 @@ impl GraphView {
         talker: &RTalker,
         collector: &mut Collector,
     ) -> Result<(), failure::Error> {
+        // A mixer is reached through its buses and stays in the first column
+        if talker.model() == Mixer::kind() {
+            return Ok(());
+        }
         let id = talker.id();
         
         if let Some(rtkrc) = &collector.talker_controls.get_mut(&id) {
@@ -338,7 +366,7 @@ impl GraphView {
                     for ear in talker.ears() {
                         ear.iter_talkers(
                             |dep, deps| {
-                                if dep.is_hidden() {
+                                if dep.is_hidden() || dep.model() == automation::MODEL {
                                     Ok(())
                                 } else {
                                     deps.insert(dep.id());
@@ -373,7 +401,7 @@ impl GraphView {
         collector: &mut Collector,
     ) -> Result<(), failure::Error> {
         // Create TalkerControls and define their column
-        if talker.is_hidden() {
+        if talker.is_hidden() || talker.model() == Mixer::kind() || talker.model() == automation::MODEL {
             return Ok(());
         } else if let Some(exclude_talkers_ids) = &collector.exclude_talkers_ids {
             if exclude_talkers_ids.contains(&talker.id()) {
@@ -408,6 +436,9 @@ impl GraphView {
         collector: &mut Collector,
     ) -> Result<(), failure::Error> {
         // Define TalkerControls row
+        if talker.model() == Mixer::kind() {
+            return Ok(());
+        }
         let id = talker.id();
         
         if let Some(rtkrc) = &collector.talker_controls.get_mut(&id) {
@@ -437,7 +468,7 @@ impl GraphView {
                     for ear in talker.ears() {
                         ear.iter_talkers(
                             |dep, deps| {
-                                if dep.is_hidden() {
+                                if dep.is_hidden() || dep.model() == automation::MODEL {
                                     Ok(())
                                 } else {
                                     deps.insert(dep.id());
@@ -514,7 +545,7 @@ impl GraphView {
                 HashSet::with_capacity(collector.talker_controls.len());
 
             for (id, tkr) in session.talkers() {
-                if !tkr.is_hidden() && tkr.model() != Mixer::kind() {
+                if !tkr.is_hidden() && tkr.model() != Mixer::kind() && tkr.model() != automation::MODEL {
                     if collector.talker_controls.contains_key(id) {
                         used_talkers.insert(*id);
                     } else {
@@ -594,6 +625,7 @@ impl GraphView {
                     self.talker_controls = talker_controls;
 
                     drawing_area.set_size_request(self.width as i32, self.height as i32);
+                    self.meters_area.queue_draw();
                     self.build_needed = false;
                 }
                 Err(e) => eprintln!("{}", e),
@@ -621,6 +653,12 @@ impl GraphView {
         }
     }
 
+    fn on_draw_meters(&self, cc: &Context) {
+        for tkrc in self.talker_controls.values() {
+            tkrc.borrow().draw_meters(cc, &self.graph_presenter.borrow());
+        }
+    }
+
     fn observe(observer: &RGraphView, bus: &REventBus) {
         let obs = observer.clone();
 
@@ -629,6 +667,10 @@ impl GraphView {
                 Notification::SelectionChanged => obs.borrow_mut().refresh(),
                 Notification::NewSession(_) => obs.borrow_mut().init(),
                 Notification::TalkerChanged | Notification::NewTalker => obs.borrow_mut().draw(),
+                Notification::Levels(levels) => {
+                    obs.borrow().graph_presenter.borrow_mut().set_mixers_levels(levels);
+                    obs.borrow().refresh_meters();
+                }
                 _ => (),
             }))
     }
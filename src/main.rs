@@ -27,6 +27,7 @@ mod settings;
 mod talker_control;
 mod talker_data_view;
 mod talkers_list_view;
+mod timeline_view;
 mod ui;
 mod undo_redo_list;
 mod util;
@@ -35,6 +36,7 @@ use application_view::ApplicationView;
 use session_presenter::SessionPresenter;
 
 fn main() {
+    session::plugins_scan::probe_on_request();
 
     let application =
         gtk::Application::new(Some("com.gitlab.gndl.graffophone"), Default::default());
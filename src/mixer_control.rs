@@ -19,6 +19,7 @@ use std::rc::Rc;
 
 use cairo::Context;
 
+use talker::audio_format::AudioFormat;
 use talker::identifier::Id;
 
 use session::event_bus::Notification;
@@ -30,14 +31,20 @@ use crate::ui::{self, control::{Area, ControlSupply}};
 use crate::util;
 
 
+const METER_WIDTH: f64 = 60.;
+const LOUDNESS_TEMPLATE: &str = "-00.0 LUFS  00.0 ms";
+
 struct SoloMuteControl {
     solo_area: Area,
     mute_area: Area,
+    meter_area: Area,
 }
 
 pub struct MixerControl {
     base: RTalkerControlBase,
     solo_mutes: Vec<SoloMuteControl>,
+    master_meter_area: Option<Area>,
+    loudness_area: Option<Area>,
 }
 
 impl MixerControl {
@@ -46,6 +53,8 @@ impl MixerControl {
         control_supply: &ControlSupply,
     ) -> Result<MixerControl, failure::Error> {
         let mut solo_mutes = Vec::new();
+        let mut master_meter_area = None;
+        let mut loudness_area = None;
 
         let mixer = rmixer.borrow();
         let base =
@@ -64,10 +73,26 @@ impl MixerControl {
 
                         solo_area.farthest_right_adjustment(&mut mute_area);
 
-                        let ctrl = SoloMuteControl {solo_area, mute_area};
+                        let meter_b_x = solo_area.e_x.max(mute_area.e_x) + ui::control::SPACE;
+                        let meter_area = Area::new(meter_b_x, meter_b_x + METER_WIDTH, solo_area.b_y, mute_area.e_y);
+
+                        let ctrl = SoloMuteControl {solo_area, mute_area, meter_area};
 
                         solo_mutes.push(ctrl);
-                        (solo_area.e_x.max(mute_area.e_x), mute_area.e_y)
+                        (meter_area.e_x, mute_area.e_y)
+                    }
+                    else if ear_idx == mixer::VOLUME_EAR_INDEX {
+                        let solo_area = ui::control::dim_to_area(b_x, b_y, &control_supply.solo_dim);
+                        let meter_area = Area::new(b_x, b_x + METER_WIDTH, solo_area.b_y, solo_area.e_y);
+                        master_meter_area = Some(meter_area);
+
+                        match control_supply.area_of(LOUDNESS_TEMPLATE, meter_area.e_x + ui::control::SPACE, b_y) {
+                            Ok(area) => {
+                                loudness_area = Some(area);
+                                (area.e_x, area.e_y)
+                            }
+                            Err(_) => (meter_area.e_x, meter_area.e_y),
+                        }
                     }
                     else {
                         (b_x, b_y)
@@ -75,7 +100,7 @@ impl MixerControl {
                 }
             )?;
 
-        Ok(Self { base, solo_mutes })
+        Ok(Self { base, solo_mutes, master_meter_area, loudness_area })
     }
     pub fn new_ref(
         mixer: &RMixer,
@@ -87,6 +112,33 @@ impl MixerControl {
         )?)))
     }
 
+    fn draw_levels(&self, cc: &Context, graph_presenter: &GraphPresenter) -> Result<(), cairo::Error> {
+        let base = self.base.borrow();
+        let no_levels = Vec::new();
+        let olevels = graph_presenter.mixer_levels(base.id());
+
+        if let Some(meter_area) = &self.master_meter_area {
+            let channels = olevels.map_or(&no_levels, |l| &l.master.channels);
+            base.draw_meter(cc, meter_area, channels)?;
+        }
+
+        if let (Some(area), Some(levels)) = (&self.loudness_area, olevels) {
+            let loudness = if levels.latency > 0 {
+                let latency_ms = levels.latency as f32 * 1000. / AudioFormat::sample_rate() as f32;
+                format!("{:.1} LUFS  {:.1} ms", levels.master.short_term, latency_ms)
+            } else {
+                format!("{:.1} LUFS", levels.master.short_term)
+            };
+            base.draw_control(cc, area, &loudness, ui::style::value, ui::style::value, ui::style::background, false)?;
+        }
+
+        for (trk_idx, ctrls) in self.solo_mutes.iter().enumerate() {
+            let channels = olevels.and_then(|l| l.tracks.get(trk_idx)).map_or(&no_levels, |t| &t.channels);
+            base.draw_meter(cc, &ctrls.meter_area, channels)?;
+        }
+        Ok(())
+    }
+
     fn draw_solo_mutes(&self, cc: &Context, graph_presenter: &GraphPresenter) -> Result<(), cairo::Error> {
         let base = self.base.borrow();
         let tkr_id = base.id();
@@ -150,6 +202,10 @@ impl TalkerControl for MixerControl {
         util::print_cairo_result(self.draw_solo_mutes(cc, graph_presenter));
     }
 
+    fn draw_meters(&self, cc: &Context, graph_presenter: &GraphPresenter) {
+        util::print_cairo_result(self.draw_levels(cc, graph_presenter));
+    }
+
     fn move_to(&mut self, x: f64, y: f64) {
         self.base().borrow_mut().move_to(x, y);
     }
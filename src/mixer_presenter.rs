@@ -1,13 +1,19 @@
 
 use talker::identifier::{Id, RIdentifier};
 
-use session::mixer::RMixer;
+use session::mixer::{Clipping, Configuration, RMixer, Summing};
+use session::panner::Panning;
 
 use crate::output_presenter::OutputPresenter;
 
+pub const SUMMINGS_LABELS: [&str; 3] = ["Sum", "1/N", "1/√N"];
+pub const CLIPPINGS_LABELS: [&str; 3] = ["None", "Tanh", "Atan"];
+pub const PANNINGS_LABELS: [&str; 3] = ["None", "Constant power", "VBAP"];
+
 pub struct MixerPresenter {
     identifier: RIdentifier,
     outputs: Vec<OutputPresenter>,
+    configuration: Configuration,
 }
 
 impl MixerPresenter {
@@ -23,6 +29,7 @@ impl MixerPresenter {
         Self {
             identifier,
             outputs,
+            configuration: *mxr.configuration(),
         }
     }
 
@@ -34,6 +41,26 @@ impl MixerPresenter {
         self.identifier.borrow().name().to_string()
     }
 
+    pub fn configuration(&self) -> &Configuration {
+        &self.configuration
+    }
+
+    pub fn set_buses(&mut self, buses: usize) {
+        self.configuration.buses = buses;
+    }
+
+    pub fn set_summing(&mut self, summing: Summing) {
+        self.configuration.summing = summing;
+    }
+
+    pub fn set_clipping(&mut self, clipping: Clipping) {
+        self.configuration.clipping = clipping;
+    }
+
+    pub fn set_panning(&mut self, panning: Panning) {
+        self.configuration.panning = panning;
+    }
+
     pub fn outputs(&self) -> &Vec<OutputPresenter> {
         &self.outputs
     }
@@ -1,6 +1,7 @@
 
+use audiofile::writer::{Dither, SampleFormat};
 use talker::identifier::{Id, Identifier};
-use session::{channel, output::ROutput};
+use session::{audiofile_output::Tags, channel, output::ROutput};
 
 pub const DEFAULT_CODEC: &str = "flac";
 pub const CODECS_LABELS: [&str; 6] = ["FLAC", "MP3", "Ogg Vorbis", "Opus", "WAV 16-bit", "WAV 24-bit"];
@@ -10,6 +11,9 @@ pub const CODEC_CONTAINERS_EXTENTIONS: [&str; 6] = [DEFAULT_CODEC, "mp3", "ogg",
 pub const DEFAULT_SAMPLE_RATE: usize = 44100;
 pub const SAMPLE_RATES: [&str; 9] = ["8000", "11025", "16000", "22050", "32000", "44100", "48000", "88200", "96000"];
 
+pub const SAMPLE_FORMATS_LABELS: [&str; 5] = ["Codec default", "16-bit", "24-bit", "32-bit", "32-bit float"];
+pub const DITHERS_LABELS: [&str; 3] = ["No dither", "TPDF dither", "Noise shaped dither"];
+
 pub const DEFAULT_AUDIO_FILE_EXTENTION: &str = DEFAULT_CODEC;
 
 pub struct OutputPresenter {
@@ -18,6 +22,10 @@ pub struct OutputPresenter {
     sample_rate: usize,
     channel_layout: String,
     file_path: String,
+    stems: bool,
+    sample_format: SampleFormat,
+    dither: Dither,
+    tags: Tags,
 }
 
 impl OutputPresenter {
@@ -27,6 +35,10 @@ impl OutputPresenter {
         sample_rate: usize,
         channel_layout: &str,
         file_path: &str,
+        stems: bool,
+        sample_format: SampleFormat,
+        dither: Dither,
+        tags: Tags,
     ) -> OutputPresenter {
         Self {
             identifier,
@@ -34,6 +46,10 @@ impl OutputPresenter {
             sample_rate,
             channel_layout: channel_layout.to_string(),
             file_path: file_path.to_string(),
+            stems,
+            sample_format,
+            dither,
+            tags,
         }
     }
             
@@ -44,7 +60,11 @@ impl OutputPresenter {
         let sample_rate = out.sample_rate();
         let channel_layout = out.channel_layout();
         let file_path = out.file_path();
-        OutputPresenter::new(identifier, codec_name, sample_rate, channel_layout, file_path)
+        let stems = out.stems();
+        let sample_format = out.sample_format();
+        let dither = out.dither();
+        let tags = out.tags();
+        OutputPresenter::new(identifier, codec_name, sample_rate, channel_layout, file_path, stems, sample_format, dither, tags)
     }
 
     pub fn identifier(&self) -> &Identifier {
@@ -113,4 +133,36 @@ impl OutputPresenter {
     pub fn set_file_path(&mut self, value: &str) {
         self.file_path = value.to_string();
     }
-}
\ No newline at end of file
+
+    pub fn stems(&self) -> bool {
+        self.stems
+    }
+
+    pub fn set_stems(&mut self, value: bool) {
+        self.stems = value;
+    }
+
+    pub fn sample_format(&self) -> SampleFormat {
+        self.sample_format
+    }
+
+    pub fn set_sample_format(&mut self, value: SampleFormat) {
+        self.sample_format = value;
+    }
+
+    pub fn dither(&self) -> Dither {
+        self.dither
+    }
+
+    pub fn set_dither(&mut self, value: Dither) {
+        self.dither = value;
+    }
+
+    pub fn tags(&self) -> &Tags {
+        &self.tags
+    }
+
+    pub fn mutable_tags(&mut self) -> &mut Tags {
+        &mut self.tags
+    }
+}
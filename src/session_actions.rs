@@ -25,6 +25,10 @@ pub const DUPLIATE_SELECTED_TALKERS_ACCEL: &str = "<Ctrl>D";
 pub const FIND_FORWARD_ACCEL: &str = "<Ctrl>F";
 pub const FIND_BACKWARD_ACCEL: &str = "<Ctrl><Shift>F";
 pub const TOGGLE_TALKERS_FACE_ACCEL: &str = "<Ctrl>M";
+pub const PLUGIN_PRESETS_ACCEL: &str = "<Ctrl><Shift>P";
+pub const PLUGIN_PARAMETERS_ACCEL: &str = "<Ctrl>E";
+pub const AUTOMATION_ACCEL: &str = "<Ctrl>L";
+pub const MIDI_LEARN_ACCEL: &str = "<Ctrl>K";
 
 pub fn create_actions_entries(
     application: &gtk::Application,
@@ -223,6 +227,42 @@ pub fn create_actions_entries(
 
     application.set_accels_for_action("session.toggle_talkers_face", &[TOGGLE_TALKERS_FACE_ACCEL]);
 
+    // Plugin presets action
+    let plugin_presets = ActionEntry::builder("plugin_presets")
+    .activate(clone!(#[strong] view, move |_: &SimpleActionGroup, _, _| view.borrow().show_plugin_presets()))
+    .build();
+
+    entries.push(plugin_presets);
+
+    application.set_accels_for_action("session.plugin_presets", &[PLUGIN_PRESETS_ACCEL]);
+
+    // Plugin parameters action
+    let plugin_parameters = ActionEntry::builder("plugin_parameters")
+    .activate(clone!(#[strong] view, move |_: &SimpleActionGroup, _, _| view.borrow().show_plugin_parameters()))
+    .build();
+
+    entries.push(plugin_parameters);
+
+    application.set_accels_for_action("session.plugin_parameters", &[PLUGIN_PARAMETERS_ACCEL]);
+
+    // Automation lane action
+    let automation = ActionEntry::builder("automation")
+    .activate(clone!(#[strong] view, move |_: &SimpleActionGroup, _, _| view.borrow().show_automation()))
+    .build();
+
+    entries.push(automation);
+
+    application.set_accels_for_action("session.automation", &[AUTOMATION_ACCEL]);
+
+    // MIDI learn action
+    let midi_learn = ActionEntry::builder("midi_learn")
+    .activate(clone!(#[strong] view, move |_: &SimpleActionGroup, _, _| view.borrow().show_midi_learn()))
+    .build();
+
+    entries.push(midi_learn);
+
+    application.set_accels_for_action("session.midi_learn", &[MIDI_LEARN_ACCEL]);
+
 
     let actions = SimpleActionGroup::new();
     actions.add_action_entries(entries);
@@ -1,29 +1,42 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::str::FromStr;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use gtk::glib;
 
 use luil::ui_connector::UiConnector;
 
+use audiofile::writer::{Dither, SampleFormat};
 use ::session::channel;
-use talker::identifier::{self, Id, Index};
+use talker::identifier::{self, Id, Identifiable, Index};
 use talker::talker::RTalker;
+use talker::lv2_handler::ControlInput;
 use talker::Identifier;
 
+use crate::session::audiofile_output::Tags;
 use crate::session::band::Operation;
 use crate::session::event_bus::{Notification, REventBus};
 use crate::session::factory::{Factory, OutputParam};
+use crate::session::meter::Metering;
+use crate::session::midi_mapping::MidiMapping;
 use crate::session::mixer::{self, RMixer};
+use crate::session::osc_server::{Command, OscServer};
+use crate::session::panner::Panning;
+use crate::session::player::Report;
 use crate::session::session::{self, Session};
+use crate::session::settings::{FeedbackSettings, LoopSettings, MidiSettings};
 use crate::session::state::State;
+use crate::session::talkers::automation::{self, Breakpoint};
 
 use crate::mixer_presenter::MixerPresenter;
 use crate::output_presenter::{self, OutputPresenter};
 use crate::undo_redo_list::UndoRedoList;
 use crate::util;
 
+const METERING_PERIOD: u64 = 50;
+const OSC_PERIOD: u64 = 20;
+
 const GSR: &str = "
 Sinusoidal 2#G Sinusoidal
 >0<440
@@ -38,13 +51,29 @@ Mixer 1#Mixer
 >1.0.3.0<1
 ";
 
+// Talker id, ear, set and hum indexes
+pub type HumKey = (Id, Index, Index, Index);
+
+// Breakpoints recorded on a hum automation during a recording
+struct AutomationTake {
+    automation_id: Id,
+    lane: Vec<Breakpoint>,
+    recorded: Vec<Breakpoint>,
+}
+
 pub struct SessionPresenter {
     session: Session,
     state: State,
+    tick: i64,
+    automation_armed_hums: HashSet<HumKey>,
+    automation_takes: HashMap<HumKey, AutomationTake>,
+    midi_learning_hum: Option<HumKey>,
+    midi_monitored: bool,
     modified: bool,
     mixers_presenters: Vec<MixerPresenter>,
     undo_redo_list: UndoRedoList,
     ui_count: usize,
+    loudness_summary_pending: bool,
     event_bus: REventBus,
 }
 pub type RSessionPresenter = Rc<RefCell<SessionPresenter>>;
@@ -55,26 +84,38 @@ impl SessionPresenter {
         let state = session.state();
         let undo_redo_list = UndoRedoList::new(GSR.to_string());
 
-        Rc::new(RefCell::new(Self {
+        let session_presenter = Rc::new(RefCell::new(Self {
             session,
             state,
+            tick: 0,
+            automation_armed_hums: HashSet::new(),
+            automation_takes: HashMap::new(),
+            midi_learning_hum: None,
+            midi_monitored: false,
             modified: false,
             mixers_presenters: Vec::new(),
             undo_redo_list,
             ui_count: 0,
+            loudness_summary_pending: false,
             event_bus: event_bus.clone(),
-        }))
+        }));
+
+        SessionPresenter::start_osc_server(&session_presenter);
+        session_presenter.borrow_mut().monitor_midi(&session_presenter);
+        session_presenter
     }
 
     pub fn new_session(session_presenter: &RSessionPresenter) {
         session_presenter.borrow_mut().exit();
         session_presenter.borrow_mut().receive_new_session(Session::new(GSR.to_string()));
+        session_presenter.borrow_mut().monitor_midi(session_presenter);
         session_presenter.borrow().notify_new_session();
     }
 
     pub fn open_session(session_presenter: &RSessionPresenter, filename: &str) {
         session_presenter.borrow_mut().exit();
         session_presenter.borrow_mut().receive_new_session(Session::from_file(filename));
+        session_presenter.borrow_mut().monitor_midi(session_presenter);
         session_presenter.borrow().notify_new_session();
     }
 
@@ -120,6 +161,7 @@ impl SessionPresenter {
         self.event_bus.borrow().notify(Notification::NewSession(
             self.session.filename().to_string(),
         ));
+        self.event_bus.borrow().notify(Notification::Tempo(self.session.tempo()));
     }
 
     pub fn notify_error(&self, error: failure::Error) {
@@ -140,6 +182,9 @@ impl SessionPresenter {
     fn manage_state(&mut self, state: State) {
 
         if state != self.state {
+            if self.state == State::Recording {
+                self.commit_automation_takes();
+            }
             self.event_bus.borrow().notify(Notification::State(state));
             self.state = state;
         }
@@ -178,6 +223,21 @@ impl SessionPresenter {
         self.notify_new_session();
     }
 
+    pub fn disabled_plugins(&self) -> Vec<(String, String, String)> {
+        match Factory::visit(|factory| Ok(factory.disabled_plugins())) {
+            Ok(disabled_plugins) => disabled_plugins,
+            Err(e) => {
+                self.event_bus.borrow().notify_error(e);
+                Vec::new()
+            }
+        }
+    }
+
+    pub fn forget_disabled_plugins(&self) {
+        let res = Factory::visit(|factory| factory.forget_disabled_plugins());
+        self.manage_result(res, Some(Notification::Info("The disabled plugins will be probed again at the next start.".to_string())));
+    }
+
     pub fn sample_rate(&self) -> usize {
         self.session.sample_rate()
     }
@@ -186,6 +246,31 @@ impl SessionPresenter {
         self.manage_state_result(res);
     }
 
+    pub fn feedback_settings(&self) -> FeedbackSettings {
+        self.session.feedback_settings().clone()
+    }
+    pub fn set_feedback_settings(&mut self, feedback_settings: FeedbackSettings) {
+        let res = self.session.set_feedback_settings(feedback_settings);
+        self.manage_state_result(res);
+    }
+
+    pub fn midi_settings(&self) -> MidiSettings {
+        self.session.midi_settings().clone()
+    }
+    pub fn set_midi_settings(&mut self, midi_settings: MidiSettings, monitor: &RSessionPresenter) {
+        let res = self.session.set_midi_settings(midi_settings);
+        self.manage_result(res, None);
+        self.monitor_midi(monitor);
+    }
+
+    pub fn loop_settings(&self) -> LoopSettings {
+        self.session.loop_settings().clone()
+    }
+    pub fn set_loop_settings(&mut self, loop_settings: LoopSettings) {
+        let res = self.session.set_loop_settings(loop_settings);
+        self.manage_state_result(res);
+    }
+
     pub fn mixers(&self) -> &HashMap<u32, RMixer> {
         self.session.mixers()
     }
@@ -216,6 +301,46 @@ impl SessionPresenter {
         }
     }
 
+    pub fn lv2_presets(&self, talker_id: Id) -> Vec<(String, String)> {
+        match self.session.lv2_presets(talker_id) {
+            Ok(presets) => presets,
+            Err(e) => {
+                self.event_bus.borrow().notify_error(e);
+                Vec::new()
+            }
+        }
+    }
+
+    pub fn lv2_control_inputs(&self, talker_id: Id) -> Option<Vec<ControlInput>> {
+        match self.session.lv2_control_inputs(talker_id) {
+            Ok(control_inputs) => Some(control_inputs),
+            Err(e) => {
+                self.event_bus.borrow().notify_error(e);
+                None
+            }
+        }
+    }
+
+    pub fn set_lv2_preset(&mut self, talker_id: Id, preset_uri: &str) {
+
+        if self.modify_band(&Operation::SetLv2Preset(talker_id, preset_uri.to_string())) {
+            self.event_bus.borrow().notify(Notification::TalkerChanged);
+        }
+    }
+
+    pub fn save_lv2_preset(&self, talker_id: Id, name: &str) -> bool {
+        match self.session.save_lv2_preset(talker_id, name) {
+            Ok(_) => {
+                self.event_bus.borrow().notify(Notification::Info(format!("Preset {} saved.", name)));
+                true
+            }
+            Err(e) => {
+                self.event_bus.borrow().notify_error(e);
+                false
+            }
+        }
+    }
+
     pub fn find_compatible_hum_with_voice_in_ear(
         &self,
         _talker_id: Id,
@@ -228,11 +353,18 @@ impl SessionPresenter {
     }
 
     pub fn modify_band_volatly(&mut self, operation: &Operation) -> bool {
+        if let Some((hum, value)) = self.automation_take_value(operation) {
+            return self.record_automation(hum, value);
+        }
         let res = self.session.modify_band(operation);
         self.manage_state_result(res)
     }
 
     pub fn modify_band(&mut self, operation: &Operation) -> bool {
+        // The automation takes enter the undo list at the end of the recording
+        if self.automation_take_value(operation).is_some() {
+            return self.modify_band_volatly(operation);
+        }
         let state_ok = self.modify_band_volatly(operation);
 
         if state_ok {
@@ -246,6 +378,135 @@ impl SessionPresenter {
         state_ok
     }
 
+    pub fn is_automation_armed(&self, hum: HumKey) -> bool {
+        self.automation_armed_hums.contains(&hum)
+    }
+
+    // The value changes of the armed hums are recorded in their automation
+    pub fn arm_automation(&mut self, hum: HumKey, armed: bool) {
+        if armed {
+            self.automation_armed_hums.insert(hum);
+        } else {
+            self.automation_armed_hums.remove(&hum);
+        }
+    }
+
+    pub fn automation(&self, (talker_id, ear_idx, set_idx, hum_idx): HumKey) -> Option<Vec<Breakpoint>> {
+        match self.session.automation(talker_id, ear_idx, set_idx, hum_idx) {
+            Ok(opoints) => opoints,
+            Err(e) => {
+                self.event_bus.borrow().notify_error(e);
+                None
+            }
+        }
+    }
+
+    // Without breakpoint, the automation is removed
+    pub fn set_automation(&mut self, (talker_id, ear_idx, set_idx, hum_idx): HumKey, points: Vec<Breakpoint>) {
+        let operation = if points.is_empty() {
+            Operation::SupAutomation(talker_id, ear_idx, set_idx, hum_idx)
+        } else {
+            Operation::SetAutomation(identifier::get_next_id(), talker_id, ear_idx, set_idx, hum_idx, points)
+        };
+
+        if self.modify_band(&operation) {
+            self.event_bus.borrow().notify(Notification::TalkerChanged);
+        }
+    }
+
+    fn automation_take_value(&self, operation: &Operation) -> Option<(HumKey, f32)> {
+        match operation {
+            Operation::SetEarHumValue(talker_id, ear_idx, set_idx, hum_idx, value) if self.state == State::Recording => {
+                let hum = (*talker_id, *ear_idx, *set_idx, *hum_idx);
+
+                if self.automation_armed_hums.contains(&hum) {
+                    Some((hum, *value))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // While recording, the recorded breakpoints replace the lane ones from the first recorded tick
+    fn record_automation(&mut self, hum: HumKey, value: f32) -> bool {
+        if !self.automation_takes.contains_key(&hum) {
+            let take = AutomationTake {
+                automation_id: identifier::get_next_id(),
+                lane: self.automation(hum).unwrap_or_default(),
+                recorded: Vec::new(),
+            };
+            self.automation_takes.insert(hum, take);
+        }
+
+        let tick = self.tick;
+
+        let operation = match self.automation_takes.get_mut(&hum) {
+            Some(take) => {
+                if take.recorded.last().map_or(false, |(t, _)| *t >= tick) {
+                    take.recorded.pop();
+                }
+                take.recorded.push((tick, value));
+
+                let (talker_id, ear_idx, set_idx, hum_idx) = hum;
+                let points = automation::merge(&take.lane, &take.recorded);
+                Operation::SetAutomation(take.automation_id, talker_id, ear_idx, set_idx, hum_idx, points)
+            }
+            None => return false,
+        };
+        let res = self.session.modify_band(&operation);
+        self.manage_state_result(res)
+    }
+
+    fn commit_automation_takes(&mut self) {
+        if !self.automation_takes.is_empty() {
+            self.automation_takes.clear();
+
+            match self.session.serialize_band() {
+                Ok(band_rep) => self.undo_redo_list.new_state(band_rep),
+                Err(e) => self.event_bus.borrow().notify_error(e),
+            }
+            self.modified = true;
+            self.event_bus.borrow().notify(Notification::TalkerChanged);
+        }
+    }
+
+    pub fn midi_mapping(&self, (talker_id, ear_idx, set_idx, hum_idx): HumKey) -> Option<MidiMapping> {
+        self.session.midi_mapping(talker_id, ear_idx, set_idx, hum_idx)
+    }
+
+    pub fn is_midi_learning(&self, hum: HumKey) -> bool {
+        self.midi_learning_hum == Some(hum)
+    }
+
+    // The next MIDI controller moved will drive the mapping hum
+    pub fn learn_midi(&mut self, mapping: MidiMapping, monitor: &RSessionPresenter) {
+        let hum = mapping.hum();
+        let res = self.session.learn_midi(Some(mapping));
+
+        if self.manage_state_result(res) {
+            self.midi_learning_hum = Some(hum);
+            self.monitor_midi(monitor);
+        }
+    }
+
+    pub fn cancel_midi_learning(&mut self) {
+        if self.midi_learning_hum.is_some() {
+            let res = self.session.learn_midi(None);
+            self.manage_state_result(res);
+            self.midi_learning_hum = None;
+        }
+    }
+
+    pub fn set_midi_mapping(&mut self, mapping: MidiMapping) {
+        self.modify_band(&Operation::SetMidiMapping(mapping));
+    }
+
+    pub fn remove_midi_mapping(&mut self, (talker_id, ear_idx, set_idx, hum_idx): HumKey) {
+        self.modify_band(&Operation::SupMidiMapping(talker_id, ear_idx, set_idx, hum_idx));
+    }
+
     pub fn get_mixer_tracks_count(&self, mixer_id: Id) -> Option<usize> {
         if let Some(tkr) = self.find_talker(mixer_id) {
             return Some(tkr.ear(mixer::TRACKS_EAR_INDEX).sets_len());
@@ -258,6 +519,23 @@ impl SessionPresenter {
         self.manage_state_result(res);
     }
 
+    pub fn tempo(&self) -> f32 {
+        self.session.tempo()
+    }
+
+    pub fn set_tempo(&mut self, tempo: f32) {
+        if tempo != self.session.tempo() && self.modify_band(&Operation::SetTempo(tempo)) {
+            self.event_bus.borrow().notify(Notification::Tempo(tempo));
+        }
+    }
+
+    pub fn start_tick(&self) -> i64 {
+        self.session.start_tick()
+    }
+    pub fn end_tick(&self) -> i64 {
+        self.session.end_tick()
+    }
+
     pub fn set_start_tick(&mut self, t: i64) {
         let res = self.session.set_start_tick(t);
         self.manage_state_result(res);
@@ -279,12 +557,218 @@ impl SessionPresenter {
         });
     }
 
+    fn monitor_levels(session_presenter_reference: &RSessionPresenter) {
+        let this = session_presenter_reference.clone();
+        let period = std::time::Duration::from_millis(METERING_PERIOD);
+
+        glib::timeout_add_local(period, move || {
+            SessionPresenter::receive_reports(&this);
+            let session_presenter = this.borrow();
+
+            match session_presenter.state {
+                State::Playing | State::Recording => glib::ControlFlow::Continue,
+                State::Exited => glib::ControlFlow::Break,
+                _ => {
+                    if session_presenter.loudness_summary_pending {
+                        glib::ControlFlow::Continue
+                    } else {
+                        session_presenter.event_bus.borrow().notify(Notification::Levels(Vec::new()));
+                        glib::ControlFlow::Break
+                    }
+                }
+            }
+        });
+    }
+
+    fn is_midi_active(&self) -> bool {
+        self.session.has_midi_input() || self.midi_learning_hum.is_some()
+    }
+
+    // Out of the playback, the MIDI controls modifications are received through the reports.
+    // The monitoring runs while MIDI inputs are connected or a controller is learned
+    fn monitor_midi(&mut self, monitor: &RSessionPresenter) {
+        if self.midi_monitored || !self.is_midi_active() {
+            return;
+        }
+        self.midi_monitored = true;
+
+        let this = monitor.clone();
+        let period = std::time::Duration::from_millis(METERING_PERIOD);
+
+        glib::timeout_add_local(period, move || {
+            let state = {
+                let mut session_presenter = this.borrow_mut();
+
+                if !session_presenter.is_midi_active() {
+                    session_presenter.midi_monitored = false;
+                    return glib::ControlFlow::Break;
+                }
+                session_presenter.state
+            };
+
+            if state != State::Playing && state != State::Recording {
+                SessionPresenter::receive_reports(&this);
+            }
+            glib::ControlFlow::Continue
+        });
+    }
+
+    fn receive_reports(session_presenter_reference: &RSessionPresenter) {
+        let band_modified = session_presenter_reference.borrow_mut().update_levels();
+
+        if band_modified {
+            session_presenter_reference.borrow().notify(Notification::TalkerChanged);
+        }
+    }
+
+    // Returns true if the band was modified by the player
+    fn update_levels(&mut self) -> bool {
+        let mut olevels = None;
+        let mut otick = None;
+        let mut band_modified = false;
+
+        for report in self.session.receive_reports() {
+            match report {
+                Report::Metering(Metering::Levels(levels)) => olevels = Some(levels),
+                Report::Tick(tick) => otick = Some(tick),
+                Report::BandModifications(operations) => {
+                    self.apply_player_modifications(&operations);
+                    band_modified = true;
+                }
+                Report::Metering(Metering::LoudnessSummary(summaries)) => {
+                    self.loudness_summary_pending = false;
+
+                    for summary in summaries {
+                        let name = match self.session.mixers().get(&summary.mixer_id) {
+                            Some(mixer) => mixer.borrow().name(),
+                            None => summary.mixer_id.to_string(),
+                        };
+                        self.event_bus.borrow().notify(Notification::Info(format!(
+                            "{} recording : integrated loudness {:.1} LUFS, true peak {:.1} dBTP",
+                            name, summary.integrated, summary.true_peak
+                        )));
+                    }
+                }
+            }
+        }
+
+        if let Some(levels) = olevels {
+            self.event_bus.borrow().notify(Notification::Levels(levels));
+        }
+        if let Some(tick) = otick {
+            self.tick = tick;
+            self.event_bus.borrow().notify(Notification::Tick(tick));
+        }
+        band_modified
+    }
+
+    // The MIDI controls values don't enter the undo list unlike the learned mappings
+    fn apply_player_modifications(&mut self, operations: &Vec<Operation>) {
+        for operation in operations {
+            if let Some((hum, value)) = self.automation_take_value(operation) {
+                self.record_automation(hum, value);
+                continue;
+            }
+            if let Err(e) = self.session.apply_player_modification(operation) {
+                self.event_bus.borrow().notify_error(e);
+                continue;
+            }
+
+            if let Operation::SetMidiMapping(mapping) = operation {
+                self.midi_learning_hum = None;
+
+                match self.session.serialize_band() {
+                    Ok(band_rep) => self.undo_redo_list.new_state(band_rep),
+                    Err(e) => self.event_bus.borrow().notify_error(e),
+                }
+                self.event_bus.borrow().notify(Notification::Info(format!(
+                    "MIDI controller {} of channel {} learned.", mapping.controller, mapping.channel + 1
+                )));
+            }
+        }
+        self.modified = true;
+    }
+
+    // The OSC commands are run as the UI ones and the transport notifications are sent back to the clients
+    fn start_osc_server(session_presenter_reference: &RSessionPresenter) {
+        let (address, port) = {
+            let this = session_presenter_reference.borrow();
+            let settings = this.session.osc_settings();
+
+            match settings.port {
+                Some(port) => (settings.bind_address().to_string(), port),
+                None => return,
+            }
+        };
+
+        let server = match OscServer::new(&address, port) {
+            Ok(server) => Rc::new(server),
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        };
+
+        let broadcaster = server.clone();
+        session_presenter_reference.borrow().event_bus.borrow_mut()
+            .add_observer(Box::new(move |notification| broadcaster.broadcast(notification)));
+
+        let this = session_presenter_reference.clone();
+        let period = std::time::Duration::from_millis(OSC_PERIOD);
+
+        glib::timeout_add_local(period, move || {
+            for command in server.receive_commands() {
+                SessionPresenter::run_osc_command(&this, command);
+            }
+            glib::ControlFlow::Continue
+        });
+    }
+
+    fn run_osc_command(session_presenter_reference: &RSessionPresenter, command: Command) {
+        let state = session_presenter_reference.borrow().state;
+
+        match command {
+            Command::Play => {
+                if state == State::Paused || state == State::Stopped {
+                    session_presenter_reference.borrow_mut().play_or_pause(session_presenter_reference);
+                }
+            }
+            Command::Pause => {
+                if state == State::Playing {
+                    session_presenter_reference.borrow_mut().play_or_pause(session_presenter_reference);
+                }
+            }
+            Command::Stop => session_presenter_reference.borrow_mut().stop(),
+            Command::Record => {
+                if state == State::Stopped {
+                    session_presenter_reference.borrow_mut().record(session_presenter_reference);
+                }
+            }
+            Command::Seek(tick) => session_presenter_reference.borrow_mut().seek(tick),
+            Command::ModifyBand(operation) => {
+                // Like a control drag, the remote changes don't enter the undo list
+                let changed = {
+                    let mut session_presenter = session_presenter_reference.borrow_mut();
+                    let changed = session_presenter.modify_band_volatly(&operation);
+                    session_presenter.modified |= changed;
+                    changed
+                };
+
+                if changed {
+                    let event_bus = session_presenter_reference.borrow().event_bus.clone();
+                    event_bus.borrow().notify(Notification::TalkerChanged);
+                }
+            }
+        }
+    }
+
     pub fn undo(&mut self) {
         if let Some(bd) = self.undo_redo_list.undo() {
             let res = self.session.load_band(bd);
 
             if self.manage_state_result(res) {
                 self.event_bus.borrow().notify(Notification::TalkerChanged);
+                self.event_bus.borrow().notify(Notification::Tempo(self.session.tempo()));
                 self.modified = true;
             }
         }
@@ -296,6 +780,7 @@ impl SessionPresenter {
 
             if self.manage_state_result(res) {
                 self.event_bus.borrow().notify(Notification::TalkerChanged);
+                self.event_bus.borrow().notify(Notification::Tempo(self.session.tempo()));
                 self.modified = true;
             }
         }
@@ -360,18 +845,36 @@ impl SessionPresenter {
 
         if monitor_state {
             SessionPresenter::monitor_state(monitor);
+            SessionPresenter::monitor_levels(monitor);
         }
     }
 
     pub fn stop(&mut self) {
         let res = self.session.stop();
-        self.manage_state_result(res);
+
+        if self.manage_state_result(res) {
+            self.tick = self.session.start_tick();
+            self.event_bus.borrow().notify(Notification::Tick(self.session.start_tick()));
+        }
+    }
+
+    pub fn seek(&mut self, t: i64) {
+        let res = self.session.seek(t);
+
+        if self.manage_state_result(res) {
+            self.tick = t;
+            self.event_bus.borrow().notify(Notification::Tick(t));
+        }
     }
 
     pub fn record(&mut self, monitor: &RSessionPresenter) {
         let res = self.session.record();
-        self.manage_state_result(res);
+
+        if self.manage_state_result(res) {
+            self.loudness_summary_pending = true;
+        }
         SessionPresenter::monitor_state(monitor);
+        SessionPresenter::monitor_levels(monitor);
     }
 
     pub fn exit(&mut self) {
@@ -451,6 +954,50 @@ impl SessionPresenter {
         self.visite_mutable_mixer_output(mixer_id, output_id, |o| o.set_file_path(value));
     }
 
+    pub fn set_mixer_output_stems(&mut self, mixer_id: Id, output_id: Id, value: bool) {
+        self.visite_mutable_mixer_output(mixer_id, output_id, |o| o.set_stems(value));
+    }
+
+    pub fn set_mixer_output_sample_format(&mut self, mixer_id: Id, output_id: Id, value_index: usize) {
+        let sample_format = SampleFormat::from_index(value_index);
+
+        self.visite_mutable_mixer_output(mixer_id, output_id, |o| o.set_sample_format(sample_format));
+    }
+
+    pub fn set_mixer_output_dither(&mut self, mixer_id: Id, output_id: Id, value_index: usize) {
+        let dither = Dither::from_index(value_index);
+
+        self.visite_mutable_mixer_output(mixer_id, output_id, |o| o.set_dither(dither));
+    }
+
+    pub fn set_mixer_output_tag(&mut self, mixer_id: Id, output_id: Id, key: &str, value: &str) {
+        let mut result = Ok(());
+        self.visite_mutable_mixer_output(mixer_id, output_id, |o| result = o.mutable_tags().set(key, value.to_string()));
+        self.manage_result(result, None);
+    }
+
+    pub fn set_mixer_buses(&mut self, mixer_id: Id, buses: usize) {
+        self.visite_mutable_mixer(mixer_id, |mixer| mixer.set_buses(buses));
+    }
+
+    pub fn set_mixer_summing(&mut self, mixer_id: Id, value_index: usize) {
+        let summing = mixer::Summing::from_index(value_index);
+
+        self.visite_mutable_mixer(mixer_id, |mixer| mixer.set_summing(summing));
+    }
+
+    pub fn set_mixer_clipping(&mut self, mixer_id: Id, value_index: usize) {
+        let clipping = mixer::Clipping::from_index(value_index);
+
+        self.visite_mutable_mixer(mixer_id, |mixer| mixer.set_clipping(clipping));
+    }
+
+    pub fn set_mixer_panning(&mut self, mixer_id: Id, value_index: usize) {
+        let panning = Panning::from_index(value_index);
+
+        self.visite_mutable_mixer(mixer_id, |mixer| mixer.set_panning(panning));
+    }
+
     pub fn default_audiofile_name(&self) -> String {
         util::filename_with_extention(self.session.filename(), output_presenter::DEFAULT_AUDIO_FILE_EXTENTION)
     }
@@ -463,7 +1010,11 @@ impl SessionPresenter {
                 output_presenter::DEFAULT_CODEC,
                 output_presenter::DEFAULT_SAMPLE_RATE,
                 channel::DEFAULT_LAYOUT,
-                file_path.as_str());
+                file_path.as_str(),
+                false,
+                SampleFormat::Auto,
+                Dither::None,
+                Tags::default());
             mixer.add_output(output);
         });
     }
@@ -480,15 +1031,31 @@ impl SessionPresenter {
             let mut outputs_params = Vec::new();
 
             for output in mixer_presenter.outputs() {
+                let mut tags = output.tags().clone();
+                tags.session = self.session.filename().to_string();
+
                 let output_params = OutputParam::File(
                     output.codec_name().to_string(),
                     output.sample_rate(),
                     output.channel_layout().to_string(),
-                    output.file_path().to_string());
+                    output.file_path().to_string(),
+                    output.stems(),
+                    output.sample_format(),
+                    output.dither(),
+                    tags);
 
                 outputs_params.push(output_params);
             }
             operations.push(Operation::SetMixerOutputs(mixer_presenter.id(), outputs_params));
+
+            if let Some(mixer) = self.session.mixers().get(&mixer_presenter.id()) {
+                if mixer.borrow().configuration() != mixer_presenter.configuration() {
+                    operations.push(Operation::SetMixerConfiguration(
+                        mixer_presenter.id(),
+                        *mixer_presenter.configuration(),
+                    ));
+                }
+            }
         }
 
         let mut operations_ok = true;
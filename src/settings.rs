@@ -3,6 +3,7 @@ use gio::{prelude::ApplicationExt, ActionEntry};
 use crate::gio::prelude::ActionMapExtManual;
 
 use crate::session_presenter::RSessionPresenter;
+use crate::ui::disabled_plugins;
 use crate::ui::general_settings;
 use crate::ui::session_settings;
 
@@ -22,6 +23,11 @@ pub fn create_actions_entries(app: &gtk::Application, session_presenter: &RSessi
     .activate(move |app, _, _| session_settings::expose(app, &ossp))
     .build();
 
+    let dssp = session_presenter.clone();
+    let disabled_plugins = ActionEntry::builder("disabled_plugins")
+    .activate(move |app, _, _| disabled_plugins::expose(app, &dssp))
+    .build();
+
     let about = ActionEntry::builder("about")
     .activate(|_, _, _| println!("About was pressed"))
     .build();
@@ -30,7 +36,7 @@ pub fn create_actions_entries(app: &gtk::Application, session_presenter: &RSessi
     .activate(|app: &gtk::Application, _, _| app.quit())
     .build();
 
-    app.add_action_entries([toggle_feedback, general_settings, session_settings, about, quit]);
+    app.add_action_entries([toggle_feedback, general_settings, session_settings, disabled_plugins, about, quit]);
 }
 
 pub fn menu() -> gio::Menu {
@@ -39,6 +45,7 @@ pub fn menu() -> gio::Menu {
     // TODO : menu.append(Some("Feedback"), Some("app.toggle_feedback"));
     menu.append(Some("General settings"), Some("app.general_settings"));
     menu.append(Some("Session settings"), Some("app.session_settings"));
+    menu.append(Some("Disabled plugins"), Some("app.disabled_plugins"));
 
     menu
 }
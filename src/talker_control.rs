@@ -13,6 +13,7 @@ use talker::talker::RTalker;
 use crate::graph_presenter::{GraphPresenter, RGraphPresenter};
 use crate::util;
 use session::event_bus::Notification;
+use session::meter::{self, ChannelLevels};
 use crate::ui::{self, control::{Area, ControlSupply}, style::Color};
 
 struct HumControl {
@@ -448,6 +449,46 @@ impl TalkerControlBase {
         self.y = y;
     }
 
+    // Draw a horizontal bar per channel, the RMS filled and the peak marked, in a dB scale
+    pub fn draw_meter(
+        &self,
+        cc: &Context,
+        area: &Area,
+        channels: &Vec<ChannelLevels>,
+    ) -> Result<(), cairo::Error> {
+        let w = area.e_x - area.b_x;
+        let h = area.e_y - area.b_y;
+
+        ui::style::background(cc);
+        cc.rectangle(self.x + area.b_x, self.y + area.b_y, w, h);
+        cc.fill()?;
+
+        if channels.is_empty() {
+            return Ok(());
+        }
+        let bar_h = h / channels.len() as f64;
+        let ratio = |db: f32| ((db - meter::MIN_DB) / -meter::MIN_DB).max(0.).min(1.) as f64;
+
+        for (idx, channel) in channels.iter().enumerate() {
+            let y = self.y + area.b_y + bar_h * idx as f64;
+
+            if channel.true_peak > 0. {
+                ui::style::meter_clip(cc);
+            } else {
+                ui::style::meter(cc);
+            }
+            cc.rectangle(self.x + area.b_x, y, w * ratio(channel.rms), bar_h - 1.);
+            cc.fill()?;
+
+            let peak_x = self.x + area.b_x + w * ratio(channel.peak);
+            ui::style::meter_peak(cc);
+            cc.move_to(peak_x, y);
+            cc.line_to(peak_x, y + bar_h - 1.);
+            cc.stroke()?;
+        }
+        Ok(())
+    }
+
     pub fn draw_control<S, SS, SSB>(
         &self,
         cc: &Context,
@@ -1029,6 +1070,10 @@ pub trait TalkerControl {
         util::print_cairo_result(base.draw_ears_and_voices(cc, graph_presenter));
     }
 
+    // The meters are drawn over the graph so that the levels can be updated alone
+    fn draw_meters(&self, _cc: &Context, _graph_presenter: &GraphPresenter) {
+    }
+
     fn position(&self) -> (f64, f64) {
         let base = self.base().borrow();
         (base.x, base.y)
@@ -0,0 +1,105 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use gtk::glib::clone;
+use gtk::prelude::*;
+
+use talker::audio_format::AudioFormat;
+use talker::transport::{self, Transport};
+
+use crate::session_presenter::RSessionPresenter;
+
+const DEFAULT_DURATION: f64 = 300.;
+
+fn position_text(tick: i64, bpm: f64) -> String {
+    let seconds = tick as f64 / AudioFormat::sample_rate() as f64;
+    let minutes = (seconds / 60.).floor();
+    let (bar, beat) = Transport::bar_beat(tick, bpm as f32);
+
+    format!("{:02}:{:06.3}  {}.{}", minutes, seconds - minutes * 60., bar + 1, beat.floor() + 1.)
+}
+
+pub struct TimelineView {
+    timeline_box: gtk::Box,
+    position_label: gtk::Label,
+    position_scale: gtk::Scale,
+    bpm_selector: gtk::SpinButton,
+    tick: Rc<Cell<i64>>,
+    // Set while the session tempo is displayed, to not send it back
+    tempo_updating: Rc<Cell<bool>>,
+}
+
+impl TimelineView {
+    pub fn new(session_presenter: &RSessionPresenter) -> TimelineView {
+        let tick = Rc::new(Cell::new(0));
+        let tempo_updating = Rc::new(Cell::new(false));
+        let tempo = session_presenter.borrow().tempo() as f64;
+
+        let position_label = gtk::Label::builder()
+            .label(&position_text(0, tempo))
+            .width_chars(20)
+            .css_classes(["monospace"])
+            .build();
+
+        let position_scale = gtk::Scale::with_range(gtk::Orientation::Horizontal, 0., DEFAULT_DURATION, 0.01);
+        position_scale.set_draw_value(false);
+        position_scale.set_hexpand(true);
+        position_scale.set_tooltip_text(Some("Playback position"));
+
+        let bpm_label = gtk::Label::new(Some("BPM"));
+        let bpm_selector = gtk::SpinButton::with_range(transport::MIN_TEMPO as f64, transport::MAX_TEMPO as f64, 1.);
+        bpm_selector.set_value(tempo);
+
+        let timeline_box = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        timeline_box.set_margin_start(6);
+        timeline_box.set_margin_end(6);
+        timeline_box.append(&position_label);
+        timeline_box.append(&position_scale);
+        timeline_box.append(&bpm_label);
+        timeline_box.append(&bpm_selector);
+
+        // Only the user moves raise a seek
+        let rsp = session_presenter.clone();
+        position_scale.connect_change_value(move |_, _, value| {
+            let t = (value.max(0.) * AudioFormat::sample_rate() as f64) as i64;
+            rsp.borrow_mut().seek(t);
+            gtk::glib::signal::Propagation::Proceed
+        });
+
+        let tsp = session_presenter.clone();
+        bpm_selector.connect_value_changed(clone!(#[strong] position_label, #[strong] tick, #[strong] tempo_updating, move |bs| {
+            position_label.set_label(&position_text(tick.get(), bs.value()));
+
+            if !tempo_updating.get() {
+                tsp.borrow_mut().set_tempo(bs.value() as f32);
+            }
+        }));
+
+        Self { timeline_box, position_label, position_scale, bpm_selector, tick, tempo_updating }
+    }
+
+    pub fn add_content<F>(&self, add: F)
+    where F: Fn(&gtk::Box),
+    {
+        add(&self.timeline_box)
+    }
+
+    pub fn set_tempo(&self, tempo: f32) {
+        self.tempo_updating.set(true);
+        self.bpm_selector.set_value(tempo as f64);
+        self.tempo_updating.set(false);
+    }
+
+    pub fn set_tick(&self, tick: i64) {
+        self.tick.set(tick);
+        self.position_label.set_label(&position_text(tick, self.bpm_selector.value()));
+
+        let seconds = tick as f64 / AudioFormat::sample_rate() as f64;
+        let adjustment = self.position_scale.adjustment();
+
+        if seconds > adjustment.upper() {
+            adjustment.set_upper(seconds * 2.);
+        }
+        self.position_scale.set_value(seconds);
+    }
+}
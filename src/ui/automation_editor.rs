@@ -0,0 +1,248 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use cairo::Context;
+use gtk::{
+    glib::clone, prelude::{BoxExt, ButtonExt, CheckButtonExt, DrawingAreaExtManual, GestureDragExt, GestureSingleExt, GtkWindowExt, WidgetExt},
+};
+
+use session::talkers::automation::Breakpoint;
+use talker::identifier::Identifiable;
+
+use crate::graph_presenter::RGraphPresenter;
+use crate::session_presenter::{HumKey, RSessionPresenter};
+use crate::ui;
+use crate::util;
+
+const LANE_WIDTH: i32 = 800;
+const LANE_HEIGHT: i32 = 240;
+const POINT_RADIUS: f64 = 4.;
+// Seconds shown when the session has no end
+const DEFAULT_DURATION: i64 = 60;
+
+// Breakpoints edited on the lane drawing area
+struct Lane {
+    points: Vec<Breakpoint>,
+    min: f32,
+    max: f32,
+    end_tick: i64,
+    dragged: Option<usize>,
+    drag_start: (f64, f64),
+}
+type RLane = Rc<RefCell<Lane>>;
+
+impl Lane {
+    fn to_x(&self, tick: i64, width: f64) -> f64 {
+        tick as f64 * width / self.end_tick as f64
+    }
+    fn to_y(&self, value: f32, height: f64) -> f64 {
+        height - (value - self.min) as f64 * height / (self.max - self.min) as f64
+    }
+    fn to_tick(&self, x: f64, width: f64) -> i64 {
+        ((x / width) * self.end_tick as f64).round().max(0.) as i64
+    }
+    fn to_value(&self, y: f64, height: f64) -> f32 {
+        let v = self.min + ((height - y) / height) as f32 * (self.max - self.min);
+        v.max(self.min).min(self.max)
+    }
+
+    fn point_at(&self, x: f64, y: f64, width: f64, height: f64) -> Option<usize> {
+        self.points.iter().position(|(tick, value)| {
+            (self.to_x(*tick, width) - x).abs() <= POINT_RADIUS * 2.
+                && (self.to_y(*value, height) - y).abs() <= POINT_RADIUS * 2.
+        })
+    }
+
+    // A moved point stays between its neighbours
+    fn move_point(&mut self, idx: usize, x: f64, y: f64, width: f64, height: f64) {
+        let prev_tick = if idx > 0 { self.points[idx - 1].0 + 1 } else { 0 };
+        let next_tick = self.points.get(idx + 1).map_or(i64::MAX, |(tick, _)| *tick - 1);
+
+        let tick = self.to_tick(x, width).max(prev_tick).min(next_tick);
+        self.points[idx] = (tick, self.to_value(y, height));
+    }
+
+    fn draw(&self, cc: &Context, width: f64, height: f64) {
+        ui::style::background(cc);
+        cc.rectangle(0., 0., width, height);
+        util::print_cairo_result(cc.fill());
+
+        if self.points.is_empty() {
+            return;
+        }
+
+        ui::style::connection(cc, &ui::style::WHITE_COLOR);
+        let (_, first_value) = self.points[0];
+        cc.move_to(0., self.to_y(first_value, height));
+
+        for (tick, value) in &self.points {
+            cc.line_to(self.to_x(*tick, width), self.to_y(*value, height));
+        }
+        let (_, last_value) = self.points[self.points.len() - 1];
+        cc.line_to(width, self.to_y(last_value, height));
+        util::print_cairo_result(cc.stroke());
+
+        ui::style::value(cc);
+
+        for (tick, value) in &self.points {
+            cc.arc(self.to_x(*tick, width), self.to_y(*value, height), POINT_RADIUS, 0., std::f64::consts::TAU);
+            util::print_cairo_result(cc.fill());
+        }
+    }
+}
+
+fn apply(session_presenter: &RSessionPresenter, hum: HumKey, lane: &RLane) {
+    let points = lane.borrow().points.clone();
+    session_presenter.borrow_mut().set_automation(hum, points);
+}
+
+pub fn expose(
+    parent: &gtk::ApplicationWindow,
+    session_presenter: &RSessionPresenter,
+    graph_presenter: &RGraphPresenter,
+    hum: HumKey,
+) {
+    let (talker_id, ear_idx, set_idx, hum_idx) = hum;
+    let talker = graph_presenter.borrow().get_talker(talker_id);
+    let ear = talker.ear(ear_idx);
+
+    let hum_tag = match ear.sets().get(set_idx).and_then(|set| set.hums().get(hum_idx)) {
+        Some(h) => h.tag().to_string(),
+        None => return,
+    };
+    let (min, max, _) = ear.hum_range(hum_idx);
+
+    let (points, armed, end_tick) = {
+        let sp = session_presenter.borrow();
+        let points = sp.automation(hum).unwrap_or_default();
+        let last_tick = points.last().map_or(0, |(tick, _)| *tick);
+        let end_tick = sp.end_tick().max(DEFAULT_DURATION * sp.sample_rate() as i64).max(last_tick + 1);
+
+        (points, sp.is_automation_armed(hum), end_tick)
+    };
+
+    let lane = Rc::new(RefCell::new(Lane {
+        points,
+        min,
+        max: if max > min { max } else { min + 1. },
+        end_tick,
+        dragged: None,
+        drag_start: (0., 0.),
+    }));
+
+    let drawing_area = gtk::DrawingArea::builder()
+        .content_width(LANE_WIDTH)
+        .content_height(LANE_HEIGHT)
+        .hexpand(true)
+        .vexpand(true)
+        .build();
+
+    drawing_area.set_draw_func(clone!(#[strong] lane, move |_, cc, width, height| {
+        lane.borrow().draw(cc, width as f64, height as f64)
+    }));
+
+    // A press on the lane takes the point under it or adds a new one, the release applies the move
+    let drag = gtk::GestureDrag::new();
+
+    drag.connect_drag_begin(clone!(#[strong] lane, #[weak] drawing_area, move |_, x, y| {
+        let (width, height) = (drawing_area.width() as f64, drawing_area.height() as f64);
+        let mut ln = lane.borrow_mut();
+
+        let idx = match ln.point_at(x, y, width, height) {
+            Some(idx) => idx,
+            None => {
+                let point = (ln.to_tick(x, width), ln.to_value(y, height));
+                let idx = ln.points.partition_point(|(tick, _)| *tick < point.0);
+
+                if ln.points.get(idx).map_or(false, |(tick, _)| *tick == point.0) {
+                    ln.points[idx] = point;
+                } else {
+                    ln.points.insert(idx, point);
+                }
+                idx
+            }
+        };
+        ln.dragged = Some(idx);
+        ln.drag_start = (x, y);
+        drawing_area.queue_draw();
+    }));
+
+    drag.connect_drag_update(clone!(#[strong] lane, #[weak] drawing_area, move |_, dx, dy| {
+        let (width, height) = (drawing_area.width() as f64, drawing_area.height() as f64);
+        let mut ln = lane.borrow_mut();
+
+        if let Some(idx) = ln.dragged {
+            let (x, y) = ln.drag_start;
+            ln.move_point(idx, (x + dx).max(0.).min(width), (y + dy).max(0.).min(height), width, height);
+            drawing_area.queue_draw();
+        }
+    }));
+
+    drag.connect_drag_end(clone!(#[strong] lane, #[strong] session_presenter, move |_, _, _| {
+        lane.borrow_mut().dragged = None;
+        apply(&session_presenter, hum, &lane);
+    }));
+    drawing_area.add_controller(drag);
+
+    // A secondary click removes the point under it
+    let remove_click = gtk::GestureClick::new();
+    remove_click.set_button(gtk::gdk::BUTTON_SECONDARY);
+
+    remove_click.connect_pressed(clone!(#[strong] lane, #[strong] session_presenter, #[weak] drawing_area, move |_, _, x, y| {
+        let (width, height) = (drawing_area.width() as f64, drawing_area.height() as f64);
+        let oidx = lane.borrow().point_at(x, y, width, height);
+
+        if let Some(idx) = oidx {
+            lane.borrow_mut().points.remove(idx);
+            apply(&session_presenter, hum, &lane);
+            drawing_area.queue_draw();
+        }
+    }));
+    drawing_area.add_controller(remove_click);
+
+    let arm_button = gtk::CheckButton::builder()
+        .label("Record the value changes")
+        .active(armed)
+        .build();
+
+    arm_button.connect_toggled(clone!(#[strong] session_presenter, move |b| {
+        session_presenter.borrow_mut().arm_automation(hum, b.is_active());
+    }));
+
+    let clear_button = gtk::Button::builder().label("Remove the automation").hexpand(true).build();
+
+    clear_button.connect_clicked(clone!(#[strong] lane, #[strong] session_presenter, #[weak] drawing_area, move |_| {
+        lane.borrow_mut().points.clear();
+        apply(&session_presenter, hum, &lane);
+        drawing_area.queue_draw();
+    }));
+
+    let close_button = gtk::Button::builder().label("Close").hexpand(true).build();
+
+    let action_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(20)
+        .build();
+    action_box.append(&clear_button);
+    action_box.append(&close_button);
+
+    let widget = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(20).margin_start(20).margin_end(20).margin_top(20).margin_bottom(20)
+        .build();
+    widget.append(&arm_button);
+    widget.append(&drawing_area);
+    widget.append(&gtk::Separator::new(gtk::Orientation::Horizontal));
+    widget.append(&action_box);
+
+    let window = gtk::Window::builder()
+        .transient_for(parent)
+        .title(format!("{} {} automation", talker.name(), hum_tag))
+        .child(&widget)
+        .visible(true)
+        .build();
+
+    close_button.connect_clicked(clone!(#[weak] window, move |_| window.close()));
+
+    window.present();
+}
@@ -0,0 +1,76 @@
+use gtk::{
+    glib::{self, clone}, prelude::{BoxExt, ButtonExt, GtkWindowExt, GridExt, WidgetExt},
+};
+
+use crate::session_presenter::RSessionPresenter;
+
+pub fn expose(app: &gtk::Application, session_presenter: &RSessionPresenter,) {
+    let disabled_plugins = session_presenter.borrow().disabled_plugins();
+
+    let grid = gtk::Grid::builder()
+        .margin_start(6)
+        .margin_end(6)
+        .margin_top(6)
+        .margin_bottom(6)
+        .row_spacing(6)
+        .column_spacing(12)
+        .build();
+
+    if disabled_plugins.is_empty() {
+        grid.attach(&gtk::Label::new(Some("No plugin is disabled.")), 0, 0, 1, 1);
+    }
+
+    for (row, (uri, name, reason)) in disabled_plugins.iter().enumerate() {
+        let name_label = gtk::Label::builder().label(name).tooltip_text(uri).halign(gtk::Align::Start).build();
+        let reason_label = gtk::Label::builder().label(reason).halign(gtk::Align::Start).wrap(true).build();
+
+        grid.attach(&name_label, 0, row as i32, 1, 1);
+        grid.attach(&reason_label, 1, row as i32, 1, 1);
+    }
+
+    let scrolled_window = gtk::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .min_content_width(600)
+        .min_content_height(300)
+        .child(&grid)
+        .build();
+
+    let forget_button = gtk::Button::builder()
+        .label("Probe again at next start")
+        .hexpand(true)
+        .sensitive(!disabled_plugins.is_empty())
+        .build();
+    let close_button = gtk::Button::builder().label("Close").hexpand(true).build();
+
+    let action_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(20)
+        .build();
+    action_box.append(&forget_button);
+    action_box.append(&close_button);
+
+    let widget = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(20).margin_start(20).margin_end(20).margin_top(20).margin_bottom(20)
+        .build();
+    widget.append(&scrolled_window);
+    widget.append(&gtk::Separator::new(gtk::Orientation::Horizontal));
+    widget.append(&action_box);
+
+    let window = gtk::Window::builder()
+        .application(app)
+        .title("Disabled plugins")
+        .child(&widget)
+        .modal(true)
+        .visible(true)
+        .build();
+
+    let rssp = session_presenter.clone();
+    forget_button.connect_clicked(move |button| {
+        rssp.borrow().forget_disabled_plugins();
+        button.set_sensitive(false);
+    });
+    close_button.connect_clicked(clone!(#[weak] window, move |_| window.destroy()));
+
+    window.present();
+}
@@ -2,11 +2,22 @@
 use std::str::FromStr;
 
 use gtk::{
-    glib::{self, clone}, prelude::{BoxExt, ButtonExt, GtkWindowExt, GridExt}, DropDown,
+    glib::{self, clone}, prelude::{BoxExt, ButtonExt, CheckButtonExt, GtkWindowExt, GridExt}, DropDown, SpinButton,
 };
 
+use session::feedback;
+use session::midi_input;
+use session::settings::{FeedbackSettings, LoopSettings, MidiSettings};
+use talker::identifier::Identifiable;
+
 use crate::session_presenter::RSessionPresenter;
 
+const DEFAULT_LABEL: &str = "Default";
+const BUFFER_SIZES: [usize; 7] = [64, 128, 256, 512, 1024, 2048, 4096];
+const MAX_LOOP_CROSSFADE: f64 = 500.;
+const MAX_LOOP_COUNT: f64 = 999.;
+const MAX_LOOP_TIME: f64 = 86400.;
+
 
 pub fn expose(app: &gtk::Application, session_presenter: &RSessionPresenter,) {
     let sample_rates_strs = ["8000", "11025", "16000", "22050", "32000", "44100", "48000", "88200", "96000"];
@@ -25,7 +36,7 @@ pub fn expose(app: &gtk::Application, session_presenter: &RSessionPresenter,) {
         .column_spacing(6)
         .build();
 
-    let row = 0;
+    let mut row = 0;
 
     let sample_rate_label = gtk::Label::new(Some("Sample rate : "));
     let sample_rate_selector = DropDown::from_strings(&sample_rates_strs);
@@ -37,6 +48,143 @@ pub fn expose(app: &gtk::Application, session_presenter: &RSessionPresenter,) {
 
     grid.attach(&sample_rate_label, 0, row, 1, 1);
     grid.attach(&sample_rate_selector, 1, row, 1, 1);
+    row += 1;
+
+    let feedback_settings = session_presenter.borrow().feedback_settings();
+
+    // Output device
+    let devices_names = feedback::output_devices_names();
+    let mut devices_labels = vec![DEFAULT_LABEL];
+    devices_labels.extend(devices_names.iter().map(|n| n.as_str()));
+
+    let device_label = gtk::Label::new(Some("Output device : "));
+    let device_selector = DropDown::from_strings(&devices_labels);
+
+    if let Some(device) = &feedback_settings.device {
+        match devices_names.iter().position(|n| n == device) {
+            Some(idx) => device_selector.set_selected(idx as u32 + 1),
+            None => eprintln!("Output device {} unavailable.", device),
+        }
+    }
+
+    grid.attach(&device_label, 0, row, 1, 1);
+    grid.attach(&device_selector, 1, row, 1, 1);
+    row += 1;
+
+    // Buffer size
+    let buffer_sizes_strs: Vec<String> = BUFFER_SIZES.iter().map(|s| s.to_string()).collect();
+    let mut buffer_sizes_labels = vec![DEFAULT_LABEL];
+    buffer_sizes_labels.extend(buffer_sizes_strs.iter().map(|s| s.as_str()));
+
+    let buffer_size_label = gtk::Label::new(Some("Buffer size : "));
+    let buffer_size_selector = DropDown::from_strings(&buffer_sizes_labels);
+
+    if let Some(buffer_size) = feedback_settings.buffer_size {
+        if let Some(idx) = BUFFER_SIZES.iter().position(|s| *s == buffer_size) {
+            buffer_size_selector.set_selected(idx as u32 + 1);
+        }
+    }
+
+    grid.attach(&buffer_size_label, 0, row, 1, 1);
+    grid.attach(&buffer_size_selector, 1, row, 1, 1);
+    row += 1;
+
+    // Feedback mixers
+    let mixers_label = gtk::Label::new(Some("Monitored mixers : "));
+    mixers_label.set_tooltip_text(Some("Mixers summed to the output device. None for the first mixer"));
+    let mixers_box = gtk::Box::builder().orientation(gtk::Orientation::Vertical).build();
+
+    let mut mixers: Vec<(u32, String)> = session_presenter.borrow().mixers().iter()
+        .map(|(id, m)| (*id, m.borrow().name()))
+        .collect();
+    mixers.sort();
+
+    let mut mixers_checks = Vec::with_capacity(mixers.len());
+
+    for (_, name) in mixers {
+        let check = gtk::CheckButton::builder()
+            .label(&name)
+            .active(feedback_settings.mixers.contains(&name))
+            .build();
+        mixers_box.append(&check);
+        mixers_checks.push((name, check));
+    }
+
+    grid.attach(&mixers_label, 0, row, 1, 1);
+    grid.attach(&mixers_box, 1, row, 1, 1);
+    row += 1;
+
+    // MIDI inputs
+    let midi_settings = session_presenter.borrow().midi_settings();
+
+    let midi_inputs_label = gtk::Label::new(Some("MIDI inputs : "));
+    midi_inputs_label.set_tooltip_text(Some("Ports listened for the MIDI controllers. None for every port"));
+    let midi_inputs_box = gtk::Box::builder().orientation(gtk::Orientation::Vertical).build();
+
+    let mut midi_inputs_checks = Vec::new();
+
+    for name in midi_input::input_ports_names() {
+        let check = gtk::CheckButton::builder()
+            .label(&name)
+            .active(midi_settings.inputs.contains(&name))
+            .build();
+        midi_inputs_box.append(&check);
+        midi_inputs_checks.push((name, check));
+    }
+
+    grid.attach(&midi_inputs_label, 0, row, 1, 1);
+    grid.attach(&midi_inputs_box, 1, row, 1, 1);
+    row += 1;
+
+    // Loop region
+    let loop_settings = session_presenter.borrow().loop_settings();
+    let start_tick = session_presenter.borrow().start_tick();
+    let end_tick = session_presenter.borrow().end_tick();
+    let samples_per_second = sample_rate as f64;
+
+    let loop_start_label = gtk::Label::new(Some("Loop start (s) : "));
+    let loop_start_selector = SpinButton::with_range(0., MAX_LOOP_TIME, 0.1);
+    loop_start_selector.set_digits(3);
+    loop_start_selector.set_value(start_tick as f64 / samples_per_second);
+
+    grid.attach(&loop_start_label, 0, row, 1, 1);
+    grid.attach(&loop_start_selector, 1, row, 1, 1);
+    row += 1;
+
+    let loop_end_label = gtk::Label::new(Some("Loop end (s) : "));
+    loop_end_label.set_tooltip_text(Some("Equal to the loop start to play without looping"));
+    let loop_end_selector = SpinButton::with_range(0., MAX_LOOP_TIME, 0.1);
+    loop_end_selector.set_digits(3);
+    loop_end_selector.set_value(end_tick as f64 / samples_per_second);
+
+    grid.attach(&loop_end_label, 0, row, 1, 1);
+    grid.attach(&loop_end_selector, 1, row, 1, 1);
+    row += 1;
+
+    let crossfade_label = gtk::Label::new(Some("Loop crossfade (ms) : "));
+    let crossfade_selector = SpinButton::with_range(0., MAX_LOOP_CROSSFADE, 1.);
+    crossfade_selector.set_value(loop_settings.crossfade as f64);
+
+    grid.attach(&crossfade_label, 0, row, 1, 1);
+    grid.attach(&crossfade_selector, 1, row, 1, 1);
+    row += 1;
+
+    let loop_count_label = gtk::Label::new(Some("Loop count : "));
+    loop_count_label.set_tooltip_text(Some("Number of passes before stopping. 0 for endless"));
+    let loop_count_selector = SpinButton::with_range(0., MAX_LOOP_COUNT, 1.);
+    loop_count_selector.set_value(loop_settings.count as f64);
+
+    grid.attach(&loop_count_label, 0, row, 1, 1);
+    grid.attach(&loop_count_selector, 1, row, 1, 1);
+    row += 1;
+
+    let punch_in_check = gtk::CheckButton::builder()
+        .label("Punch-in recording")
+        .tooltip_text("Only record the loop region")
+        .active(loop_settings.punch_in)
+        .build();
+
+    grid.attach(&punch_in_check, 1, row, 1, 1);
 
     let action_separator = gtk::Separator::builder().hexpand(true).vexpand(true).orientation(gtk::Orientation::Horizontal).build();
 
@@ -74,6 +222,45 @@ pub fn expose(app: &gtk::Application, session_presenter: &RSessionPresenter,) {
             rssp.borrow_mut().set_sample_rate(new_sample_rate);
         }
 
+        let device_idx = device_selector.selected() as usize;
+        let buffer_size_idx = buffer_size_selector.selected() as usize;
+
+        let new_feedback_settings = FeedbackSettings {
+            device: if device_idx > 0 { devices_names.get(device_idx - 1).cloned() } else { None },
+            buffer_size: if buffer_size_idx > 0 { BUFFER_SIZES.get(buffer_size_idx - 1).cloned() } else { None },
+            mixers: mixers_checks.iter().filter(|(_, c)| c.is_active()).map(|(n, _)| n.clone()).collect(),
+        };
+
+        if new_feedback_settings != feedback_settings {
+            rssp.borrow_mut().set_feedback_settings(new_feedback_settings);
+        }
+
+        let new_midi_settings = MidiSettings {
+            inputs: midi_inputs_checks.iter().filter(|(_, c)| c.is_active()).map(|(n, _)| n.clone()).collect(),
+        };
+
+        if new_midi_settings != midi_settings {
+            rssp.borrow_mut().set_midi_settings(new_midi_settings, &rssp);
+        }
+
+        let new_start_tick = (loop_start_selector.value() * samples_per_second) as i64;
+        let new_end_tick = (loop_end_selector.value() * samples_per_second) as i64;
+
+        if new_start_tick != start_tick || new_end_tick != end_tick {
+            rssp.borrow_mut().set_start_tick(new_start_tick);
+            rssp.borrow_mut().set_end_tick(new_end_tick.max(new_start_tick));
+        }
+
+        let new_loop_settings = LoopSettings {
+            crossfade: crossfade_selector.value() as usize,
+            count: loop_count_selector.value() as usize,
+            punch_in: punch_in_check.is_active(),
+        };
+
+        if new_loop_settings != loop_settings {
+            rssp.borrow_mut().set_loop_settings(new_loop_settings);
+        }
+
         window.destroy();
     }));
     cancel_button.connect_clicked(clone!(#[weak] window, move |_| window.destroy()));
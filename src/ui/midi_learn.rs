@@ -0,0 +1,202 @@
+use gtk::{
+    glib::{self, clone}, prelude::{BoxExt, ButtonExt, GridExt, GtkWindowExt, WidgetExt}, DropDown, SpinButton,
+};
+
+use session::midi_mapping::{Curve, MidiMapping};
+use talker::identifier::Identifiable;
+
+use crate::graph_presenter::RGraphPresenter;
+use crate::session_presenter::{HumKey, RSessionPresenter};
+
+const LEARNING_CHECK_PERIOD: u64 = 100;
+const NOT_MAPPED_LABEL: &str = "Not mapped";
+const LEARNING_LABEL: &str = "Move a MIDI controller...";
+
+fn controller_label(mapping: &Option<MidiMapping>) -> String {
+    match mapping {
+        Some(m) => format!("Channel {}, controller {}", m.channel + 1, m.controller),
+        None => NOT_MAPPED_LABEL.to_string(),
+    }
+}
+
+pub fn expose(
+    parent: &gtk::ApplicationWindow,
+    session_presenter: &RSessionPresenter,
+    graph_presenter: &RGraphPresenter,
+    hum: HumKey,
+) {
+    let (talker_id, ear_idx, set_idx, hum_idx) = hum;
+    let talker = graph_presenter.borrow().get_talker(talker_id);
+    let ear = talker.ear(ear_idx);
+
+    let hum_tag = match ear.sets().get(set_idx).and_then(|set| set.hums().get(hum_idx)) {
+        Some(h) => h.tag().to_string(),
+        None => return,
+    };
+    let (hum_min, hum_max, _) = ear.hum_range(hum_idx);
+    let (hum_min, hum_max) = if hum_max > hum_min { (hum_min, hum_max) } else { (hum_min, hum_min + 1.) };
+
+    let mapping = session_presenter.borrow().midi_mapping(hum);
+
+    let grid = gtk::Grid::builder()
+        .margin_start(6)
+        .margin_end(6)
+        .margin_top(6)
+        .margin_bottom(6)
+        .halign(gtk::Align::Center)
+        .valign(gtk::Align::Center)
+        .row_spacing(6)
+        .column_spacing(6)
+        .build();
+
+    let mut row = 0;
+
+    let controller_value_label = gtk::Label::new(Some(&controller_label(&mapping)));
+
+    grid.attach(&gtk::Label::new(Some("Controller : ")), 0, row, 1, 1);
+    grid.attach(&controller_value_label, 1, row, 1, 1);
+    row += 1;
+
+    let step = (hum_max - hum_min) as f64 / 127.;
+
+    let min_selector = SpinButton::with_range(hum_min as f64, hum_max as f64, step);
+    min_selector.set_digits(3);
+    min_selector.set_value(mapping.as_ref().map_or(hum_min, |m| m.min) as f64);
+
+    grid.attach(&gtk::Label::new(Some("Minimum : ")), 0, row, 1, 1);
+    grid.attach(&min_selector, 1, row, 1, 1);
+    row += 1;
+
+    let max_selector = SpinButton::with_range(hum_min as f64, hum_max as f64, step);
+    max_selector.set_digits(3);
+    max_selector.set_value(mapping.as_ref().map_or(hum_max, |m| m.max) as f64);
+
+    grid.attach(&gtk::Label::new(Some("Maximum : ")), 0, row, 1, 1);
+    grid.attach(&max_selector, 1, row, 1, 1);
+    row += 1;
+
+    let curves = Curve::all();
+    let curves_labels: Vec<&str> = curves.iter().map(|c| c.to_str()).collect();
+    let curve_selector = DropDown::from_strings(&curves_labels);
+
+    if let Some(idx) = mapping.as_ref().and_then(|m| curves.iter().position(|c| *c == m.curve)) {
+        curve_selector.set_selected(idx as u32);
+    }
+
+    grid.attach(&gtk::Label::new(Some("Curve : ")), 0, row, 1, 1);
+    grid.attach(&curve_selector, 1, row, 1, 1);
+
+    let learn_button = gtk::Button::builder().label("Learn").hexpand(true).build();
+    let remove_button = gtk::Button::builder().label("Remove").hexpand(true).sensitive(mapping.is_some()).build();
+
+    let mapping_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(20)
+        .build();
+    mapping_box.append(&learn_button);
+    mapping_box.append(&remove_button);
+
+    let cancel_button = gtk::Button::builder().label("Cancel").hexpand(true).build();
+    let ok_button = gtk::Button::builder().label("Ok").hexpand(true).build();
+
+    let action_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(20)
+        .build();
+    action_box.append(&cancel_button);
+    action_box.append(&ok_button);
+
+    let widget = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(20).margin_start(20).margin_end(20).margin_top(20).margin_bottom(20)
+        .build();
+    widget.append(&grid);
+    widget.append(&mapping_box);
+    widget.append(&gtk::Separator::new(gtk::Orientation::Horizontal));
+    widget.append(&action_box);
+
+    let window = gtk::Window::builder()
+        .transient_for(parent)
+        .title(format!("{} {} MIDI learn", talker.name(), hum_tag))
+        .child(&widget)
+        .modal(true)
+        .visible(true)
+        .build();
+
+    // Mapping of the hum with the dialog range and curve
+    let edited_mapping = clone!(#[strong] min_selector, #[strong] max_selector, #[strong] curve_selector, move || {
+        MidiMapping::new(
+            talker_id,
+            ear_idx,
+            set_idx,
+            hum_idx,
+            min_selector.value() as f32,
+            max_selector.value() as f32,
+            curves[(curve_selector.selected() as usize).min(curves.len() - 1)],
+        )
+    });
+
+    let edited = edited_mapping.clone();
+    learn_button.connect_clicked(clone!(#[strong] session_presenter, #[weak] controller_value_label, #[weak] remove_button, move |button| {
+        session_presenter.borrow_mut().learn_midi(edited(), &session_presenter);
+
+        if !session_presenter.borrow().is_midi_learning(hum) {
+            return;
+        }
+        controller_value_label.set_label(LEARNING_LABEL);
+        button.set_sensitive(false);
+        let button = button.clone();
+
+        // The learning ends when the player reports the mapping
+        let period = std::time::Duration::from_millis(LEARNING_CHECK_PERIOD);
+
+        glib::timeout_add_local(period, clone!(
+            #[strong] session_presenter, #[weak] controller_value_label, #[weak] button, #[weak] remove_button,
+            #[upgrade_or] glib::ControlFlow::Break,
+            move || {
+                if session_presenter.borrow().is_midi_learning(hum) {
+                    return glib::ControlFlow::Continue;
+                }
+                let mapping = session_presenter.borrow().midi_mapping(hum);
+
+                controller_value_label.set_label(&controller_label(&mapping));
+                remove_button.set_sensitive(mapping.is_some());
+                button.set_sensitive(true);
+                glib::ControlFlow::Break
+            }
+        ));
+    }));
+
+    remove_button.connect_clicked(clone!(#[strong] session_presenter, #[weak] controller_value_label, #[weak] learn_button, move |button| {
+        session_presenter.borrow_mut().cancel_midi_learning();
+        session_presenter.borrow_mut().remove_midi_mapping(hum);
+
+        controller_value_label.set_label(NOT_MAPPED_LABEL);
+        learn_button.set_sensitive(true);
+        button.set_sensitive(false);
+    }));
+
+    // The learning is cancelled with the dialog
+    window.connect_close_request(clone!(#[strong] session_presenter, move |_| {
+        session_presenter.borrow_mut().cancel_midi_learning();
+        glib::Propagation::Proceed
+    }));
+
+    ok_button.connect_clicked(clone!(#[strong] session_presenter, #[weak] window, move |_| {
+        let current_mapping = session_presenter.borrow().midi_mapping(hum);
+
+        if let Some(current) = current_mapping {
+            let mut mapping = edited_mapping();
+            mapping.channel = current.channel;
+            mapping.controller = current.controller;
+
+            if mapping != current {
+                session_presenter.borrow_mut().set_midi_mapping(mapping);
+            }
+        }
+        window.close();
+    }));
+    cancel_button.connect_clicked(clone!(#[weak] window, move |_| window.close()));
+
+    window.present();
+}
@@ -1,6 +1,11 @@
+pub mod automation_editor;
 pub mod bounded_float_entry;
 pub mod session_settings;
+pub mod disabled_plugins;
 pub mod general_settings;
+pub mod midi_learn;
+pub mod plugin_parameters;
+pub mod plugin_presets;
 pub mod plugin_ui;
 pub mod session_opening_dialog;
 pub mod session_saving_dialog;
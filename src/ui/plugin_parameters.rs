@@ -0,0 +1,206 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use gtk::{
+    glib::{self, clone}, prelude::{BoxExt, ButtonExt, Cast, CheckButtonExt, GtkWindowExt, GridExt, RangeExt, ScaleExt, WidgetExt},
+};
+
+use talker::identifier::{Id, Identifiable, Index};
+use talker::lv2_handler::ControlInput;
+
+use crate::graph_presenter::RGraphPresenter;
+use crate::session_presenter::RSessionPresenter;
+
+const SCALE_WIDTH: i32 = 240;
+const SCALE_STEPS: f64 = 1000.;
+
+// Changed values not yet committed to the undo list
+type RPendingValues = Rc<RefCell<BTreeMap<Index, f32>>>;
+
+// Logarithmic ports are edited on the scale of the value logarithm
+fn to_position(value: f32, logarithmic: bool) -> f64 {
+    if logarithmic { (value as f64).ln() } else { value as f64 }
+}
+fn to_value(position: f64, logarithmic: bool) -> f32 {
+    if logarithmic { position.exp() as f32 } else { position as f32 }
+}
+
+fn toggle_widget(graph_presenter: &RGraphPresenter, talker_id: Id, ear_idx: Index, value: f32) -> gtk::Widget {
+    let check = gtk::CheckButton::builder().active(value > 0.5).build();
+
+    let gp = graph_presenter.clone();
+    check.connect_toggled(move |c| {
+        let v = if c.is_active() { 1. } else { 0. };
+        gp.borrow_mut().set_talker_ear_hum_value(talker_id, ear_idx, 0, 0, v);
+    });
+    check.upcast()
+}
+
+fn enumeration_widget(graph_presenter: &RGraphPresenter, talker_id: Id, ear_idx: Index, value: f32, scale_points: &Vec<(f32, String)>) -> gtk::Widget {
+    let labels: Vec<&str> = scale_points.iter().map(|(_, label)| label.as_str()).collect();
+    let selector = gtk::DropDown::from_strings(&labels);
+
+    let selected = scale_points.iter()
+        .position(|(v, _)| (*v - value).abs() < f32::EPSILON)
+        .unwrap_or(0);
+    selector.set_selected(selected as u32);
+
+    let gp = graph_presenter.clone();
+    let values: Vec<f32> = scale_points.iter().map(|(v, _)| *v).collect();
+    selector.connect_selected_notify(move |s| {
+        if let Some(v) = values.get(s.selected() as usize) {
+            gp.borrow_mut().set_talker_ear_hum_value(talker_id, ear_idx, 0, 0, *v);
+        }
+    });
+    selector.upcast()
+}
+
+fn scale_widget(
+    graph_presenter: &RGraphPresenter,
+    pending_values: &RPendingValues,
+    talker_id: Id,
+    ear_idx: Index,
+    (min, max, value): (f32, f32, f32),
+    control_input: &ControlInput,
+) -> gtk::Widget {
+    let logarithmic = control_input.logarithmic && min > 0.;
+    let integer = control_input.integer;
+
+    let start = to_position(min, logarithmic);
+    let end = to_position(max, logarithmic);
+    let step = if integer && !logarithmic { 1. } else { (end - start) / SCALE_STEPS };
+
+    let scale = gtk::Scale::with_range(gtk::Orientation::Horizontal, start, end, step);
+    scale.set_width_request(SCALE_WIDTH);
+    scale.set_draw_value(true);
+    scale.set_value_pos(gtk::PositionType::Right);
+    scale.set_value(to_position(value, logarithmic));
+    scale.set_format_value_func(move |_, position| {
+        let v = to_value(position, logarithmic);
+        if integer { format!("{}", v.round()) } else { format!("{:.3}", v) }
+    });
+
+    let gp = graph_presenter.clone();
+    let pending_values = pending_values.clone();
+    scale.connect_value_changed(move |s| {
+        let mut v = to_value(s.value(), logarithmic);
+
+        if integer {
+            v = v.round();
+        }
+        gp.borrow_mut().set_talker_ear_hum_value_volatly(talker_id, ear_idx, 0, 0, v);
+        pending_values.borrow_mut().insert(ear_idx, v);
+    });
+    scale.upcast()
+}
+
+pub fn expose(
+    parent: &gtk::ApplicationWindow,
+    session_presenter: &RSessionPresenter,
+    graph_presenter: &RGraphPresenter,
+    talker_id: Id,
+) {
+    let (talker_name, control_inputs) = {
+        let sp = session_presenter.borrow();
+
+        match (sp.find_talker(talker_id), sp.lv2_control_inputs(talker_id)) {
+            (Some(tkr), Some(control_inputs)) => (tkr.name(), control_inputs),
+            _ => return,
+        }
+    };
+    let talker = graph_presenter.borrow().get_talker(talker_id);
+    let pending_values: RPendingValues = Rc::new(RefCell::new(BTreeMap::new()));
+
+    let content = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(12)
+        .build();
+
+    // The ports are gathered by group, in the order of their first port
+    let mut groups: Vec<(Option<String>, gtk::Grid, i32)> = Vec::new();
+
+    for control_input in &control_inputs {
+        let ear = talker.ear(control_input.ear_idx);
+        let (min, max, _) = ear.hum_range(0);
+        let value = ear.talk_value_or_default(0, 0);
+
+        let widget = if control_input.toggled {
+            toggle_widget(graph_presenter, talker_id, control_input.ear_idx, value)
+        } else if !control_input.scale_points.is_empty() {
+            enumeration_widget(graph_presenter, talker_id, control_input.ear_idx, value, &control_input.scale_points)
+        } else {
+            scale_widget(graph_presenter, &pending_values, talker_id, control_input.ear_idx, (min, max, value), control_input)
+        };
+        widget.set_hexpand(true);
+
+        let group_idx = match groups.iter().position(|(g, _, _)| *g == control_input.group) {
+            Some(idx) => idx,
+            None => {
+                let grid = gtk::Grid::builder()
+                    .margin_start(6)
+                    .margin_end(6)
+                    .margin_top(6)
+                    .margin_bottom(6)
+                    .row_spacing(6)
+                    .column_spacing(12)
+                    .build();
+
+                match &control_input.group {
+                    Some(group) => {
+                        let frame = gtk::Frame::builder().label(group).child(&grid).build();
+                        content.append(&frame);
+                    }
+                    None => content.append(&grid),
+                }
+                groups.push((control_input.group.clone(), grid, 0));
+                groups.len() - 1
+            }
+        };
+        let (_, grid, row) = &mut groups[group_idx];
+
+        let label = gtk::Label::builder().label(ear.tag()).halign(gtk::Align::Start).build();
+        grid.attach(&label, 0, *row, 1, 1);
+        grid.attach(&widget, 1, *row, 1, 1);
+        *row += 1;
+    }
+
+    let scrolled_window = gtk::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .min_content_width(480)
+        .min_content_height(400)
+        .vexpand(true)
+        .child(&content)
+        .build();
+
+    let close_button = gtk::Button::builder().label("Close").hexpand(true).build();
+
+    let widget = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(20).margin_start(20).margin_end(20).margin_top(20).margin_bottom(20)
+        .build();
+    widget.append(&scrolled_window);
+    widget.append(&gtk::Separator::new(gtk::Orientation::Horizontal));
+    widget.append(&close_button);
+
+    let window = gtk::Window::builder()
+        .transient_for(parent)
+        .title(format!("{} parameters", talker_name))
+        .child(&widget)
+        .visible(true)
+        .build();
+
+    // The scales moves are committed when the panel is closed
+    let gp = graph_presenter.clone();
+    window.connect_close_request(clone!(#[strong] pending_values, move |_| {
+        let values = std::mem::take(&mut *pending_values.borrow_mut());
+
+        for (ear_idx, value) in values {
+            gp.borrow_mut().set_talker_ear_hum_value(talker_id, ear_idx, 0, 0, value);
+        }
+        glib::Propagation::Proceed
+    }));
+    close_button.connect_clicked(clone!(#[weak] window, move |_| window.close()));
+
+    window.present();
+}
@@ -0,0 +1,95 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gtk::{
+    glib::{self, clone}, prelude::{BoxExt, ButtonExt, EditableExt, GtkWindowExt, GridExt}, DropDown,
+};
+
+use talker::identifier::{Id, Identifiable};
+
+use crate::session_presenter::RSessionPresenter;
+
+fn presets_labels(presets: &Vec<(String, String)>) -> gtk::StringList {
+    let labels: Vec<&str> = presets.iter().map(|(_, label)| label.as_str()).collect();
+    gtk::StringList::new(&labels)
+}
+
+pub fn expose(parent: &gtk::ApplicationWindow, session_presenter: &RSessionPresenter, talker_id: Id) {
+    let talker_name = match session_presenter.borrow().find_talker(talker_id) {
+        Some(tkr) => tkr.name(),
+        None => return,
+    };
+    let presets = Rc::new(RefCell::new(session_presenter.borrow().lv2_presets(talker_id)));
+
+    let grid = gtk::Grid::builder()
+        .margin_start(6)
+        .margin_end(6)
+        .margin_top(6)
+        .margin_bottom(6)
+        .halign(gtk::Align::Center)
+        .valign(gtk::Align::Center)
+        .row_spacing(6)
+        .column_spacing(6)
+        .build();
+
+    // Presets list
+    let preset_label = gtk::Label::new(Some("Preset : "));
+    let preset_selector = DropDown::builder().model(&presets_labels(&presets.borrow())).build();
+    let apply_button = gtk::Button::builder().label("Apply").build();
+
+    grid.attach(&preset_label, 0, 0, 1, 1);
+    grid.attach(&preset_selector, 1, 0, 1, 1);
+    grid.attach(&apply_button, 2, 0, 1, 1);
+
+    // New user preset
+    let name_label = gtk::Label::new(Some("Save as : "));
+    let name_entry = gtk::Entry::builder().placeholder_text("Preset name").build();
+    let save_button = gtk::Button::builder().label("Save").build();
+
+    grid.attach(&name_label, 0, 1, 1, 1);
+    grid.attach(&name_entry, 1, 1, 1, 1);
+    grid.attach(&save_button, 2, 1, 1, 1);
+
+    let close_button = gtk::Button::builder().label("Close").hexpand(true).build();
+
+    let widget = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(20).margin_start(20).margin_end(20).margin_top(20).margin_bottom(20)
+        .build();
+    widget.append(&grid);
+    widget.append(&gtk::Separator::new(gtk::Orientation::Horizontal));
+    widget.append(&close_button);
+
+    let window = gtk::Window::builder()
+        .transient_for(parent)
+        .title(format!("{} presets", talker_name))
+        .child(&widget)
+        .modal(true)
+        .visible(true)
+        .build();
+
+    let rssp = session_presenter.clone();
+    apply_button.connect_clicked(clone!(#[strong] presets, #[weak] preset_selector, move |_| {
+        if let Some((uri, _)) = presets.borrow().get(preset_selector.selected() as usize) {
+            rssp.borrow_mut().set_lv2_preset(talker_id, uri);
+        }
+    }));
+
+    let ossp = session_presenter.clone();
+    save_button.connect_clicked(clone!(#[strong] presets, #[weak] preset_selector, #[weak] name_entry, move |_| {
+        let name = name_entry.text().to_string();
+
+        if ossp.borrow().save_lv2_preset(talker_id, &name) {
+            *presets.borrow_mut() = ossp.borrow().lv2_presets(talker_id);
+            preset_selector.set_model(Some(&presets_labels(&presets.borrow())));
+
+            if let Some(idx) = presets.borrow().iter().position(|(_, label)| *label == name) {
+                preset_selector.set_selected(idx as u32);
+            }
+            name_entry.set_text("");
+        }
+    }));
+    close_button.connect_clicked(clone!(#[weak] window, move |_| window.destroy()));
+
+    window.present();
+}
@@ -1,18 +1,27 @@
 use gio::prelude::FileExt;
 use gtk::{
-    glib::{self, clone}, prelude::{BoxExt, ButtonExt, EditableExt, EntryBufferExtManual, EntryExt, GridExt, GtkWindowExt, WidgetExt}, DropDown, FileDialog,
+    glib::{self, clone}, prelude::{BoxExt, ButtonExt, CheckButtonExt, EditableExt, EntryBufferExtManual, EntryExt, GridExt, GtkWindowExt, WidgetExt}, DropDown, FileDialog, MenuButton, Popover,
 };
 
 use session::channel;
 use talker::identifier::Id;
 
-use crate::{output_presenter::{self, OutputPresenter}, session_presenter::RSessionPresenter};
+use crate::{mixer_presenter, output_presenter::{self, OutputPresenter}, session_presenter::RSessionPresenter};
 
 const ACTION_COLUMN: i32 = 0;
 const CODEC_COLUMN: i32 = 1;
 const SAMPLE_RATE_COLUMN: i32 = 2;
 const CHANNEL_LAYOUT_COLUMN: i32 = 3;
-const FILEPATH_COLUMN: i32 = 4;
+const SAMPLE_FORMAT_COLUMN: i32 = 4;
+const DITHER_COLUMN: i32 = 5;
+const FILEPATH_COLUMN: i32 = 6;
+const STEMS_COLUMN: i32 = 7;
+const TAGS_COLUMN: i32 = 8;
+
+// The session tag is set from the session filename
+const EDITABLE_TAGS: [(&str, &str); 4] = [("title", "Title"), ("artist", "Artist"), ("album", "Album"), ("comment", "Comment")];
+
+const MAX_BUSES: f64 = 16.;
 
 
 fn add_output_selectors(window: &gtk::Window,
@@ -75,6 +84,31 @@ fn add_output_selectors(window: &gtk::Window,
     outputs_box.attach(&channel_layout_selector, CHANNEL_LAYOUT_COLUMN, row, 1, 1);
 
     
+    // Sample format selector
+    let sample_format_selector = DropDown::from_strings(&output_presenter::SAMPLE_FORMATS_LABELS);
+    sample_format_selector.set_selected(output_presenter.sample_format().index() as u32);
+    sample_format_selector.set_can_focus(false);
+
+    sample_format_selector.connect_selected_item_notify(clone!(#[weak] session_presenter, move |i| {
+        session_presenter.borrow_mut().set_mixer_output_sample_format(mixer_id, output_id, i.selected() as usize);
+    }));
+
+    outputs_box.attach(&sample_format_selector, SAMPLE_FORMAT_COLUMN, row, 1, 1);
+
+    
+    // Dither selector
+    let dither_selector = DropDown::from_strings(&output_presenter::DITHERS_LABELS);
+    dither_selector.set_selected(output_presenter.dither().index() as u32);
+    dither_selector.set_can_focus(false);
+    dither_selector.set_tooltip_text(Some("Dither applied when reducing to an integer sample format"));
+
+    dither_selector.connect_selected_item_notify(clone!(#[weak] session_presenter, move |i| {
+        session_presenter.borrow_mut().set_mixer_output_dither(mixer_id, output_id, i.selected() as usize);
+    }));
+
+    outputs_box.attach(&dither_selector, DITHER_COLUMN, row, 1, 1);
+
+    
     // File path entry
     let filepath_entry = gtk::Entry::builder()
         .primary_icon_name("document-open-symbolic").primary_icon_sensitive(true).primary_icon_activatable(true)
@@ -105,6 +139,56 @@ fn add_output_selectors(window: &gtk::Window,
 
     outputs_box.attach(&filepath_entry, FILEPATH_COLUMN, row, 1, 1);
 
+
+    // Stems check
+    let stems_check = gtk::CheckButton::builder()
+        .label("Stems")
+        .active(output_presenter.stems())
+        .tooltip_text("Write each track to its own file")
+        .can_focus(false)
+        .build();
+
+    stems_check.connect_toggled(clone!(#[weak] session_presenter, move |c| {
+        session_presenter.borrow_mut().set_mixer_output_stems(mixer_id, output_id, c.is_active());
+    }));
+
+    outputs_box.attach(&stems_check, STEMS_COLUMN, row, 1, 1);
+
+
+    // Tags editor
+    let tags_grid = gtk::Grid::builder()
+        .margin_start(6).margin_end(6).margin_top(6).margin_bottom(6)
+        .row_spacing(6).column_spacing(6)
+        .build();
+
+    let tags = output_presenter.tags().entries();
+
+    for (tag_row, (key, label)) in EDITABLE_TAGS.into_iter().enumerate() {
+        let tag_label = gtk::Label::builder().label(label).halign(gtk::Align::End).build();
+        tags_grid.attach(&tag_label, 0, tag_row as i32, 1, 1);
+
+        let tag_entry = gtk::Entry::builder().width_request(240).build();
+
+        if let Some((_, value)) = tags.iter().find(|(k, _)| *k == key) {
+            tag_entry.buffer().set_text(*value);
+        }
+
+        tag_entry.connect_changed(clone!(#[weak] session_presenter, move |e| {
+            session_presenter.borrow_mut().set_mixer_output_tag(mixer_id, output_id, key, e.buffer().text().as_str());
+        }));
+        tags_grid.attach(&tag_entry, 1, tag_row as i32, 1, 1);
+    }
+
+    let tags_popover = Popover::builder().child(&tags_grid).build();
+    let tags_button = MenuButton::builder()
+        .label("Tags")
+        .popover(&tags_popover)
+        .tooltip_text("Metadata written in the file")
+        .can_focus(false)
+        .build();
+
+    outputs_box.attach(&tags_button, TAGS_COLUMN, row, 1, 1);
+
     row + 1
 }
 
@@ -113,6 +197,10 @@ fn update_outputs_view(window: &gtk::Window,
         mixer_id: Id,
         outputs_box: &gtk::Grid,
     ) {
+    outputs_box.remove_column(8);
+    outputs_box.remove_column(7);
+    outputs_box.remove_column(6);
+    outputs_box.remove_column(5);
     outputs_box.remove_column(4);
     outputs_box.remove_column(3);
     outputs_box.remove_column(2);
@@ -168,8 +256,70 @@ pub fn expose(app: &gtk::Application, session_presenter: &RSessionPresenter,) {
 
         update_outputs_view(&window, session_presenter, mixer_id, &outputs_box);
 
+        // Aux buses count
+        let buses_box = gtk::Box::builder().orientation(gtk::Orientation::Horizontal)
+            .spacing(6).margin_start(6).margin_end(6).margin_top(6)
+            .build();
+
+        let buses_label = gtk::Label::new(Some("Aux buses"));
+        buses_box.append(&buses_label);
+
+        let buses_spin = gtk::SpinButton::with_range(0., MAX_BUSES, 1.);
+        buses_spin.set_value(mixer.configuration().buses as f64);
+        buses_spin.set_tooltip_text(Some("Number of send buses fed by the tracks"));
+
+        buses_spin.connect_value_changed(clone!(#[weak] session_presenter, move |s| {
+            session_presenter.borrow_mut().set_mixer_buses(mixer_id, s.value_as_int() as usize);
+        }));
+        buses_box.append(&buses_spin);
+
+        // Summing mode
+        let summing_label = gtk::Label::new(Some("Summing"));
+        buses_box.append(&summing_label);
+
+        let summing_selector = DropDown::from_strings(&mixer_presenter::SUMMINGS_LABELS);
+        summing_selector.set_selected(mixer.configuration().summing.index() as u32);
+        summing_selector.set_can_focus(false);
+        summing_selector.set_tooltip_text(Some("Tracks sum coefficient"));
+
+        summing_selector.connect_selected_item_notify(clone!(#[weak] session_presenter, move |i| {
+            session_presenter.borrow_mut().set_mixer_summing(mixer_id, i.selected() as usize);
+        }));
+        buses_box.append(&summing_selector);
+
+        // Master soft clip
+        let clipping_label = gtk::Label::new(Some("Soft clip"));
+        buses_box.append(&clipping_label);
+
+        let clipping_selector = DropDown::from_strings(&mixer_presenter::CLIPPINGS_LABELS);
+        clipping_selector.set_selected(mixer.configuration().clipping.index() as u32);
+        clipping_selector.set_can_focus(false);
+
+        clipping_selector.connect_selected_item_notify(clone!(#[weak] session_presenter, move |i| {
+            session_presenter.borrow_mut().set_mixer_clipping(mixer_id, i.selected() as usize);
+        }));
+        buses_box.append(&clipping_selector);
+
+        // Tracks panner
+        let panning_label = gtk::Label::new(Some("Panner"));
+        buses_box.append(&panning_label);
+
+        let panning_selector = DropDown::from_strings(&mixer_presenter::PANNINGS_LABELS);
+        panning_selector.set_selected(mixer.configuration().panning.index() as u32);
+        panning_selector.set_can_focus(false);
+        panning_selector.set_tooltip_text(Some("Azimuth, elevation and width hums on each track"));
+
+        panning_selector.connect_selected_item_notify(clone!(#[weak] session_presenter, move |i| {
+            session_presenter.borrow_mut().set_mixer_panning(mixer_id, i.selected() as usize);
+        }));
+        buses_box.append(&panning_selector);
+
+        let mixer_box = gtk::Box::builder().orientation(gtk::Orientation::Vertical).build();
+        mixer_box.append(&buses_box);
+        mixer_box.append(&outputs_box);
+
         let outputs_label = format!("{} outputs", mixer.name());
-        let outputs_frame = gtk::Frame::builder().label(outputs_label).child(&outputs_box).visible(true).build();
+        let outputs_frame = gtk::Frame::builder().label(outputs_label).child(&mixer_box).visible(true).build();
         widget.append(&outputs_frame);
     }
 
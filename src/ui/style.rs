@@ -53,6 +53,9 @@ const VALUE_COLOR: Color = (0.5, 0.5, 1.); // cyan
 const SUP_COLOR: Color = (HFF, H00, H00); // red
 const ADD_COLOR: Color = (0., 1., 0.); // green
 const SWITCH_COLOR: Color = (0.9, 0.87, 0.77); //(0.7, 0.4, 0.3);
+const METER_COLOR: Color = (0., 0.8, 0.3);
+const METER_PEAK_COLOR: Color = (1., 0.8, 0.);
+const METER_CLIP_COLOR: Color = (HFF, H00, H00);
 
 pub fn background(cc: &Context) {
     set_color(cc, BACKGROUND_COLOR);
@@ -140,6 +143,17 @@ pub fn switch(cc: &Context) {
     set_color(cc, SWITCH_COLOR);
 }
 
+pub fn meter(cc: &Context) {
+    set_color(cc, METER_COLOR);
+}
+pub fn meter_peak(cc: &Context) {
+    set_color(cc, METER_PEAK_COLOR);
+    cc.set_line_width(1.);
+}
+pub fn meter_clip(cc: &Context) {
+    set_color(cc, METER_CLIP_COLOR);
+}
+
 pub fn connection(cc: &Context, color: &Color) {
     set_color(cc, *color);
     cc.set_line_width(2.);
@@ -14,6 +14,7 @@ pub mod identifier;
 pub mod lv2_handler;
 pub mod talker;
 pub mod talker_handler;
+pub mod transport;
 pub mod voice;
 
 pub use identifier::Identifier;
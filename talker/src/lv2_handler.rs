@@ -12,6 +12,18 @@ use audio_format;
 const MIN_BLOCK_SIZE: usize = audio_format::MIN_CHUNK_SIZE;
 const MAX_BLOCK_SIZE: usize = audio_format::DEFAULT_CHUNK_SIZE;
 
+const LV2_PORT_URI: &str = "http://lv2plug.in/ns/lv2core#port";
+const LV2_SYMBOL_URI: &str = "http://lv2plug.in/ns/lv2core#symbol";
+const PSET_PRESET_URI: &str = "http://lv2plug.in/ns/ext/presets#Preset";
+const PSET_VALUE_URI: &str = "http://lv2plug.in/ns/ext/presets#value";
+const RDFS_LABEL_URI: &str = "http://www.w3.org/2000/01/rdf-schema#label";
+const LV2_NAME_URI: &str = "http://lv2plug.in/ns/lv2core#name";
+const LV2_TOGGLED_URI: &str = "http://lv2plug.in/ns/lv2core#toggled";
+const LV2_INTEGER_URI: &str = "http://lv2plug.in/ns/lv2core#integer";
+const LV2_ENUMERATION_URI: &str = "http://lv2plug.in/ns/lv2core#enumeration";
+const PPROPS_LOGARITHMIC_URI: &str = "http://lv2plug.in/ns/ext/port-props#logarithmic";
+const PG_GROUP_URI: &str = "http://lv2plug.in/ns/ext/port-groups#group";
+
 enum WorkerOrder {
     Run,
 }
@@ -116,6 +128,183 @@ pub fn get_ears_indexes(plugin_uri: &str) -> Result<Vec<usize>, failure::Error>
     })
 }
 
+// Symbol and ear index of the control input ports
+pub fn get_control_inputs_ears(plugin_uri: &str) -> Result<Vec<(String, usize)>, failure::Error> {
+    visit(|lv2_handler| {
+        match lv2_handler.world.plugin_by_uri(plugin_uri) {
+            Some(plugin) => {
+                let mut control_inputs_ears = Vec::new();
+                let mut ear_idx = 0;
+
+                for port in plugin.ports() {
+                    match port.port_type {
+                        PortType::ControlInput => {
+                            control_inputs_ears.push((port.symbol, ear_idx));
+                            ear_idx += 1;
+                        },
+                        PortType::AudioInput | PortType::AtomSequenceInput | PortType::CVInput => ear_idx += 1,
+                        _ => (),
+                    }
+                }
+                Ok(control_inputs_ears)
+            }
+            None => Err(failure::err_msg(format!("LV2 plugin {} not found.", plugin_uri))),
+        }
+    })
+}
+
+// Control input port properties useful to edit its value
+pub struct ControlInput {
+    pub ear_idx: usize,
+    pub toggled: bool,
+    pub integer: bool,
+    pub logarithmic: bool,
+    // Value and label of the enumeration
+    pub scale_points: Vec<(f32, String)>,
+    pub group: Option<String>,
+}
+
+pub fn get_control_inputs(plugin_uri: &str) -> Result<Vec<ControlInput>, failure::Error> {
+    visit(|lv2_handler| {
+        match lv2_handler.world.plugin_by_uri(plugin_uri) {
+            Some(plugin) => {
+                let world = lv2_handler.world.raw();
+                let toggled = world.new_uri(LV2_TOGGLED_URI);
+                let integer = world.new_uri(LV2_INTEGER_URI);
+                let enumeration = world.new_uri(LV2_ENUMERATION_URI);
+                let logarithmic = world.new_uri(PPROPS_LOGARITHMIC_URI);
+                let group_predicate = world.new_uri(PG_GROUP_URI);
+                let name_predicate = world.new_uri(LV2_NAME_URI);
+                let label_predicate = world.new_uri(RDFS_LABEL_URI);
+
+                let mut control_inputs = Vec::new();
+                let mut ear_idx = 0;
+
+                for port in plugin.ports() {
+                    match port.port_type {
+                        PortType::ControlInput => {
+                            if let Some(lilv_port) = plugin.raw().port_by_index(port.index.0) {
+                                let mut scale_points = Vec::new();
+
+                                if lilv_port.has_property(&enumeration) {
+                                    for scale_point in lilv_port.scale_points() {
+                                        let ovalue = scale_point.value().as_float();
+                                        let olabel = scale_point.label().as_str().map(|s| s.to_string());
+
+                                        if let (Some(value), Some(label)) = (ovalue, olabel) {
+                                            scale_points.push((value, label));
+                                        }
+                                    }
+                                    scale_points.sort_by(|a, b| a.0.total_cmp(&b.0));
+                                }
+
+                                let group = lilv_port.get(&group_predicate).and_then(|group| {
+                                    world.get(Some(&group), Some(&name_predicate), None)
+                                        .or_else(|| world.get(Some(&group), Some(&label_predicate), None))
+                                        .and_then(|node| node.as_str().map(|s| s.to_string()))
+                                });
+
+                                control_inputs.push(ControlInput {
+                                    ear_idx,
+                                    toggled: lilv_port.has_property(&toggled),
+                                    integer: lilv_port.has_property(&integer),
+                                    logarithmic: lilv_port.has_property(&logarithmic),
+                                    scale_points,
+                                    group,
+                                });
+                            }
+                            ear_idx += 1;
+                        },
+                        PortType::AudioInput | PortType::AtomSequenceInput | PortType::CVInput => ear_idx += 1,
+                        _ => (),
+                    }
+                }
+                Ok(control_inputs)
+            }
+            None => Err(failure::err_msg(format!("LV2 plugin {} not found.", plugin_uri))),
+        }
+    })
+}
+
+// URI and label of the presets applying to the plugin
+pub fn get_presets(plugin_uri: &str) -> Result<Vec<(String, String)>, failure::Error> {
+    visit(|lv2_handler| {
+        match lv2_handler.world.plugin_by_uri(plugin_uri) {
+            Some(plugin) => {
+                let world = lv2_handler.world.raw();
+                let preset_class = world.new_uri(PSET_PRESET_URI);
+                let label_predicate = world.new_uri(RDFS_LABEL_URI);
+                let mut presets = Vec::new();
+
+                if let Some(nodes) = plugin.raw().related(Some(&preset_class)) {
+                    for preset in nodes {
+                        let _ = world.load_resource(&preset);
+
+                        if let Some(uri) = preset.as_uri() {
+                            let label = world.get(Some(&preset), Some(&label_predicate), None)
+                                .and_then(|node| node.as_str().map(|s| s.to_string()))
+                                .unwrap_or(uri.to_string());
+
+                            presets.push((uri.to_string(), label));
+                        }
+                    }
+                }
+                presets.sort_by(|a, b| a.1.cmp(&b.1));
+                Ok(presets)
+            }
+            None => Err(failure::err_msg(format!("LV2 plugin {} not found.", plugin_uri))),
+        }
+    })
+}
+
+// Ports symbol and value of the preset
+pub fn get_preset_port_values(preset_uri: &str) -> Result<Vec<(String, f32)>, failure::Error> {
+    visit(|lv2_handler| {
+        let world = lv2_handler.world.raw();
+        let preset = world.new_uri(preset_uri);
+        let port_predicate = world.new_uri(LV2_PORT_URI);
+        let symbol_predicate = world.new_uri(LV2_SYMBOL_URI);
+        let value_predicate = world.new_uri(PSET_VALUE_URI);
+
+        world.load_resource(&preset)
+            .map_err(|e| failure::err_msg(format!("LV2 preset {} loading failed : {:?}", preset_uri, e)))?;
+
+        let mut port_values = Vec::new();
+
+        for port in world.find_nodes(Some(&preset), Some(&port_predicate), None) {
+            let osymbol = world.get(Some(&port), Some(&symbol_predicate), None)
+                .and_then(|node| node.as_str().map(|s| s.to_string()));
+            let ovalue = world.get(Some(&port), Some(&value_predicate), None)
+                .and_then(|node| node.as_float());
+
+            if let (Some(symbol), Some(value)) = (osymbol, ovalue) {
+                port_values.push((symbol, value));
+            }
+        }
+        Ok(port_values)
+    })
+}
+
+// The bundle URI ends with a slash
+pub fn load_bundle(bundle_uri: &str) -> Result<(), failure::Error> {
+    visit(|lv2_handler| {
+        let world = lv2_handler.world.raw();
+
+        world.load_bundle(&world.new_uri(bundle_uri));
+        Ok(())
+    })
+}
+
+// Data loaded from the bundle are removed from the world
+pub fn unload_bundle(bundle_uri: &str) -> Result<(), failure::Error> {
+    visit(|lv2_handler| {
+        let world = lv2_handler.world.raw();
+
+        let _ = world.unload_bundle(&world.new_uri(bundle_uri));
+        Ok(())
+    })
+}
+
 pub fn get_port_symbol_indexes(plugin_uri: &str) -> Result<HashMap<String, u32>, failure::Error> {
     visit(|lv2_handler| {
         match lv2_handler.world.plugin_by_uri(plugin_uri) {
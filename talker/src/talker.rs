@@ -1,4 +1,5 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::rc::Rc;
 
 extern crate failure;
@@ -231,7 +232,13 @@ impl Identifiable for TalkerBase {
 pub trait Talker {
     fn activate(&mut self) {}
     fn deactivate(&mut self) {}
-    
+
+    // Called when the playback jumps back in time (e.g. loop wrap)
+    fn reset(&mut self) {
+        self.deactivate();
+        self.activate();
+    }
+
 
     fn data_language(&self) -> Option<Language> {
         None
@@ -279,6 +286,11 @@ pub trait Talker {
         Ok(Vec::new())
     }
 
+    // Delay in samples of the voices relative to the ears
+    fn latency(&self, _base: &TalkerBase) -> usize {
+        0
+    }
+
     fn talk(&mut self, _base: &TalkerBase, _port: usize, _tick: i64, _len: usize) -> usize {
         0
     }
@@ -311,6 +323,7 @@ impl Talker for NilTalker {}
 pub struct TalkerCab {
     base: TalkerBase,
     core: RefCell<Box<dyn Talker>>,
+    visiting: Cell<bool>,
 }
 
 pub type RTalker = Rc<TalkerCab>;
@@ -328,6 +341,7 @@ impl TalkerCab {
         Self {
             base,
             core: RefCell::new(core),
+            visiting: Cell::new(false),
         }
     }
     pub fn new_ref(ctalker: CTalker) -> RTalker {
@@ -356,12 +370,20 @@ impl TalkerCab {
     }
 
     pub fn depends_of(&self, id: Id) -> bool {
+        // The mixers buses allow loops in the graph so a talker already visited is skipped
+        if self.visiting.replace(true) {
+            return false;
+        }
+        let mut res = false;
+
         for ear in &self.base.ears {
             if ear.depends_of(id) {
-                return true;
+                res = true;
+                break;
             }
         }
-        false
+        self.visiting.set(false);
+        res
     }
 
     pub fn is_hidden(&self) -> bool {
@@ -621,6 +643,40 @@ impl TalkerCab {
     pub fn deactivate(&self) {
         self.core.borrow_mut().deactivate()
     }
+    pub fn reset(&self) {
+        self.core.borrow_mut().reset()
+    }
+
+    pub fn latency(&self) -> usize {
+        self.core.borrow().latency(&self.base)
+    }
+
+    // Talker latency added to the greatest latency of the talkers it listens to.
+    // The latencies already computed are kept by talker id for the shared paths
+    pub fn path_latency(&self, latencies: &mut HashMap<Id, usize>) -> usize {
+        if let Some(latency) = latencies.get(&self.id()) {
+            return *latency;
+        }
+        if self.visiting.replace(true) {
+            return 0;
+        }
+        let mut ears_latency = 0;
+
+        for ear in &self.base.ears {
+            for set in ear.sets().iter() {
+                for hum in set.hums() {
+                    for talk in hum.talks() {
+                        ears_latency = ears_latency.max(talk.talker().path_latency(latencies));
+                    }
+                }
+            }
+        }
+        self.visiting.set(false);
+
+        let latency = self.latency() + ears_latency;
+        latencies.insert(self.id(), latency);
+        latency
+    }
 
     pub fn talk(&self, port: usize, tick: i64, len: usize) -> usize {
         let ln = self.core.borrow_mut().talk(&self.base, port, tick, len);
@@ -0,0 +1,42 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use audio_format::AudioFormat;
+
+pub const DEFAULT_TEMPO: f32 = 120.;
+pub const MIN_TEMPO: f32 = 20.;
+pub const MAX_TEMPO: f32 = 300.;
+pub const BEATS_PER_BAR: f32 = 4.;
+pub const BEAT_UNIT: i32 = 4;
+
+// f32 values stored as bits. 0x42F00000 is 120.0
+static TEMPO: AtomicU32 = AtomicU32::new(0x42F00000);
+static SPEED: AtomicU32 = AtomicU32::new(0);
+
+pub struct Transport {}
+
+impl Transport {
+    // Beats per minute
+    pub fn tempo() -> f32 {
+        f32::from_bits(TEMPO.load(Ordering::Relaxed))
+    }
+    pub fn set_tempo(tempo: f32) {
+        TEMPO.store(tempo.to_bits(), Ordering::Relaxed);
+    }
+
+    // 1 when playing, 0 when paused or stopped
+    pub fn speed() -> f32 {
+        f32::from_bits(SPEED.load(Ordering::Relaxed))
+    }
+    pub fn set_speed(speed: f32) {
+        SPEED.store(speed.to_bits(), Ordering::Relaxed);
+    }
+
+    // Bar index and beat in the bar at the tick
+    pub fn bar_beat(tick: i64, tempo: f32) -> (i64, f32) {
+        let beats = tick as f64 * tempo as f64 / (60. * AudioFormat::sample_rate() as f64);
+        let bar = (beats / BEATS_PER_BAR as f64).floor();
+
+        (bar as i64, (beats - bar * BEATS_PER_BAR as f64) as f32)
+    }
+}
+